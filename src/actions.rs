@@ -0,0 +1,600 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::gamepad;
+use crate::gamepad::MyGamepad;
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<RebindAction>()
+            .init_resource::<SettingsPath>()
+            .init_resource::<ActionMap>()
+            .init_resource::<InputDevice>()
+            .add_startup_system(load_action_map)
+            .add_system(track_input_device)
+            .add_systems((start_rebind, capture_rebind, save_action_map).chain());
+    }
+}
+
+/// Everything the player can do, independent of the device it is done with.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Move,
+    Aim,
+    Interact,
+    CycleTarget,
+    Attack,
+    Inventory,
+    UseItem,
+    ZoomIn,
+    ZoomOut,
+    /// Held to zoom with the mouse wheel instead of scrolling the log.
+    ZoomModifier,
+    OpenLog,
+    ScrollLogUp,
+    ScrollLogDown,
+    ExpandLog,
+    Pause,
+    QuickSave,
+    QuickLoad,
+    DebugLog,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    MenuConfirm,
+    MenuBack,
+    MenuOverwrite,
+    MenuDelete,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+    /// Four keys read as a direction, like WASD.
+    KeyAxis { up: KeyCode, down: KeyCode, left: KeyCode, right: KeyCode },
+    Stick(Stick),
+    /// Pointing with the mouse cursor, only meaningful for [Action::Aim].
+    Cursor,
+}
+
+/// Stick deflection that counts as using the gamepad.
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// The device the player last pressed something on, which prompts show buttons for.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad,
+}
+
+/// How to show the button for an action: a key name, or a cell of the gamepad glyph sheet.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ButtonPrompt {
+    Text(String),
+    Glyph(usize),
+}
+
+/// Row-major cells of `ui/gamepad_buttons.png`.
+pub fn gamepad_glyph(button: GamepadButtonType) -> Option<usize> {
+    use GamepadButtonType::*;
+    let glyph = match button {
+        South => 0,
+        East => 1,
+        West => 2,
+        North => 3,
+        LeftTrigger => 4,
+        RightTrigger => 5,
+        LeftTrigger2 => 6,
+        RightTrigger2 => 7,
+        Select => 8,
+        Start => 9,
+        LeftThumb => 10,
+        RightThumb => 11,
+        DPadUp => 12,
+        DPadDown => 13,
+        DPadLeft => 14,
+        DPadRight => 15,
+        _ => return None,
+    };
+    Some(glyph)
+}
+
+fn key_name(key: KeyCode) -> String {
+    let name = match key {
+        KeyCode::Return => "Enter",
+        KeyCode::Escape => "Esc",
+        KeyCode::Delete => "Del",
+        KeyCode::Back => "Backspace",
+        KeyCode::Equals => "=",
+        KeyCode::Minus => "-",
+        KeyCode::Key0 => "0",
+        KeyCode::Key1 => "1",
+        KeyCode::Key2 => "2",
+        KeyCode::Key3 => "3",
+        KeyCode::Key4 => "4",
+        KeyCode::Key5 => "5",
+        KeyCode::Key6 => "6",
+        KeyCode::Key7 => "7",
+        KeyCode::Key8 => "8",
+        KeyCode::Key9 => "9",
+        _ => return format!("{key:?}"),
+    };
+    name.to_string()
+}
+
+impl Binding {
+    /// `None` for bindings of the other device, and for axes which have no single button to show.
+    pub fn prompt(&self, device: InputDevice) -> Option<ButtonPrompt> {
+        match (*self, device) {
+            (Binding::Key(key), InputDevice::KeyboardMouse) => Some(ButtonPrompt::Text(format!("[{}]", key_name(key)))),
+            (Binding::Mouse(button), InputDevice::KeyboardMouse) => {
+                let name = match button {
+                    MouseButton::Left => "LMB".to_string(),
+                    MouseButton::Right => "RMB".to_string(),
+                    MouseButton::Middle => "MMB".to_string(),
+                    MouseButton::Other(index) => format!("Mouse{index}"),
+                };
+                Some(ButtonPrompt::Text(format!("[{name}]")))
+            }
+            (Binding::Gamepad(button), InputDevice::Gamepad) => Some(match gamepad_glyph(button) {
+                Some(glyph) => ButtonPrompt::Glyph(glyph),
+                None => ButtonPrompt::Text(format!("({button:?})")),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Where the player's input settings are persisted.
+#[derive(Resource)]
+pub struct SettingsPath(pub PathBuf);
+
+impl Default for SettingsPath {
+    fn default() -> Self {
+        SettingsPath(PathBuf::from("settings/input.json"))
+    }
+}
+
+impl SettingsPath {
+    /// A settings file of its own for a test app, so rebinding in one never leaks into another
+    /// or into the developer's real settings.
+    #[cfg(test)]
+    pub fn temporary() -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("onyx_settings_{}_{id}", std::process::id()));
+        SettingsPath(dir.join("input.json"))
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ActionMap {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        use Binding::*;
+        let bindings = BTreeMap::from([
+            (Action::Move, vec![
+                KeyAxis { up: KeyCode::W, down: KeyCode::S, left: KeyCode::A, right: KeyCode::D },
+                Stick(self::Stick::Left),
+            ]),
+            (Action::Aim, vec![Cursor, Stick(self::Stick::Right)]),
+            (Action::Interact, vec![Key(KeyCode::E), Gamepad(GamepadButtonType::South)]),
+            (Action::CycleTarget, vec![Key(KeyCode::Q), Gamepad(GamepadButtonType::RightTrigger)]),
+            (Action::Attack, vec![Mouse(MouseButton::Left), Gamepad(GamepadButtonType::RightTrigger2)]),
+            (Action::Inventory, vec![Key(KeyCode::I), Gamepad(GamepadButtonType::North)]),
+            (Action::UseItem, vec![Key(KeyCode::F), Mouse(MouseButton::Right), Gamepad(GamepadButtonType::West)]),
+            (Action::ZoomIn, vec![Key(KeyCode::Equals), Gamepad(GamepadButtonType::LeftTrigger)]),
+            (Action::ZoomOut, vec![Key(KeyCode::Minus), Gamepad(GamepadButtonType::LeftTrigger2)]),
+            (Action::ZoomModifier, vec![Key(KeyCode::LControl), Key(KeyCode::RControl)]),
+            (Action::OpenLog, vec![Key(KeyCode::Tab), Gamepad(GamepadButtonType::Select)]),
+            (Action::ScrollLogUp, vec![Key(KeyCode::PageUp), Gamepad(GamepadButtonType::DPadUp)]),
+            (Action::ScrollLogDown, vec![Key(KeyCode::PageDown), Gamepad(GamepadButtonType::DPadDown)]),
+            (Action::ExpandLog, vec![Key(KeyCode::L), Gamepad(GamepadButtonType::LeftThumb)]),
+            (Action::Pause, vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::Start)]),
+            (Action::QuickSave, vec![Key(KeyCode::F5)]),
+            (Action::QuickLoad, vec![Key(KeyCode::F9)]),
+            (Action::MenuUp, vec![Key(KeyCode::Up), Key(KeyCode::W), Gamepad(GamepadButtonType::DPadUp)]),
+            (Action::MenuDown, vec![Key(KeyCode::Down), Key(KeyCode::S), Gamepad(GamepadButtonType::DPadDown)]),
+            (Action::MenuLeft, vec![Key(KeyCode::Left), Key(KeyCode::A), Gamepad(GamepadButtonType::DPadLeft)]),
+            (Action::MenuRight, vec![Key(KeyCode::Right), Key(KeyCode::D), Gamepad(GamepadButtonType::DPadRight)]),
+            (Action::MenuConfirm, vec![Key(KeyCode::Return), Key(KeyCode::Space), Gamepad(GamepadButtonType::South)]),
+            (Action::MenuBack, vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::East)]),
+            (Action::MenuOverwrite, vec![Key(KeyCode::O), Gamepad(GamepadButtonType::West)]),
+            (Action::MenuDelete, vec![Key(KeyCode::Delete), Gamepad(GamepadButtonType::North)]),
+        ]);
+        #[cfg(feature = "debug-mode")]
+        let bindings = bindings.into_iter()
+            .chain([(Action::DebugLog, vec![Key(KeyCode::Space)])])
+            .collect();
+        ActionMap { bindings }
+    }
+}
+
+impl ActionMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_bound(&self, action: Action, binding: &Binding) -> bool {
+        self.bindings(action).contains(binding)
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: Action, binding: &Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|b| b != binding);
+        }
+    }
+
+    /// Swaps `old` for `new` keeping its position, or adds `new` if `old` isn't bound.
+    pub fn rebind(&mut self, action: Action, old: &Binding, new: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|b| *b != new);
+        match bindings.iter().position(|b| b == old) {
+            Some(index) => bindings[index] = new,
+            None => bindings.push(new),
+        }
+    }
+
+    /// The first button of `action` on `device`, or on the other device if it has none there.
+    pub fn prompt(&self, action: Action, device: InputDevice) -> Option<ButtonPrompt> {
+        let other = match device {
+            InputDevice::KeyboardMouse => InputDevice::Gamepad,
+            InputDevice::Gamepad => InputDevice::KeyboardMouse,
+        };
+        [device, other].into_iter()
+            .find_map(|device| self.bindings(action).iter().find_map(|binding| binding.prompt(device)))
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        let defaults = ActionMap::default().bindings(action).to_vec();
+        self.bindings.insert(action, defaults);
+    }
+
+    /// Reads a settings file, falling back to defaults for actions it doesn't mention.
+    pub fn load(path: &Path) -> io::Result<ActionMap> {
+        let contents = fs::read(path)?;
+        let mut map: ActionMap = serde_json::from_slice(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        for (action, bindings) in ActionMap::default().bindings {
+            map.bindings.entry(action).or_insert(bindings);
+        }
+        Ok(map)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_vec_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, path)
+    }
+}
+
+/// Asks for the next button pressed on any device to be bound to `action`, replacing `replace`.
+pub struct RebindAction {
+    pub action: Action,
+    pub replace: Option<Binding>,
+}
+
+/// Present while waiting for the button of a [RebindAction]; actions read as idle meanwhile.
+#[derive(Resource)]
+pub struct PendingRebind {
+    pub action: Action,
+    pub replace: Option<Binding>,
+}
+
+/// Action state of the player at this machine, read through the [ActionMap].
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    map: Res<'w, ActionMap>,
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    mouse_input: Res<'w, Input<MouseButton>>,
+    gamepad_input: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    my_gamepad: Option<Res<'w, MyGamepad>>,
+    pending_rebind: Option<Res<'w, PendingRebind>>,
+}
+
+impl ActionInput<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pending_rebind.is_none()
+            && self.map.bindings(action).iter().any(|b| self.binding_pressed(b, false))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pending_rebind.is_none()
+            && self.map.bindings(action).iter().any(|b| self.binding_pressed(b, true))
+    }
+
+    /// Sum of every directional binding of `action`.
+    pub fn axis(&self, action: Action) -> Vec2 {
+        if self.pending_rebind.is_some() { return Vec2::ZERO; }
+        self.map.bindings(action).iter().map(|b| self.binding_axis(b)).sum()
+    }
+
+    fn binding_pressed(&self, binding: &Binding, just: bool) -> bool {
+        match *binding {
+            Binding::Key(key) => match just {
+                true => self.keyboard_input.just_pressed(key),
+                false => self.keyboard_input.pressed(key),
+            },
+            Binding::Mouse(button) => match just {
+                true => self.mouse_input.just_pressed(button),
+                false => self.mouse_input.pressed(button),
+            },
+            Binding::Gamepad(button_type) => {
+                let Some(gp) = self.my_gamepad.as_ref() else { return false; };
+                let button = GamepadButton { gamepad: gp.0, button_type };
+                match just {
+                    true => self.gamepad_input.just_pressed(button),
+                    false => self.gamepad_input.pressed(button),
+                }
+            }
+            Binding::KeyAxis { .. } | Binding::Stick(_) | Binding::Cursor => false,
+        }
+    }
+
+    fn binding_axis(&self, binding: &Binding) -> Vec2 {
+        match *binding {
+            Binding::KeyAxis { up, down, left, right } => {
+                let key = |key| if self.keyboard_input.pressed(key) { 1. } else { 0. };
+                Vec2::new(key(right) - key(left), key(up) - key(down))
+            }
+            Binding::Stick(stick) => {
+                let Some(gp) = self.my_gamepad.as_ref() else { return Vec2::ZERO; };
+                match stick {
+                    Stick::Left => gamepad::get_left_axis_direction(&self.axes, gp.0),
+                    Stick::Right => gamepad::get_right_axis_direction(&self.axes, gp.0),
+                }
+            }
+            _ => Vec2::ZERO,
+        }
+    }
+}
+
+fn load_action_map(path: Res<SettingsPath>, mut map: ResMut<ActionMap>) {
+    match ActionMap::load(&path.0) {
+        Ok(loaded) => *map = loaded,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => warn!("Failed to load input settings, using defaults: {error}"),
+    }
+}
+
+fn track_input_device(
+    mut device: ResMut<InputDevice>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    my_gamepad: Option<Res<MyGamepad>>,
+) {
+    let gamepad_used = my_gamepad.map_or(false, |gp| {
+        gamepad_input.get_just_pressed().any(|button| button.gamepad == gp.0)
+            || gamepad::get_left_axis_direction(&axes, gp.0).length() > STICK_THRESHOLD
+            || gamepad::get_right_axis_direction(&axes, gp.0).length() > STICK_THRESHOLD
+    });
+    let keyboard_mouse_used = keyboard_input.get_just_pressed().next().is_some()
+        || mouse_input.get_just_pressed().next().is_some();
+    let used = match (keyboard_mouse_used, gamepad_used) {
+        (true, _) => InputDevice::KeyboardMouse,
+        (false, true) => InputDevice::Gamepad,
+        (false, false) => return,
+    };
+    if *device != used {
+        *device = used;
+    }
+}
+
+fn start_rebind(mut commands: Commands, mut rebind_ev: EventReader<RebindAction>) {
+    if let Some(rebind) = rebind_ev.iter().last() {
+        commands.insert_resource(PendingRebind { action: rebind.action, replace: rebind.replace });
+    }
+}
+
+fn capture_rebind(
+    mut commands: Commands,
+    pending: Option<Res<PendingRebind>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    my_gamepad: Option<Res<MyGamepad>>,
+    mut map: ResMut<ActionMap>,
+) {
+    let Some(pending) = pending else { return; };
+    let gamepad = my_gamepad.map(|gp| gp.0);
+    let pressed = keyboard_input.get_just_pressed().next().map(|&key| Binding::Key(key))
+        .or_else(|| mouse_input.get_just_pressed().next().map(|&button| Binding::Mouse(button)))
+        .or_else(|| gamepad_input.get_just_pressed()
+            .find(|button| Some(button.gamepad) == gamepad)
+            .map(|button| Binding::Gamepad(button.button_type)));
+    let Some(binding) = pressed else { return; };
+    match &pending.replace {
+        Some(old) => map.rebind(pending.action, old, binding),
+        None => map.bind(pending.action, binding),
+    }
+    commands.remove_resource::<PendingRebind>();
+}
+
+fn save_action_map(path: Res<SettingsPath>, map: Res<ActionMap>) {
+    if !map.is_changed() || map.is_added() { return; }
+    if let Err(error) = map.save(&path.0) {
+        warn!("Failed to save input settings: {error}");
+    }
+}
+
+#[cfg(test)]
+mod actions_test {
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::gamepad::GamepadButtonChangedEvent;
+    use bevy::input::keyboard::KeyboardInput;
+    use super::*;
+    use crate::gamepad::GamepadPlugin;
+    use crate::test_utils::{connect_test_gamepad, update};
+
+    #[test]
+    fn it_keeps_multiple_bindings_per_action() {
+        let map = ActionMap::default();
+        assert!(map.is_bound(Action::Interact, &Binding::Key(KeyCode::E)));
+        assert!(map.is_bound(Action::Interact, &Binding::Gamepad(GamepadButtonType::South)));
+    }
+
+    #[test]
+    fn it_binds_the_debug_log_only_in_debug_mode() {
+        let map = ActionMap::default();
+        assert_eq!(map.is_bound(Action::DebugLog, &Binding::Key(KeyCode::Space)), cfg!(feature = "debug-mode"));
+    }
+
+    #[test]
+    fn it_replaces_a_binding_in_place() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::Interact, &Binding::Key(KeyCode::E), Binding::Key(KeyCode::F));
+        assert_eq!(map.bindings(Action::Interact), [
+            Binding::Key(KeyCode::F),
+            Binding::Gamepad(GamepadButtonType::South),
+        ]);
+    }
+
+    #[test]
+    fn it_prompts_with_the_binding_of_the_device_in_use() {
+        let mut map = ActionMap::default();
+        assert_eq!(map.prompt(Action::Interact, InputDevice::KeyboardMouse), Some(ButtonPrompt::Text("[E]".into())));
+        assert_eq!(map.prompt(Action::Interact, InputDevice::Gamepad), Some(ButtonPrompt::Glyph(0)));
+        assert_eq!(map.prompt(Action::Attack, InputDevice::KeyboardMouse), Some(ButtonPrompt::Text("[LMB]".into())));
+        map.unbind(Action::Interact, &Binding::Gamepad(GamepadButtonType::South));
+        assert_eq!(map.prompt(Action::Interact, InputDevice::Gamepad), Some(ButtonPrompt::Text("[E]".into())));
+    }
+
+    #[test]
+    fn it_tracks_the_last_used_device() {
+        let mut app = setup(test_path("device"));
+        connect_test_gamepad(&mut app);
+        app.world.send_event(GamepadButtonChangedEvent::new(
+            Gamepad { id: 1 },
+            GamepadButtonType::South,
+            1.0,
+        ));
+        update(&mut app, 2);
+        assert_eq!(*app.world.resource::<InputDevice>(), InputDevice::Gamepad);
+        press_key(&mut app, KeyCode::E);
+        app.update();
+        assert_eq!(*app.world.resource::<InputDevice>(), InputDevice::KeyboardMouse);
+    }
+
+    #[test]
+    fn it_fills_missing_actions_with_defaults_when_loading() {
+        let path = test_path("partial");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{"bindings": {"Interact": [{"Key": "F"}]}}"#).unwrap();
+        let map = ActionMap::load(&path).unwrap();
+        assert_eq!(map.bindings(Action::Interact), [Binding::Key(KeyCode::F)]);
+        assert_eq!(map.bindings(Action::Move), ActionMap::default().bindings(Action::Move));
+    }
+
+    #[test]
+    fn it_gives_each_test_app_its_own_settings_file() {
+        assert_ne!(SettingsPath::temporary().0, SettingsPath::temporary().0);
+        assert!(!SettingsPath::temporary().0.exists());
+    }
+
+    #[test]
+    fn it_loads_saved_bindings_on_startup() {
+        let path = test_path("startup");
+        let mut map = ActionMap::default();
+        map.unbind(Action::Pause, &Binding::Key(KeyCode::Escape));
+        map.save(&path).unwrap();
+        let app = setup(path);
+        assert_eq!(*app.world.resource::<ActionMap>(), map);
+    }
+
+    #[test]
+    fn it_rebinds_to_the_next_pressed_key_and_persists_it() {
+        let path = test_path("rebind_key");
+        let mut app = setup(path.clone());
+        app.world.send_event(RebindAction {
+            action: Action::Interact,
+            replace: Some(Binding::Key(KeyCode::E)),
+        });
+        update(&mut app, 2);
+        assert!(app.world.get_resource::<PendingRebind>().is_some());
+        press_key(&mut app, KeyCode::F);
+        app.update();
+        let map = app.world.resource::<ActionMap>();
+        assert!(map.is_bound(Action::Interact, &Binding::Key(KeyCode::F)));
+        assert!(!map.is_bound(Action::Interact, &Binding::Key(KeyCode::E)));
+        assert!(app.world.get_resource::<PendingRebind>().is_none());
+        assert_eq!(ActionMap::load(&path).unwrap(), *map);
+    }
+
+    #[test]
+    fn it_adds_gamepad_buttons_when_rebinding() {
+        let mut app = setup(test_path("rebind_gamepad"));
+        connect_test_gamepad(&mut app);
+        app.world.send_event(RebindAction { action: Action::OpenLog, replace: None });
+        update(&mut app, 2);
+        app.world.send_event(GamepadButtonChangedEvent::new(
+            Gamepad { id: 1 },
+            GamepadButtonType::North,
+            1.0,
+        ));
+        update(&mut app, 2);
+        let map = app.world.resource::<ActionMap>();
+        assert!(map.is_bound(Action::OpenLog, &Binding::Gamepad(GamepadButtonType::North)));
+        assert!(map.is_bound(Action::OpenLog, &Binding::Key(KeyCode::Tab)));
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Pressed,
+        });
+    }
+
+    fn test_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("onyx_actions_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("input.json")
+    }
+
+    fn setup(path: PathBuf) -> App {
+        let mut app = App::new();
+        app
+            .add_plugin(InputPlugin)
+            .add_plugin(GamepadPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath(path));
+        app.update();
+        app
+    }
+}
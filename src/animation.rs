@@ -1,86 +1,344 @@
-use bevy::prelude::{Added, Changed, Component, Mut, Or, Query, Res, Time, Timer};
-use bevy::sprite::TextureAtlasSprite;
-use bevy::time::TimerMode;
-use bevy::utils::petgraph::matrix_graph::Zero;
+use std::collections::HashMap;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
 use bevy_ecs_ldtk::EntityInstance;
 use bevy_rapier2d::prelude::Velocity;
+use serde::Deserialize;
+use crate::aseprite::AsepriteSheet;
+use crate::game_state::GameState;
+use crate::health::{Dead, Invulnerable};
+use crate::spell::Projectile;
 
-#[derive(Debug, PartialEq, Default)]
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<AnimationLibrary>()
+            .init_asset_loader::<AnimationLibraryLoader>()
+            .init_resource::<AnimationLibrary>()
+            .init_resource::<AnimationLibraryHandle>()
+            .add_system(load_animation_library)
+            .add_systems((play_triggered_animations, animation_system)
+                .chain()
+                .after(load_animation_library)
+                .in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Deserialize)]
 pub enum AnimationState {
     #[default]
     Idle,
-    Moving,
+    Walk,
+    Hit,
+    Death,
+    Cast,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Deserialize)]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+    Up,
+    Down,
 }
 
-#[derive(Component, Default)]
+impl Facing {
+    /// Direction of travel for `velocity`, favouring the horizontal on diagonals.
+    fn of(velocity: Vec2) -> Option<Facing> {
+        if velocity == Vec2::ZERO { return None; }
+        Some(match velocity.x.abs() >= velocity.y.abs() {
+            true if velocity.x < 0. => Facing::Left,
+            true => Facing::Right,
+            false if velocity.y < 0. => Facing::Down,
+            false => Facing::Up,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default, Deserialize)]
+pub enum PlayMode {
+    #[default]
+    Loop,
+    /// Holds the last frame once played, then gives way to idle or walking unless it is a death.
+    Once,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct FrameRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct AnimationClip {
+    /// Aseprite sheet, by its name in the character's [AnimationSheets], to take frames and their
+    /// durations from instead of `first`, `last` and `frame_time`.
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// Tag of the sheet to play, the whole sheet without one.
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub first: usize,
+    #[serde(default)]
+    pub last: usize,
+    /// Seconds each frame is shown.
+    #[serde(default)]
+    pub frame_time: f32,
+    #[serde(default)]
+    pub mode: PlayMode,
+    /// Frames to use instead when facing a direction. Without one for `Left` the sprite is flipped.
+    #[serde(default)]
+    pub directions: HashMap<Facing, FrameRange>,
+}
+
+impl AnimationClip {
+    /// Frames to play facing `facing`, and whether the sprite is flipped for it, if it should change.
+    fn frames(&self, facing: Facing) -> (FrameRange, Option<bool>) {
+        match self.directions.get(&facing) {
+            Some(range) => (*range, Some(false)),
+            None => {
+                let flip = match facing {
+                    Facing::Left => Some(true),
+                    Facing::Right => Some(false),
+                    Facing::Up | Facing::Down => None,
+                };
+                (FrameRange { first: self.first, last: self.last }, flip)
+            }
+        }
+    }
+}
+
+pub type AnimationSet = HashMap<AnimationState, AnimationClip>;
+
+/// Every character's clips by animation set name, empty until `assets/data/game.animations.json` loads.
+#[derive(Resource, TypeUuid, Deserialize, Clone, Default, Debug)]
+#[uuid = "c41a8f36-95d2-4e7b-b063-2f9e7d15a4c8"]
+pub struct AnimationLibrary(HashMap<String, AnimationSet>);
+
+impl AnimationLibrary {
+    pub fn parse(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn get(&self, set: &str) -> Option<&AnimationSet> {
+        self.0.get(set)
+    }
+
+    pub fn clip(&self, set: &str, state: AnimationState) -> Option<&AnimationClip> {
+        self.get(set)?.get(&state)
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationLibraryLoader;
+
+impl AssetLoader for AnimationLibraryLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let library = AnimationLibrary::parse(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(library));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["animations.json"]
+    }
+}
+
+/// Keeps the library asset loaded so edits to it are picked up while the game runs.
+#[derive(Resource)]
+struct AnimationLibraryHandle(Handle<AnimationLibrary>);
+
+impl FromWorld for AnimationLibraryHandle {
+    fn from_world(world: &mut World) -> Self {
+        AnimationLibraryHandle(world.resource::<AssetServer>().load("data/game.animations.json"))
+    }
+}
+
+/// Game library as shipped, for tests that need real characters' clips.
+#[cfg(test)]
+pub fn game_library() -> AnimationLibrary {
+    AnimationLibrary::parse(&std::fs::read("assets/data/game.animations.json").unwrap()).unwrap()
+}
+
+/// Aseprite sheets by the name clips refer to them with, for characters with one sheet per clip.
+#[derive(Component, Default, Debug)]
+pub struct AnimationSheets(pub HashMap<String, Handle<AsepriteSheet>>);
+
+#[derive(Component, Default, Debug)]
 pub struct CharacterAnimation {
+    /// Entry of the [AnimationLibrary] the clips come from.
+    pub set: String,
     pub state: AnimationState,
-    pub timer: Timer,
-    first: usize,
-    last: usize,
+    pub facing: Facing,
+    /// Frame of the current clip, counted from its first.
+    frame: usize,
+    elapsed: f32,
+    finished: bool,
+}
+
+impl CharacterAnimation {
+    pub fn new(set: &str) -> CharacterAnimation {
+        CharacterAnimation { set: set.to_string(), ..default() }
+    }
+
+    /// Switches to `state` from its first frame, unless it is already playing.
+    pub fn play(&mut self, state: AnimationState) {
+        if self.state == state && !self.finished { return; }
+        self.state = state;
+        self.frame = 0;
+        self.elapsed = 0.;
+        self.finished = false;
+    }
+
+    /// Whether a one-shot clip has shown its last frame.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Steps through `range` by `delta` seconds, showing each frame for `frame_time` of its index.
+    fn advance(&mut self, mode: PlayMode, range: FrameRange, frame_time: impl Fn(usize) -> f32, delta: f32) {
+        let frames = range.last.saturating_sub(range.first) + 1;
+        self.elapsed += delta;
+        loop {
+            let duration = frame_time(range.first + self.frame);
+            if self.finished || duration <= 0. || self.elapsed < duration { break; }
+            self.elapsed -= duration;
+            match mode {
+                PlayMode::Loop => self.frame = (self.frame + 1) % frames,
+                PlayMode::Once if self.frame + 1 >= frames => self.finished = true,
+                PlayMode::Once => self.frame += 1,
+            }
+        }
+        self.frame = self.frame.min(frames - 1);
+    }
+
+    fn interruptible(&self) -> bool {
+        match self.state {
+            AnimationState::Idle | AnimationState::Walk => true,
+            AnimationState::Death => false,
+            AnimationState::Hit | AnimationState::Cast => self.finished,
+        }
+    }
 }
 
 impl From<&EntityInstance> for CharacterAnimation {
     fn from(entity_instance: &EntityInstance) -> CharacterAnimation {
-        match entity_instance.identifier.as_ref() {
-            "Player" => CharacterAnimation {
-                state: AnimationState::Moving,
-                timer: Timer::from_seconds(0.25, TimerMode::Repeating),
-                first: 0,
-                last: 7,
-            },
-            _ => CharacterAnimation::default(),
+        CharacterAnimation::new(&entity_instance.identifier)
+    }
+}
+
+/// Copies the library into its resource whenever the asset loads or changes.
+fn load_animation_library(
+    mut events: EventReader<AssetEvent<AnimationLibrary>>,
+    handle: Res<AnimationLibraryHandle>,
+    assets: Res<Assets<AnimationLibrary>>,
+    mut library: ResMut<AnimationLibrary>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle: loaded } | AssetEvent::Modified { handle: loaded }) = event else { continue; };
+        if *loaded != handle.0 { continue; }
+        if let Some(loaded) = assets.get(loaded) {
+            *library = loaded.clone();
         }
     }
 }
 
-pub fn animation_system(
+/// Starts the one-shot clips gameplay calls for: hits, deaths and casts.
+fn play_triggered_animations(
+    library: Res<AnimationLibrary>,
+    mut animation_q: Query<&mut CharacterAnimation>,
+    dead_q: Query<Entity, Added<Dead>>,
+    hit_q: Query<Entity, (Added<Invulnerable>, Without<Dead>)>,
+    projectile_q: Query<&Projectile, Added<Projectile>>,
+) {
+    let triggered = dead_q.iter().map(|entity| (entity, AnimationState::Death))
+        .chain(hit_q.iter().map(|entity| (entity, AnimationState::Hit)))
+        .chain(projectile_q.iter().map(|projectile| (projectile.caster, AnimationState::Cast)));
+    for (entity, state) in triggered {
+        let Ok(mut animation) = animation_q.get_mut(entity) else { continue; };
+        if animation.state == AnimationState::Death { continue; }
+        if library.clip(&animation.set, state).is_some() {
+            animation.play(state);
+        }
+    }
+}
+
+fn animation_system(
     time: Res<Time>,
+    library: Res<AnimationLibrary>,
+    aseprites: Res<Assets<AsepriteSheet>>,
     mut query: Query<(&mut CharacterAnimation,
                       &mut TextureAtlasSprite,
-                      &Velocity),
-        Or<(Added<Velocity>, Changed<Velocity>)>>,
+                      Option<&Velocity>,
+                      Option<&AnimationSheets>,
+                      Option<&mut Handle<TextureAtlas>>)>,
 ) {
     for (
         mut animation,
         mut sprite,
-        velocity
+        velocity,
+        sheets,
+        atlas
     ) in query.iter_mut() {
-        update_animation_state(&mut animation, velocity);
-        update_sprite_sheet(&time, &mut animation, &mut sprite, velocity);
-    }
-}
-
-fn update_sprite_sheet(time: &Res<Time>, animation: &mut Mut<CharacterAnimation>, sprite: &mut Mut<TextureAtlasSprite>, velocity: &Velocity) {
-    if time.delta().is_zero() || velocity.linvel.length().is_zero() { return; }
-    animation.timer.tick(time.delta());
-    if animation.timer.just_finished() {
-        sprite.index = if sprite.index == animation.last {
-            animation.first
-        } else {
-            sprite.index + 1
-        };
-    }
-}
-
-fn update_animation_state(animation: &mut Mut<CharacterAnimation>, velocity: &Velocity) {
-    if velocity.linvel.length() == 0. {
-        animation.state = AnimationState::Idle;
-    } else {
-        animation.state = AnimationState::Moving;
+        let velocity = velocity.map_or(Vec2::ZERO, |velocity| velocity.linvel);
+        if let Some(facing) = Facing::of(velocity) {
+            animation.facing = facing;
+        }
+        if animation.interruptible() {
+            animation.play(if velocity == Vec2::ZERO { AnimationState::Idle } else { AnimationState::Walk });
+        }
+        let Some(clip) = library.clip(&animation.set, animation.state) else { continue; };
+        let sheet = clip.sheet.as_ref().zip(sheets)
+            .and_then(|(name, sheets)| aseprites.get(sheets.0.get(name)?));
+        if let (Some(sheet), Some(mut atlas)) = (sheet, atlas) {
+            if *atlas != sheet.atlas {
+                *atlas = sheet.atlas.clone();
+            }
+        }
+        let (mut range, flip) = clip.frames(animation.facing);
+        if let Some((first, last)) = sheet.and_then(|sheet| sheet.frames(clip.tag.as_deref())) {
+            range = FrameRange { first, last };
+        }
+        let frame_time = |frame: usize| sheet
+            .and_then(|sheet| sheet.durations.get(frame).copied())
+            .unwrap_or(clip.frame_time);
+        animation.advance(clip.mode, range, frame_time, time.delta_seconds());
+        let index = range.first + animation.frame;
+        if sprite.index != index {
+            sprite.index = index;
+        }
+        if let Some(flip) = flip.filter(|flip| sprite.flip_x != *flip) {
+            sprite.flip_x = flip;
+        }
     }
 }
 
 #[cfg(test)]
 mod test_player_animation {
+    use std::thread;
     use std::time::Duration;
-    use bevy::prelude::{App, Entity, Time, Vec2};
-    use bevy::sprite::{SpriteSheetBundle, TextureAtlasSprite};
-    use bevy::time::{Timer, TimerMode};
-    use bevy::utils::default;
+    use bevy::prelude::*;
     use bevy_rapier2d::prelude::Velocity;
-    use crate::animation::{AnimationState, animation_system, CharacterAnimation};
-    use crate::physics_bundle::CharacterPhysicsBundle;
+    use super::*;
+    use bevy::asset::HandleId;
+    use crate::aseprite::{AsepritePlugin, AsepriteTag};
+
+    const LIBRARY: &str = r#"{
+        "Hero": {
+            "Idle": { "first": 0, "last": 1, "frame_time": 1.0 },
+            "Walk": { "first": 4, "last": 7, "frame_time": 1.0, "directions": { "Up": { "first": 10, "last": 11 } } },
+            "Hit": { "first": 8, "last": 9, "frame_time": 1.0, "mode": "Once" },
+            "Cast": { "sheet": "Spells", "tag": "Spin", "mode": "Once" }
+        }
+    }"#;
 
     #[test]
     fn it_has_idle_animation_by_default() {
@@ -90,54 +348,120 @@ mod test_player_animation {
     }
 
     #[test]
-    fn it_changes_to_moving_animation_when_moving() {
+    fn it_changes_to_walk_animation_when_moving() {
         let (mut app, player) = setup();
         add_velocity(&mut app, player, Vec2::new(10., 0.));
         app.update();
         let animation = app.world.entity(player).get::<CharacterAnimation>().unwrap();
-        assert_eq!(animation.state, AnimationState::Moving);
+        assert_eq!(animation.state, AnimationState::Walk);
+        assert_eq!(sprite(&app, player).index, 4);
     }
 
     #[test]
     fn updates_sprite_by_frame() {
         let (mut app, player) = setup();
-        let sprite_sheet = app.world.entity(player).get::<TextureAtlasSprite>().unwrap();
-        assert_eq!(sprite_sheet.index, 0);
+        assert_eq!(sprite(&app, player).index, 0);
 
-        // test to check it doesnt update sprite if velocity is 0.
-        add_velocity(&mut app, player, Vec2::new(0., 0.));
-        update_time_resource(&mut app, Duration::from_secs(1));
-        app.update();
-        let sprite_sheet = app.world.entity(player).get::<TextureAtlasSprite>().unwrap();
-        assert_eq!(sprite_sheet.index, 0);
+        advance_time(&mut app, Duration::from_secs(1));
+        assert_eq!(sprite(&app, player).index, 1);
+        advance_time(&mut app, Duration::from_secs(1));
+        assert_eq!(sprite(&app, player).index, 0, "idle loops back to its first frame");
 
+        add_velocity(&mut app, player, Vec2::new(10., 0.));
+        advance_time(&mut app, Duration::from_secs(1));
+        assert_eq!(sprite(&app, player).index, 5);
+    }
 
+    #[test]
+    fn it_flips_or_switches_frames_by_facing() {
+        let (mut app, player) = setup();
+        add_velocity(&mut app, player, Vec2::new(-10., 0.));
+        app.update();
+        assert!(sprite(&app, player).flip_x);
+        add_velocity(&mut app, player, Vec2::new(0., 10.));
+        app.update();
+        let sprite = sprite(&app, player);
+        assert_eq!(sprite.index, 10);
+        assert!(!sprite.flip_x);
+    }
+
+    #[test]
+    fn it_returns_to_walking_after_a_one_shot_clip() {
+        let (mut app, player) = setup();
         add_velocity(&mut app, player, Vec2::new(10., 0.));
-        update_time_resource(&mut app, Duration::from_secs(1));
+        app.world.get_mut::<CharacterAnimation>(player).unwrap().play(AnimationState::Hit);
+        advance_time(&mut app, Duration::from_secs(1));
+        assert_eq!(sprite(&app, player).index, 9);
+        advance_time(&mut app, Duration::from_secs(1));
+        let animation = app.world.get::<CharacterAnimation>(player).unwrap();
+        assert!(animation.finished());
+        assert_eq!(sprite(&app, player).index, 9);
+        app.update();
+        assert_eq!(app.world.get::<CharacterAnimation>(player).unwrap().state, AnimationState::Walk);
+    }
+
+    #[test]
+    fn it_plays_tags_of_aseprite_sheets_with_their_durations() {
+        let (mut app, player) = setup();
+        let atlas = Handle::weak(HandleId::random::<TextureAtlas>());
+        let sheet = app.world.resource_mut::<Assets<AsepriteSheet>>().add(AsepriteSheet {
+            atlas: atlas.clone(),
+            durations: vec![1., 0.5, 2.],
+            tags: vec![AsepriteTag { name: "Spin".to_string(), from: 1, to: 2 }],
+        });
+        app.world.entity_mut(player).insert(AnimationSheets(HashMap::from([("Spells".to_string(), sheet)])));
+        app.world.get_mut::<CharacterAnimation>(player).unwrap().play(AnimationState::Cast);
         app.update();
+        assert_eq!(sprite(&app, player).index, 1);
+        assert_eq!(app.world.get::<Handle<TextureAtlas>>(player).unwrap(), &atlas);
+        advance_time(&mut app, Duration::from_secs(1));
+        assert_eq!(sprite(&app, player).index, 2);
+        assert!(!app.world.get::<CharacterAnimation>(player).unwrap().finished());
+    }
 
-        let sprite_sheet = app.world.entity(player).get::<TextureAtlasSprite>().unwrap();
-        assert_eq!(sprite_sheet.index, 1);
+    #[test]
+    fn it_reads_the_game_animation_library() {
+        let library = game_library();
+        for set in ["Player", "Zombie"] {
+            assert!(library.clip(set, AnimationState::Idle).is_some(), "{set} has no idle clip");
+            assert!(library.clip(set, AnimationState::Walk).is_some(), "{set} has no walk clip");
+        }
+        assert_eq!(library.clip("Zombie", AnimationState::Death).unwrap().mode, PlayMode::Once);
+    }
+
+    #[test]
+    fn it_replaces_the_library_once_the_asset_loads() {
+        let (mut app, _) = setup();
+        app.add_state::<GameState>().add_plugin(AnimationPlugin);
+        for _ in 0..100 {
+            if app.world.resource::<AnimationLibrary>().get("Zombie").is_some() { break; }
+            thread::sleep(Duration::from_millis(10));
+            app.update();
+        }
+        let library = app.world.resource::<AnimationLibrary>();
+        assert_eq!(library.get("Zombie"), game_library().get("Zombie"));
+        assert!(library.get("Hero").is_none());
     }
 
     fn setup() -> (App, Entity) {
         let mut app = App::new();
+        app.add_plugin(TaskPoolPlugin::default())
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(AsepritePlugin);
         initialize_time(&mut app);
+        app.insert_resource(AnimationLibrary::parse(LIBRARY.as_bytes()).unwrap());
         let player = app.world
-            .spawn((
-                SpriteSheetBundle::default(),
-                CharacterAnimation {
-                    timer: Timer::from_seconds(1., TimerMode::Repeating),
-                    state: Default::default(),
-                    first: 0,
-                    last: 7,
-                },
-            )).id();
+            .spawn((SpriteSheetBundle::default(), CharacterAnimation::new("Hero")))
+            .id();
         app.add_system(animation_system);
         app.update();
         (app, player)
     }
 
+    fn sprite(app: &App, player: Entity) -> &TextureAtlasSprite {
+        app.world.entity(player).get::<TextureAtlasSprite>().unwrap()
+    }
+
     fn initialize_time(app: &mut App) {
         app.init_resource::<Time>();
         let mut time = Time::default();
@@ -154,9 +478,10 @@ mod test_player_animation {
             });
     }
 
-    fn update_time_resource(app: &mut App, duration: Duration) {
+    fn advance_time(app: &mut App, duration: Duration) {
         let mut time = app.world.resource_mut::<Time>();
         let last_update = time.last_update().unwrap();
         time.update_with_instant(last_update + duration);
+        app.update();
     }
 }
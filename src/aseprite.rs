@@ -0,0 +1,348 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use bevy::asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+
+pub struct AsepritePlugin;
+
+impl Plugin for AsepritePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<AsepriteSheet>()
+            .init_asset_loader::<AsepriteLoader>();
+    }
+}
+
+const FILE_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const HEADER_SIZE: usize = 128;
+const TAGS_CHUNK: u16 = 0x2018;
+const SLICE_CHUNK: u16 = 0x2022;
+const NINE_PATCH_FLAG: u32 = 1 << 0;
+
+/// Frames of an Aseprite file laid out as the horizontal `<name>-Sheet.png` strip exported next to it.
+/// Files with slices, like sheets of items drawn in a single frame, get one atlas entry per slice instead.
+#[derive(TypeUuid, Debug)]
+#[uuid = "5b4e3c1a-7d2f-4e8b-9a61-0c3f2d8e7b94"]
+pub struct AsepriteSheet {
+    pub atlas: Handle<TextureAtlas>,
+    /// Seconds each frame is shown.
+    pub durations: Vec<f32>,
+    pub tags: Vec<AsepriteTag>,
+}
+
+impl AsepriteSheet {
+    /// First and last frame of the tag called `name`, or of the whole file without one.
+    pub fn frames(&self, name: Option<&str>) -> Option<(usize, usize)> {
+        match name {
+            Some(name) => self.tags.iter().find(|tag| tag.name == name).map(|tag| (tag.from, tag.to)),
+            None if self.durations.is_empty() => None,
+            None => Some((0, self.durations.len() - 1)),
+        }
+    }
+}
+
+/// A named run of frames, played forward.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// A named region of the first frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsepriteSlice {
+    pub name: String,
+    pub rect: Rect,
+}
+
+/// What the loader reads from an `.aseprite` file; the pixels come from its exported sheet.
+#[derive(Debug, PartialEq)]
+pub struct AsepriteFile {
+    pub frame_size: Vec2,
+    pub durations: Vec<f32>,
+    pub tags: Vec<AsepriteTag>,
+    pub slices: Vec<AsepriteSlice>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AsepriteError {
+    NotAseprite,
+    Truncated,
+    /// A frame or chunk claims a length shorter than what it holds.
+    BadLength,
+    /// The named tag starts after it ends or runs past the last frame.
+    BadTag(String),
+}
+
+impl Display for AsepriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsepriteError::NotAseprite => write!(f, "not an Aseprite file"),
+            AsepriteError::Truncated => write!(f, "Aseprite file ends early"),
+            AsepriteError::BadLength => write!(f, "Aseprite file has a frame or chunk shorter than its header"),
+            AsepriteError::BadTag(name) => write!(f, "Aseprite tag {name} is outside the file's frames"),
+        }
+    }
+}
+
+impl Error for AsepriteError {}
+
+/// Little-endian reads over the file, failing on anything past its end.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], AsepriteError> {
+        let bytes: &'a [u8] = self.bytes;
+        let bytes = bytes.get(self.position..self.position + count).ok_or(AsepriteError::Truncated)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, AsepriteError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, AsepriteError> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, AsepriteError> {
+        self.bytes(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, AsepriteError> {
+        self.u32().map(|value| value as i32)
+    }
+
+    fn string(&mut self) -> Result<String, AsepriteError> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+
+    /// Moves on to the end of a frame or chunk, which can't be before what was already read of it.
+    fn skip_to(&mut self, end: usize) -> Result<(), AsepriteError> {
+        if end < self.position { return Err(AsepriteError::BadLength); }
+        self.position = end;
+        Ok(())
+    }
+}
+
+impl AsepriteFile {
+    pub fn parse(bytes: &[u8]) -> Result<AsepriteFile, AsepriteError> {
+        let mut reader = Reader { bytes, position: 0 };
+        reader.u32()?;
+        if reader.u16()? != FILE_MAGIC { return Err(AsepriteError::NotAseprite); }
+        let frames = reader.u16()?;
+        let frame_size = Vec2::new(reader.u16()? as f32, reader.u16()? as f32);
+        reader.position = HEADER_SIZE;
+        let mut durations = Vec::with_capacity(frames as usize);
+        let mut tags = Vec::new();
+        let mut slices = Vec::new();
+        for _ in 0..frames {
+            let frame_start = reader.position;
+            let frame_length = reader.u32()? as usize;
+            if reader.u16()? != FRAME_MAGIC { return Err(AsepriteError::NotAseprite); }
+            let old_chunks = reader.u16()?;
+            durations.push(reader.u16()? as f32 / 1000.);
+            reader.bytes(2)?;
+            let chunks = match reader.u32()? {
+                0 => old_chunks as u32,
+                chunks => chunks,
+            };
+            for _ in 0..chunks {
+                let chunk_start = reader.position;
+                let chunk_length = reader.u32()? as usize;
+                match reader.u16()? {
+                    TAGS_CHUNK => tags.extend(read_tags(&mut reader)?),
+                    SLICE_CHUNK => slices.push(read_slice(&mut reader)?),
+                    _ => {}
+                }
+                reader.skip_to(chunk_start + chunk_length)?;
+            }
+            reader.skip_to(frame_start + frame_length)?;
+        }
+        if let Some(tag) = tags.iter().find(|tag| tag.from > tag.to || tag.to >= durations.len()) {
+            return Err(AsepriteError::BadTag(tag.name.clone()));
+        }
+        Ok(AsepriteFile { frame_size, durations, tags, slices })
+    }
+}
+
+fn read_tags(reader: &mut Reader) -> Result<Vec<AsepriteTag>, AsepriteError> {
+    let count = reader.u16()?;
+    reader.bytes(8)?;
+    (0..count).map(|_| {
+        let from = reader.u16()? as usize;
+        let to = reader.u16()? as usize;
+        // Direction, repeat count, reserved bytes and the deprecated colour.
+        reader.u8()?;
+        reader.bytes(12)?;
+        Ok(AsepriteTag { name: reader.string()?, from, to })
+    }).collect()
+}
+
+/// Reads where a slice is on the first frame, ignoring where it moves to on later ones.
+fn read_slice(reader: &mut Reader) -> Result<AsepriteSlice, AsepriteError> {
+    let keys = reader.u32()?;
+    let flags = reader.u32()?;
+    reader.u32()?;
+    let name = reader.string()?;
+    if keys == 0 { return Err(AsepriteError::Truncated); }
+    // The first key's frame number.
+    reader.u32()?;
+    let min = Vec2::new(reader.i32()? as f32, reader.i32()? as f32);
+    let size = Vec2::new(reader.u32()? as f32, reader.u32()? as f32);
+    if flags & NINE_PATCH_FLAG != 0 {
+        reader.bytes(16)?;
+    }
+    Ok(AsepriteSlice { name, rect: Rect::from_corners(min, min + size) })
+}
+
+#[derive(Default)]
+pub struct AsepriteLoader;
+
+impl AssetLoader for AsepriteLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let file = AsepriteFile::parse(bytes)?;
+            let path = load_context.path();
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let sheet_path = AssetPath::new(path.with_file_name(format!("{stem}-Sheet.png")), None);
+            let texture = load_context.get_handle(sheet_path.clone());
+            let frames = file.durations.len().max(1);
+            let atlas = match file.slices.is_empty() {
+                true => TextureAtlas::from_grid(texture, file.frame_size, frames, 1, None, None),
+                false => {
+                    let mut atlas = TextureAtlas::new_empty(texture, file.frame_size * Vec2::new(frames as f32, 1.));
+                    for slice in &file.slices {
+                        atlas.add_texture(slice.rect);
+                    }
+                    atlas
+                }
+            };
+            let atlas = load_context.set_labeled_asset("atlas", LoadedAsset::new(atlas).with_dependency(sheet_path.clone()));
+            let sheet = AsepriteSheet { atlas, durations: file.durations, tags: file.tags };
+            load_context.set_default_asset(LoadedAsset::new(sheet).with_dependency(sheet_path));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite", "ase"]
+    }
+}
+
+#[cfg(test)]
+mod aseprite_test {
+    use super::*;
+
+    const FRAME_HEADER_SIZE: usize = 16;
+
+    #[test]
+    fn it_reads_frames_from_the_zombie_sprites() {
+        let file = AsepriteFile::parse(include_bytes!("../assets/cementary/Enemy/Zombie - Base/Run/Run.aseprite")).unwrap();
+        assert_eq!(file.frame_size, Vec2::new(64., 64.));
+        assert_eq!(file.durations, vec![0.1; 6]);
+    }
+
+    #[test]
+    fn it_reads_tags() {
+        let file = AsepriteFile::parse(&tagged_file()).unwrap();
+        assert_eq!(file.durations, vec![0.1, 0.25, 0.05]);
+        assert_eq!(file.tags, vec![
+            AsepriteTag { name: "Idle".to_string(), from: 0, to: 1 },
+            AsepriteTag { name: "Hit".to_string(), from: 2, to: 2 },
+        ]);
+    }
+
+    #[test]
+    fn it_reads_slices_from_the_weapon_sheet() {
+        let file = AsepriteFile::parse(include_bytes!("../assets/cementary/Weapons/Cursed Weapons/Cursed Weapons.aseprite")).unwrap();
+        assert_eq!(file.durations.len(), 1);
+        assert_eq!(file.slices.len(), 9);
+        assert_eq!(file.slices[1], AsepriteSlice {
+            name: "Weapon 2".to_string(),
+            rect: Rect::new(16., 0., 32., 48.),
+        });
+    }
+
+    #[test]
+    fn it_rejects_lengths_that_would_read_backwards() {
+        let mut file = tagged_file();
+        file[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(AsepriteFile::parse(&file), Err(AsepriteError::BadLength));
+        let mut file = tagged_file();
+        let chunk_start = HEADER_SIZE + FRAME_HEADER_SIZE;
+        file[chunk_start..chunk_start + 4].copy_from_slice(&4u32.to_le_bytes());
+        assert_eq!(AsepriteFile::parse(&file), Err(AsepriteError::BadLength));
+    }
+
+    #[test]
+    fn it_rejects_tags_outside_the_frames() {
+        let mut file = tagged_file();
+        // The `to` of the second tag, after the tag count, reserved bytes and the whole first tag.
+        let to = HEADER_SIZE + FRAME_HEADER_SIZE + 6 + 2 + 8 + (17 + 2 + "Idle".len()) + 2;
+        file[to..to + 2].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(AsepriteFile::parse(&file), Err(AsepriteError::BadTag("Hit".to_string())));
+    }
+
+    #[test]
+    fn it_rejects_other_files() {
+        assert_eq!(AsepriteFile::parse(include_bytes!("../assets/dungeon/wizzard.png")), Err(AsepriteError::NotAseprite));
+        assert_eq!(AsepriteFile::parse(&tagged_file()[..140]), Err(AsepriteError::Truncated));
+    }
+
+    #[test]
+    fn it_finds_tag_frames() {
+        let sheet = AsepriteSheet {
+            atlas: Handle::default(),
+            durations: vec![0.1; 4],
+            tags: vec![AsepriteTag { name: "Hit".to_string(), from: 2, to: 3 }],
+        };
+        assert_eq!(sheet.frames(Some("Hit")), Some((2, 3)));
+        assert_eq!(sheet.frames(Some("Run")), None);
+        assert_eq!(sheet.frames(None), Some((0, 3)));
+    }
+
+    /// Three 16x16 frames, the first holding the tags chunk.
+    fn tagged_file() -> Vec<u8> {
+        let mut tags = Vec::new();
+        tags.extend(2u16.to_le_bytes());
+        tags.extend([0; 8]);
+        for (name, from, to) in [("Idle", 0u16, 1u16), ("Hit", 2, 2)] {
+            tags.extend(from.to_le_bytes());
+            tags.extend(to.to_le_bytes());
+            tags.extend([0; 13]);
+            tags.extend((name.len() as u16).to_le_bytes());
+            tags.extend(name.as_bytes());
+        }
+        let mut chunk = Vec::new();
+        chunk.extend((6 + tags.len() as u32).to_le_bytes());
+        chunk.extend(TAGS_CHUNK.to_le_bytes());
+        chunk.extend(tags);
+
+        let mut file = vec![0; HEADER_SIZE];
+        file[4..6].copy_from_slice(&FILE_MAGIC.to_le_bytes());
+        file[6..8].copy_from_slice(&3u16.to_le_bytes());
+        file[8..10].copy_from_slice(&16u16.to_le_bytes());
+        file[10..12].copy_from_slice(&16u16.to_le_bytes());
+        for (index, duration) in [100u16, 250, 50].into_iter().enumerate() {
+            let chunks: &[u8] = if index == 0 { &chunk } else { &[] };
+            file.extend((FRAME_HEADER_SIZE as u32 + chunks.len() as u32).to_le_bytes());
+            file.extend(FRAME_MAGIC.to_le_bytes());
+            file.extend((if index == 0 { 1u16 } else { 0 }).to_le_bytes());
+            file.extend(duration.to_le_bytes());
+            file.extend([0; 2]);
+            file.extend((if index == 0 { 1u32 } else { 0 }).to_le_bytes());
+            file.extend(chunks);
+        }
+        file
+    }
+}
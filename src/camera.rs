@@ -1,41 +1,514 @@
 use bevy::prelude::*;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::render::camera::ScalingMode;
+use bevy_ecs_ldtk::prelude::*;
+use crate::actions::{Action, ActionInput};
+use crate::cursor_indicator::CursorIndicator;
+use crate::game_state::GameState;
+use crate::health::Invulnerable;
+use crate::level::selected_level_iid;
+use crate::network::NetworkPeer;
+use crate::player::Player;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_camera)
-            .add_system(set_zoom);
+        app
+            .add_event::<CameraEffect>()
+            .init_resource::<CameraEffects>()
+            .add_startup_system(spawn_camera)
+            .add_systems((
+                follow_local_player,
+                update_camera_bounds,
+                zoom_on_input,
+                shake_on_damage,
+                receive_camera_effects,
+                update_look_ahead,
+                move_camera,
+                apply_camera_effects,
+            ).chain().in_set(OnUpdate(GameState::InGame)))
+            .add_system(reset_camera.in_schedule(OnExit(GameState::InGame)));
+    }
+}
+
+const DEFAULT_ZOOM: f32 = 0.35;
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 0.6;
+/// Zoom change per second while a zoom action is held.
+const ZOOM_SPEED: f32 = 0.3;
+/// Zoom change per mouse wheel line.
+const ZOOM_STEP: f32 = 0.05;
+const ZOOM_DAMPING: f32 = 8.;
+/// Trauma worn off per second.
+const TRAUMA_DECAY: f32 = 1.;
+/// Trauma added each time the camera target is hurt.
+const DAMAGE_TRAUMA: f32 = 0.4;
+/// Offset of the view at full trauma.
+const MAX_SHAKE: f32 = 8.;
+/// How far ahead of its target, along the target's aim, the camera looks.
+const LOOK_AHEAD_DISTANCE: f32 = 24.;
+const LOOK_AHEAD_DAMPING: f32 = 3.;
+
+/// An IntGrid layer whose painted cells bound what the camera may show in its level.
+const CAMERA_REACH_LAYER: &str = "CAMERA_REACH";
+
+/// Eases its camera towards `target`, keeping the view inside the current level.
+#[derive(Component, Debug)]
+pub struct CameraController {
+    /// Entity to follow. Falls back to the local player whenever unset or despawned, so cutscenes can
+    /// point it somewhere else for a while.
+    pub target: Option<Entity>,
+    /// Half extents of the box around the view's centre the target moves in without the camera following.
+    pub dead_zone: Vec2,
+    /// How quickly the camera catches up, per second.
+    pub damping: f32,
+    bounds: Option<Rect>,
+    /// Followed position, before effects like shaking are applied on top.
+    focus: Vec2,
+    /// Jump straight to the target next frame instead of panning there.
+    snap: bool,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        CameraController {
+            target: None,
+            dead_zone: Vec2::new(16., 12.),
+            damping: 6.,
+            bounds: None,
+            focus: Vec2::ZERO,
+            snap: true,
+        }
+    }
+}
+
+/// Requests for the camera beyond following its target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraEffect {
+    /// Eases the projection scale to the given one, kept within the zoom limits.
+    ZoomTo(f32),
+    /// Adds trauma, shaking the view until it wears off. Trauma is capped at 1.
+    Shake(f32),
+}
+
+/// State of the effects layered over the camera's follow.
+#[derive(Resource, Debug)]
+pub struct CameraEffects {
+    pub zoom: f32,
+    pub target_zoom: f32,
+    pub trauma: f32,
+    pub look_ahead: Vec2,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        CameraEffects {
+            zoom: DEFAULT_ZOOM,
+            target_zoom: DEFAULT_ZOOM,
+            trauma: 0.,
+            look_ahead: Vec2::ZERO,
+        }
+    }
+}
+
+impl CameraEffects {
+    fn zoom_to(&mut self, zoom: f32) {
+        self.target_zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Offset of a shaking view `elapsed` seconds into the game, scaled by the square of the trauma.
+    fn shake_offset(&self, elapsed: f32) -> Vec2 {
+        let noise = Vec2::new((elapsed * 41.).sin(), (elapsed * 53. + 1.3).sin());
+        noise * MAX_SHAKE * self.trauma * self.trauma
     }
 }
 
 pub fn spawn_camera(mut commands: Commands) {
     let mut camera_bundle = Camera2dBundle::default();
     camera_bundle.projection.scaling_mode =  ScalingMode::FixedVertical(1000.);
-    commands.spawn(camera_bundle);
+    camera_bundle.projection.scale = DEFAULT_ZOOM;
+    commands.spawn((camera_bundle, CameraController::default()));
 }
 
-pub fn set_zoom(mut query: Query<&mut OrthographicProjection, Added<Camera>>) {
-    for mut projection in query.iter_mut() {
-        projection.scale = 0.35;
+/// Area of `level` the camera may show, given the world position of its bottom left corner.
+pub fn level_camera_bounds(level: &Level, origin: Vec2) -> Rect {
+    let whole_level = Rect::from_corners(origin, origin + Vec2::new(level.px_wid as f32, level.px_hei as f32));
+    let Some(layer) = level.layer_instances.iter().flatten().find(|layer| layer.identifier == CAMERA_REACH_LAYER) else {
+        return whole_level;
+    };
+    let grid_size = layer.grid_size as f32;
+    layer.int_grid_csv.iter().enumerate()
+        .filter(|(_, value)| **value != 0)
+        .map(|(index, _)| {
+            // LDtk counts rows from the top.
+            let cell = IVec2::new(index as i32 % layer.c_wid, layer.c_hei - 1 - index as i32 / layer.c_wid);
+            let corner = origin + cell.as_vec2() * grid_size;
+            Rect::from_corners(corner, corner + Vec2::splat(grid_size))
+        })
+        .reduce(|reach, cell| reach.union(cell))
+        .unwrap_or(whole_level)
+}
+
+/// Keeps `position` far enough inside `bounds` for a view of `half_view` not to show past them,
+/// centring on `bounds` along axes it is too small for.
+fn clamp_to_bounds(position: Vec2, bounds: Rect, half_view: Vec2) -> Vec2 {
+    let min = bounds.min + half_view;
+    let max = bounds.max - half_view;
+    let clamp = |value: f32, min: f32, max: f32, centre: f32| if min > max { centre } else { value.clamp(min, max) };
+    Vec2::new(
+        clamp(position.x, min.x, max.x, bounds.center().x),
+        clamp(position.y, min.y, max.y, bounds.center().y),
+    )
+}
+
+fn follow_local_player(
+    mut camera_q: Query<&mut CameraController>,
+    target_q: Query<(), With<GlobalTransform>>,
+    player_q: Query<Entity, (With<Player>, Without<NetworkPeer>)>,
+) {
+    for mut controller in camera_q.iter_mut() {
+        if matches!(controller.target, Some(target) if target_q.contains(target)) { continue; }
+        let Some(player) = player_q.iter().next() else { continue; };
+        controller.target = Some(player);
+        controller.snap = true;
+    }
+}
+
+fn update_camera_bounds(
+    mut camera_q: Query<&mut CameraController>,
+    level_selection: Res<LevelSelection>,
+    world_q: Query<&Handle<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    level_q: Query<(&Handle<LdtkLevel>, &GlobalTransform)>,
+    levels: Res<Assets<LdtkLevel>>,
+) {
+    let selected = selected_level_iid(&level_selection, &world_q, &ldtk_assets);
+    let bounds = level_q.iter()
+        .filter_map(|(handle, transform)| Some((&levels.get(handle)?.level, transform)))
+        .find(|(level, _)| Some(&level.iid) == selected.as_ref())
+        .map(|(level, transform)| level_camera_bounds(level, transform.translation().truncate()));
+    for mut controller in camera_q.iter_mut() {
+        if controller.bounds != bounds {
+            controller.bounds = bounds;
+        }
+    }
+}
+
+/// Zooms with the zoom actions, or the mouse wheel while the zoom modifier is held.
+fn zoom_on_input(
+    time: Res<Time>,
+    actions: ActionInput,
+    mut wheel_ev: EventReader<MouseWheel>,
+    effects: Res<CameraEffects>,
+    mut effect_ev: EventWriter<CameraEffect>,
+) {
+    let mut change = 0.;
+    if actions.pressed(Action::ZoomIn) { change -= ZOOM_SPEED * time.delta_seconds(); }
+    if actions.pressed(Action::ZoomOut) { change += ZOOM_SPEED * time.delta_seconds(); }
+    let lines: f32 = wheel_ev.iter().map(|event| match event.unit {
+        MouseScrollUnit::Line => event.y,
+        MouseScrollUnit::Pixel => event.y / 20.,
+    }).sum();
+    if actions.pressed(Action::ZoomModifier) {
+        change -= lines * ZOOM_STEP;
+    }
+    if change != 0. {
+        effect_ev.send(CameraEffect::ZoomTo(effects.target_zoom + change));
+    }
+}
+
+/// Shakes for hits that took health, which are the ones that leave their target invulnerable.
+fn shake_on_damage(
+    hit_q: Query<(), Added<Invulnerable>>,
+    camera_q: Query<&CameraController>,
+    mut effect_ev: EventWriter<CameraEffect>,
+) {
+    for controller in camera_q.iter() {
+        if controller.target.map_or(false, |target| hit_q.contains(target)) {
+            effect_ev.send(CameraEffect::Shake(DAMAGE_TRAUMA));
+        }
     }
 }
 
+fn receive_camera_effects(
+    time: Res<Time>,
+    mut effect_ev: EventReader<CameraEffect>,
+    mut effects: ResMut<CameraEffects>,
+) {
+    for effect in effect_ev.iter() {
+        match *effect {
+            CameraEffect::ZoomTo(zoom) => effects.zoom_to(zoom),
+            CameraEffect::Shake(trauma) => effects.trauma = (effects.trauma + trauma).min(1.),
+        }
+    }
+    effects.trauma = (effects.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+    let ease = 1. - (-ZOOM_DAMPING * time.delta_seconds()).exp();
+    effects.zoom += (effects.target_zoom - effects.zoom) * ease;
+}
+
+/// Eases the look-ahead towards where the cursor indicator of the camera target points.
+fn update_look_ahead(
+    time: Res<Time>,
+    camera_q: Query<&CameraController>,
+    indicator_q: Query<(&Parent, &GlobalTransform), With<CursorIndicator>>,
+    mut effects: ResMut<CameraEffects>,
+) {
+    let Ok(controller) = camera_q.get_single() else { return; };
+    let aim = indicator_q.iter()
+        .find(|(parent, _)| Some(parent.get()) == controller.target)
+        .map_or(Vec2::ZERO, |(_, transform)| (transform.compute_transform().rotation * Vec3::X).truncate());
+    let look_ahead = effects.look_ahead.lerp(aim * LOOK_AHEAD_DISTANCE, 1. - (-LOOK_AHEAD_DAMPING * time.delta_seconds()).exp());
+    effects.look_ahead = look_ahead;
+}
+
+fn move_camera(
+    time: Res<Time>,
+    effects: Res<CameraEffects>,
+    mut camera_q: Query<(&mut CameraController, &OrthographicProjection)>,
+    target_q: Query<&GlobalTransform>,
+) {
+    for (mut controller, projection) in camera_q.iter_mut() {
+        let Some(target) = controller.target.and_then(|target| target_q.get(target).ok()) else { continue; };
+        let target = target.translation().truncate() + effects.look_ahead;
+        let position = controller.focus;
+        let desired = if controller.snap {
+            controller.snap = false;
+            target
+        } else {
+            let offset = target - position;
+            let goal = position + (offset.abs() - controller.dead_zone).max(Vec2::ZERO) * offset.signum();
+            position.lerp(goal, 1. - (-controller.damping * time.delta_seconds()).exp())
+        };
+        controller.focus = match controller.bounds {
+            Some(bounds) => clamp_to_bounds(desired, bounds, projection.area.half_size()),
+            None => desired,
+        };
+    }
+}
+
+fn apply_camera_effects(
+    time: Res<Time>,
+    effects: Res<CameraEffects>,
+    mut camera_q: Query<(&CameraController, &mut Transform, &mut OrthographicProjection)>,
+) {
+    let shake = effects.shake_offset(time.elapsed_seconds());
+    for (controller, mut transform, mut projection) in camera_q.iter_mut() {
+        let position = match controller.bounds {
+            Some(bounds) => clamp_to_bounds(controller.focus + shake, bounds, projection.area.half_size()),
+            None => controller.focus + shake,
+        };
+        transform.translation = position.extend(transform.translation.z);
+        if projection.scale != effects.zoom {
+            projection.scale = effects.zoom;
+        }
+    }
+}
+
+/// Leaves the camera where a fresh one would be, for the menus. The zoom is the player's choice and stays.
+fn reset_camera(
+    mut camera_q: Query<(&mut CameraController, &mut Transform)>,
+    mut effects: ResMut<CameraEffects>,
+) {
+    for (mut controller, mut transform) in camera_q.iter_mut() {
+        *controller = CameraController::default();
+        *transform = Camera2dBundle::default().transform;
+    }
+    effects.trauma = 0.;
+    effects.look_ahead = Vec2::ZERO;
+}
+
 
 #[cfg(test)]
 mod camera_test {
     use super::*;
-    use crate::test_utils::{LoadTestPlugins, update};
+    use std::f32::consts::FRAC_PI_2;
+    use std::time::Duration;
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::time::TimeUpdateStrategy;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::game_logs::GameLogEvent;
+    use crate::health::{Health, HealthPlugin, PLAYER_HEALTH};
+    use crate::localization::{Localization, LocalizationPlugin};
+    use crate::test_utils::{enter_game, inflict_damage, LoadTestPlugins, update};
 
     #[test]
     fn spawns_camera() {
         let mut app = App::new();
         app.add_plugins(LoadTestPlugins)
+            .add_state::<GameState>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
             .add_plugin(CameraPlugin);
         update(&mut app, 3);
         assert_eq!(app.world.query::<&Camera>().iter(&app.world).len(), 1)
     }
+
+    #[test]
+    fn it_snaps_to_a_new_target_then_eases_towards_it() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::new(50., 20., 0.));
+        set_target(&mut app, target);
+        app.update();
+        assert_eq!(camera_position(&mut app), Vec2::new(50., 20.));
+        app.world.get_mut::<Transform>(target).unwrap().translation.x = 150.;
+        update(&mut app, 2);
+        let x = camera_position(&mut app).x;
+        assert!(x > 50. && x < 150. - 16., "camera at {x}");
+    }
+
+    #[test]
+    fn it_ignores_movement_inside_the_dead_zone() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        set_target(&mut app, target);
+        app.update();
+        app.world.get_mut::<Transform>(target).unwrap().translation = Vec3::new(10., -8., 0.);
+        update(&mut app, 2);
+        assert_eq!(camera_position(&mut app), Vec2::ZERO);
+    }
+
+    #[test]
+    fn it_eases_the_zoom_within_its_limits() {
+        let mut app = setup();
+        app.world.send_event(CameraEffect::ZoomTo(10.));
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<CameraEffects>().target_zoom, MAX_ZOOM);
+        let scale = app.world.query::<&OrthographicProjection>().single(&app.world).scale;
+        assert!(scale > DEFAULT_ZOOM && scale < MAX_ZOOM, "scale is {scale}");
+    }
+
+    #[test]
+    fn it_zooms_with_the_mouse_wheel_only_while_the_modifier_is_held() {
+        let mut app = setup();
+        let scroll = |app: &mut App| app.world.send_event(MouseWheel { unit: MouseScrollUnit::Line, x: 0., y: 1. });
+        scroll(&mut app);
+        app.update();
+        assert_eq!(app.world.resource::<CameraEffects>().target_zoom, DEFAULT_ZOOM);
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::LControl),
+            state: ButtonState::Pressed,
+        });
+        scroll(&mut app);
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<CameraEffects>().target_zoom, DEFAULT_ZOOM - ZOOM_STEP);
+    }
+
+    #[test]
+    fn it_shakes_when_the_target_is_hurt_then_settles() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        set_target(&mut app, target);
+        app.update();
+        inflict_damage(&mut app, target, 1);
+        app.update();
+        let trauma = app.world.resource::<CameraEffects>().trauma;
+        assert!(trauma > 0. && trauma < DAMAGE_TRAUMA, "trauma is {trauma}");
+        let shaken = camera_position(&mut app);
+        assert_ne!(shaken, Vec2::ZERO);
+        assert!(shaken.length() <= MAX_SHAKE * DAMAGE_TRAUMA * DAMAGE_TRAUMA * 2f32.sqrt());
+        update(&mut app, 5);
+        assert_eq!(camera_position(&mut app), Vec2::ZERO);
+    }
+
+    #[test]
+    fn it_does_not_shake_for_hits_taken_while_invulnerable() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        set_target(&mut app, target);
+        app.update();
+        inflict_damage(&mut app, target, 1);
+        app.update();
+        let trauma = app.world.resource::<CameraEffects>().trauma;
+        inflict_damage(&mut app, target, 1);
+        app.update();
+        assert!(app.world.resource::<CameraEffects>().trauma < trauma);
+        assert_eq!(app.world.get::<Health>(target).unwrap().current, PLAYER_HEALTH - 1);
+    }
+
+    #[test]
+    fn it_looks_ahead_where_the_target_aims() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        app.world.spawn((CursorIndicator, TransformBundle::from_transform(Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_2)))))
+            .set_parent(target);
+        set_target(&mut app, target);
+        update(&mut app, 10);
+        let effects = app.world.resource::<CameraEffects>();
+        assert!(effects.look_ahead.y > 0. && effects.look_ahead.x.abs() < 0.01, "look-ahead is {}", effects.look_ahead);
+        let position = camera_position(&mut app);
+        assert!(position.y > 0. && position.x.abs() < 0.01, "camera at {position}");
+    }
+
+    #[test]
+    fn it_keeps_the_view_inside_the_bounds() {
+        let bounds = Rect::new(0., 0., 100., 50.);
+        assert_eq!(clamp_to_bounds(Vec2::new(-40., 10.), bounds, Vec2::new(20., 10.)), Vec2::new(20., 10.));
+        assert_eq!(clamp_to_bounds(Vec2::new(90., 45.), bounds, Vec2::new(20., 10.)), Vec2::new(80., 40.));
+        assert_eq!(clamp_to_bounds(Vec2::new(90., 45.), bounds, Vec2::new(20., 40.)), Vec2::new(80., 25.));
+    }
+
+    #[test]
+    fn it_limits_the_bounds_to_the_camera_reach_layer() {
+        let level = Level {
+            px_wid: 64,
+            px_hei: 32,
+            layer_instances: Some(vec![LayerInstance {
+                identifier: CAMERA_REACH_LAYER.to_string(),
+                c_wid: 4,
+                c_hei: 2,
+                grid_size: 16,
+                int_grid_csv: vec![0, 1, 1, 0, 0, 0, 1, 0],
+                ..default()
+            }]),
+            ..default()
+        };
+        assert_eq!(level_camera_bounds(&level, Vec2::new(100., 0.)), Rect::new(116., 0., 148., 32.));
+        let open_level = Level { px_wid: 64, px_hei: 32, ..default() };
+        assert_eq!(level_camera_bounds(&open_level, Vec2::new(100., 0.)), Rect::new(100., 0., 164., 32.));
+    }
+
+    fn spawn_target(app: &mut App, translation: Vec3) -> Entity {
+        let target = app.world.spawn((
+            Health::new(PLAYER_HEALTH),
+            TransformBundle::from_transform(Transform::from_translation(translation)),
+        )).id();
+        app.update();
+        target
+    }
+
+    fn set_target(app: &mut App, target: Entity) {
+        let mut controller = app.world.query::<&mut CameraController>().single_mut(&mut app.world);
+        controller.target = Some(target);
+        controller.snap = true;
+    }
+
+    fn camera_position(app: &mut App) -> Vec2 {
+        app.world.query_filtered::<&Transform, With<Camera>>().single(&app.world).translation.truncate()
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_state::<GameState>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_asset::<LdtkAsset>()
+            .add_asset::<LdtkLevel>()
+            .insert_resource(LevelSelection::Index(0))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+            .add_event::<GameLogEvent>()
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_plugin(HealthPlugin)
+            .add_plugin(CameraPlugin);
+        enter_game(&mut app);
+        app
+    }
 }
 
 pub fn get_default_window() -> Window {
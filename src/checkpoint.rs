@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::CollisionEvent;
+use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory};
+use crate::game_state::GameState;
+use crate::health::RespawnPoint;
+use crate::localization::Localization;
+use crate::physics_bundle::ObjectPhysicsBundle;
+use crate::player::Player;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_ldtk_entity::<CheckpointBundle>("Checkpoint")
+            .add_system(reach_checkpoints.in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Checkpoint;
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct CheckpointBundle {
+    pub checkpoint: Checkpoint,
+    /// The green wall banner from the tileset.
+    #[bundle]
+    #[sprite_sheet_bundle("dungeon/0x72_DungeonTilesetII_v1.4.png", 16.0, 16.0, 32, 32, 0.0, 0.0, 97)]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    #[bundle]
+    pub physics: ObjectPhysicsBundle,
+}
+
+fn reach_checkpoints(
+    mut collision_ev: EventReader<CollisionEvent>,
+    checkpoint_q: Query<&Transform, With<Checkpoint>>,
+    mut player_q: Query<&mut RespawnPoint, With<Player>>,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
+) {
+    for event in collision_ev.iter() {
+        let CollisionEvent::Started(a, b, _) = *event else { continue; };
+        let (checkpoint, player) = if checkpoint_q.contains(a) { (a, b) } else { (b, a) };
+        let (Ok(transform), Ok(mut respawn_point)) = (checkpoint_q.get(checkpoint), player_q.get_mut(player)) else { continue; };
+        let translation = transform.translation.truncate().extend(respawn_point.0.z);
+        if respawn_point.0 == translation { continue; }
+        respawn_point.0 = translation;
+        log.send(GameLogEvent::new(LogCategory::System, localization.get("log.checkpoint")));
+    }
+}
+
+#[cfg(test)]
+mod checkpoint_test {
+    use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
+    use super::*;
+    use crate::localization::LocalizationPlugin;
+    use crate::test_utils::{enter_game, LoadTestPlugins};
+
+    #[test]
+    fn it_moves_the_respawn_point_to_touched_checkpoints() {
+        let mut app = setup();
+        let player = app.world.spawn((Player, RespawnPoint(Vec3::new(0., 0., 5.)))).id();
+        let checkpoint = app.world.spawn((Checkpoint, TransformBundle::from_transform(Transform::from_xyz(40., 8., 1.)))).id();
+        app.world.send_event(CollisionEvent::Started(player, checkpoint, CollisionEventFlags::SENSOR));
+        app.update();
+        assert_eq!(app.world.get::<RespawnPoint>(player).unwrap().0, Vec3::new(40., 8., 5.));
+        let logs = app.world.resource::<Events<GameLogEvent>>();
+        assert_eq!(logs.iter_current_update_events().next().unwrap().text(), "Checkpoint reached.");
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
+            .add_event::<CollisionEvent>()
+            .add_plugin(CheckpointPlugin);
+        enter_game(&mut app);
+        app
+    }
+}
@@ -1,25 +1,27 @@
 use bevy::app::App;
-use bevy::prelude::{Added, Axis, Camera, Commands, Component, CursorMoved, default, Entity,
-                    EventReader, GamepadAxis, GlobalTransform, Plugin, Query, Res, SpriteBundle,
-                    Transform, Vec3, Window, With};
+use bevy::prelude::{Added, Camera, Commands, Component, CursorMoved, default, Entity,
+                    EventReader, GlobalTransform, IntoSystemConfigs, OnUpdate, Plugin,
+                    Query, Res, SpriteBundle, Transform, Vec3, Window, With};
 use bevy::asset::AssetServer;
 use bevy::hierarchy::BuildChildren;
 use bevy::math::{Quat, Vec2};
 use bevy::window::PrimaryWindow;
-use crate::gamepad;
-use crate::gamepad::MyGamepad;
+use crate::actions::{Action, ActionInput, ActionMap, Binding};
+use crate::game_state::GameState;
 use crate::player::Player;
 
 #[derive(Component)]
-struct CursorIndicator;
+pub struct CursorIndicator;
 
 pub struct CursorIndicatorPlugin;
 
 impl Plugin for CursorIndicatorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(spawn_cursor_indicator)
-            .add_system(my_gamepad_system)
-            .add_system(my_cursor_system);
+        app.add_systems((
+            spawn_cursor_indicator,
+            my_gamepad_system,
+            my_cursor_system,
+        ).in_set(OnUpdate(GameState::InGame)));
     }
 }
 
@@ -40,13 +42,10 @@ fn spawn_cursor_indicator(
 }
 
 fn my_gamepad_system(
-    axes: Res<Axis<GamepadAxis>>,
-    gamepad_res: Option<Res<MyGamepad>>,
+    actions: ActionInput,
     mut indicator_q: Query<&mut Transform, With<CursorIndicator>>,
 ) {
-    if gamepad_res.is_none() { return; }
-    let my_gamepad = gamepad_res.unwrap().0;
-    let direction = gamepad::get_right_axis_direction(axes, my_gamepad);
+    let direction = actions.axis(Action::Aim);
     if direction.length() == 0. { return; }
     for mut transform in indicator_q.iter_mut() {
         transform.rotation = get_rotation_from_to(Vec2::ZERO, direction);
@@ -54,12 +53,14 @@ fn my_gamepad_system(
 }
 
 fn my_cursor_system(
+    action_map: Res<ActionMap>,
     windows_query: Query<&Window, With<PrimaryWindow>>,
     cursor_evr: EventReader<CursorMoved>,
     q_camera: Query<(&Camera, &GlobalTransform)>,
     mut indicator_q: Query<(&mut Transform, &GlobalTransform), With<CursorIndicator>>,
 ) {
     if cursor_evr.len() == 0 || q_camera.is_empty() { return; }
+    if !action_map.is_bound(Action::Aim, &Binding::Cursor) { return; }
     let (camera, camera_transform) = q_camera.single();
     let wnd = windows_query.single();
     if let Some(screen_pos) = wnd.cursor_position() {
@@ -94,10 +95,12 @@ mod indicator_cursor_test {
     use super::*;
     use bevy::prelude::*;
     use bevy::prelude::GamepadAxisType::{RightStickX, RightStickY};
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use bevy_ecs_ldtk::prelude::*;
     use crate::camera::CameraPlugin;
     use crate::gamepad::GamepadPlugin;
     use crate::player::Player;
-    use crate::test_utils::{connect_test_gamepad, LoadTestPlugins, update};
+    use crate::test_utils::{connect_test_gamepad, enter_game, LoadTestPlugins, update};
 
     #[test]
     fn it_spawns_indicator_as_child_of_player() {
@@ -132,6 +135,16 @@ mod indicator_cursor_test {
         assert_eq!(cursor_transform.rotation.xyz(), Vec3::new(0., 0., 1.));
     }
 
+    #[test]
+    fn indicator_ignores_mouse_when_cursor_aim_is_unbound() {
+        let mut app = setup();
+        app.world.resource_mut::<ActionMap>().unbind(Action::Aim, &Binding::Cursor);
+        set_cursor_position(&mut app);
+        update(&mut app, 1);
+        let cursor_transform = get_cursor_transform(&mut app);
+        assert_eq!(cursor_transform.rotation.xyz(), Vec3::new(0., 0., 0.));
+    }
+
     #[test]
     fn it_looks_towards_right_joystick_direction() {
         let mut app = setup();
@@ -196,12 +209,18 @@ mod indicator_cursor_test {
     fn setup() -> App {
         let mut app = App::new();
         app.add_plugins(LoadTestPlugins)
+            .add_state::<GameState>()
             .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_asset::<LdtkAsset>()
+            .add_asset::<LdtkLevel>()
+            .insert_resource(LevelSelection::Index(0))
             .add_plugin(CameraPlugin)
             .add_plugin(GamepadPlugin)
             .add_plugin(CursorIndicatorPlugin);
         app.world.spawn(Player);
-        app.update();
+        enter_game(&mut app);
         app
     }
 }
@@ -0,0 +1,656 @@
+use std::collections::HashMap;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use bevy_ecs_ldtk::prelude::*;
+use serde::Deserialize;
+use crate::actions::{Action, ActionInput};
+use crate::door::Door;
+use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory};
+use crate::game_state::GameState;
+use crate::interaction::{Interaction, InteractionAppExt, InteractionEvent, InteractionSet};
+use crate::inventory::{Inventory, Item};
+use crate::localization::Localization;
+use crate::physics_bundle::ObjectPhysicsBundle;
+use crate::save_game::GameProgress;
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<DialogueTree>()
+            .init_asset_loader::<DialogueLoader>()
+            .register_ldtk_entity::<NpcBundle>("Npc")
+            .register_interaction::<Npc>()
+            .add_system(close_dialogue.in_schedule(OnExit(GameState::InGame)))
+            .add_systems((
+                setup_npcs,
+                start_dialogue.after(InteractionSet::React),
+                advance_dialogue.in_set(DialogueSet),
+                spawn_dialogue_box,
+                update_dialogue_box,
+            ).chain().in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+const DIALOGUE_FONT: &str = "fonts/kongtext/kongtext.ttf";
+const CHARACTERS: &str = "dungeon/characters.png";
+/// `dungeon/characters.png` holds one 16x32 character per row, its first frame facing right.
+const CHARACTER_COLUMNS: usize = 9;
+const CHARACTER_ROWS: usize = 8;
+const CHARACTER_SIZE: Vec2 = Vec2::new(16., 32.);
+const PORTRAIT_SCALE: f32 = 3.;
+/// Characters of a line revealed per second.
+const TYPEWRITER_SPEED: f32 = 40.;
+const SELECTED_CHOICE: Color = Color::rgb(1., 0.85, 0.35);
+
+/// A conversation, read from a `.dialogue.ron` file and reloaded when the file changes.
+#[derive(TypeUuid, Deserialize, Debug, PartialEq)]
+#[uuid = "0f3c9a7e-52d1-4b8e-a6f4-3e9d1c7b2a58"]
+pub struct DialogueTree {
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+/// A line said by the NPC, followed by the player's replies or by the `next` line.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DialogueNode {
+    pub text: String,
+    /// Who says the line, the NPC without one.
+    #[serde(default)]
+    pub speaker: Option<String>,
+    /// Row of `dungeon/characters.png` to show, the NPC's without one.
+    #[serde(default)]
+    pub portrait: Option<usize>,
+    /// Applied when the line is shown.
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    /// Where to go without choices; the dialogue ends without either.
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DialogueChoice {
+    pub text: String,
+    /// The dialogue ends when picking a choice without one.
+    #[serde(default)]
+    pub next: Option<String>,
+    /// Left out of the choices unless it holds.
+    #[serde(default)]
+    pub condition: Option<DialogueCondition>,
+    /// Applied when the choice is picked.
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum DialogueCondition {
+    Flag(String),
+    NotFlag(String),
+    /// The player talking carries the item.
+    HasItem(Item),
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum DialogueEffect {
+    SetFlag(String),
+    GiveItem(Item),
+    TakeItem(Item),
+    /// Unlocks and opens the door with this LDtk iid.
+    OpenDoor(String),
+}
+
+impl DialogueTree {
+    pub fn parse(bytes: &[u8]) -> Result<DialogueTree, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+
+    /// Choices of `node` whose conditions hold.
+    pub fn choices<'a>(&'a self, node: &str, holds: impl Fn(&DialogueCondition) -> bool) -> Vec<&'a DialogueChoice> {
+        self.nodes.get(node).map_or(Vec::new(), |node| node.choices.iter()
+            .filter(|choice| choice.condition.as_ref().map_or(true, &holds))
+            .collect())
+    }
+}
+
+#[derive(Default)]
+pub struct DialogueLoader;
+
+impl AssetLoader for DialogueLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let tree = DialogueTree::parse(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(tree));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue.ron"]
+    }
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct NpcBundle {
+    #[from_entity_instance]
+    pub npc: Npc,
+    #[bundle]
+    #[sprite_sheet_bundle("dungeon/characters.png", 16.0, 32.0, 9, 8, 0.0, 0.0, 0)]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    #[bundle]
+    pub physics: ObjectPhysicsBundle,
+}
+
+#[derive(Component, Default, Debug)]
+pub struct Npc {
+    pub name: String,
+    /// Asset path of its [DialogueTree].
+    pub dialogue: String,
+    /// Row of `dungeon/characters.png` it is drawn with.
+    pub portrait: usize,
+}
+
+impl Interaction for Npc {
+    fn interact(&mut self) {}
+
+    fn prompt(&self, text: &Localization) -> String {
+        text.get("prompt.talk").to_string()
+    }
+}
+
+impl From<&EntityInstance> for Npc {
+    fn from(value: &EntityInstance) -> Self {
+        let field = |identifier: &str| value.field_instances.iter()
+            .find(|f| f.identifier == identifier)
+            .map(|f| &f.value);
+        let string = |identifier: &str| match field(identifier) {
+            Some(FieldValue::String(Some(value))) => value.clone(),
+            _ => String::new(),
+        };
+        let portrait = match field("portrait") {
+            Some(FieldValue::Int(Some(row))) => (*row).clamp(0, CHARACTER_ROWS as i32 - 1) as usize,
+            _ => 0,
+        };
+        Npc { name: string("name"), dialogue: string("dialogue"), portrait }
+    }
+}
+
+/// The loaded [DialogueTree] of an [Npc].
+#[derive(Component)]
+pub struct NpcDialogue(pub Handle<DialogueTree>);
+
+/// Systems reading input the dialogue box takes over, like the pause key it closes on, run before it.
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DialogueSet;
+
+/// Run condition for gameplay controls, which share keys and buttons with the dialogue box.
+pub fn no_dialogue(dialogue: Option<Res<ActiveDialogue>>) -> bool {
+    dialogue.is_none()
+}
+
+/// The conversation on screen. Interacting with NPCs does nothing else meanwhile.
+#[derive(Resource, Debug)]
+pub struct ActiveDialogue {
+    pub npc: Entity,
+    pub actor: Entity,
+    tree: Handle<DialogueTree>,
+    node: String,
+    /// Whether the effects of `node` were applied and its line logged.
+    entered: bool,
+    /// Characters of the line shown so far.
+    revealed: f32,
+    selected: usize,
+}
+
+impl ActiveDialogue {
+    fn go_to(&mut self, node: String) {
+        self.node = node;
+        self.entered = false;
+        self.revealed = 0.;
+        self.selected = 0;
+    }
+}
+
+/// What dialogue conditions read and dialogue effects change.
+#[derive(SystemParam)]
+struct DialogueWorld<'w, 's> {
+    progress: ResMut<'w, GameProgress>,
+    inventory_q: Query<'w, 's, &'static mut Inventory>,
+    door_q: Query<'w, 's, &'static mut Door>,
+}
+
+impl DialogueWorld<'_, '_> {
+    fn holds(&self, condition: &DialogueCondition, actor: Entity) -> bool {
+        match condition {
+            DialogueCondition::Flag(flag) => self.progress.dialogue_flags.contains(flag),
+            DialogueCondition::NotFlag(flag) => !self.progress.dialogue_flags.contains(flag),
+            DialogueCondition::HasItem(item) => self.inventory_q.get(actor)
+                .map_or(false, |inventory| inventory.count(item) > 0),
+        }
+    }
+
+    fn apply(&mut self, effect: &DialogueEffect, actor: Entity) {
+        match effect {
+            DialogueEffect::SetFlag(flag) => { self.progress.dialogue_flags.insert(flag.clone()); }
+            DialogueEffect::GiveItem(item) => {
+                let Ok(mut inventory) = self.inventory_q.get_mut(actor) else { return; };
+                if inventory.add(item.clone(), 1).is_err() {
+                    warn!("No room for {item:?} given in dialogue");
+                }
+            }
+            DialogueEffect::TakeItem(item) => {
+                let Ok(mut inventory) = self.inventory_q.get_mut(actor) else { return; };
+                let _ = inventory.remove(item, 1);
+            }
+            DialogueEffect::OpenDoor(iid) => {
+                for mut door in self.door_q.iter_mut().filter(|door| door.iid() == iid) {
+                    door.unlock();
+                    door.set_open(true);
+                }
+            }
+        }
+    }
+}
+
+fn setup_npcs(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut npc_q: Query<(Entity, &Npc, &mut TextureAtlasSprite), Added<Npc>>,
+) {
+    for (entity, npc, mut sprite) in npc_q.iter_mut() {
+        sprite.index = npc.portrait * CHARACTER_COLUMNS;
+        commands.entity(entity).insert(NpcDialogue(asset_server.load(npc.dialogue.as_str())));
+    }
+}
+
+fn start_dialogue(
+    mut commands: Commands,
+    mut interaction_ev: EventReader<InteractionEvent>,
+    active: Option<Res<ActiveDialogue>>,
+    npc_q: Query<&NpcDialogue>,
+    trees: Res<Assets<DialogueTree>>,
+) {
+    let mut talking = active.is_some();
+    for event in interaction_ev.iter() {
+        if talking { continue; }
+        let Ok(dialogue) = npc_q.get(event.target) else { continue; };
+        let Some(tree) = trees.get(&dialogue.0) else { continue; };
+        talking = true;
+        commands.insert_resource(ActiveDialogue {
+            npc: event.target,
+            actor: event.actor,
+            tree: dialogue.0.clone(),
+            node: tree.start.clone(),
+            entered: false,
+            revealed: 0.,
+            selected: 0,
+        });
+    }
+}
+
+fn advance_dialogue(
+    mut commands: Commands,
+    actions: ActionInput,
+    time: Res<Time>,
+    dialogue: Option<ResMut<ActiveDialogue>>,
+    trees: Res<Assets<DialogueTree>>,
+    npc_q: Query<&Npc>,
+    mut world: DialogueWorld,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
+) {
+    let Some(mut dialogue) = dialogue else { return; };
+    // The tree may have been edited away from under the current line.
+    let (Some(tree), Ok(npc)) = (trees.get(&dialogue.tree), npc_q.get(dialogue.npc)) else {
+        commands.remove_resource::<ActiveDialogue>();
+        return;
+    };
+    let Some(node) = tree.nodes.get(&dialogue.node) else {
+        commands.remove_resource::<ActiveDialogue>();
+        return;
+    };
+    let actor = dialogue.actor;
+    if !dialogue.entered {
+        dialogue.entered = true;
+        node.effects.iter().for_each(|effect| world.apply(effect, actor));
+        let speaker = node.speaker.as_deref().unwrap_or(&npc.name);
+        let args = [("speaker", speaker), ("line", node.text.as_str())];
+        log.send(GameLogEvent::from_template(LogCategory::Dialogue, localization.get("log.dialogue_line"), &args));
+        return;
+    }
+    if actions.just_pressed(Action::MenuBack) {
+        commands.remove_resource::<ActiveDialogue>();
+        return;
+    }
+    let length = node.text.chars().count() as f32;
+    if dialogue.revealed < length {
+        dialogue.revealed = (dialogue.revealed + time.delta_seconds() * TYPEWRITER_SPEED).min(length);
+    }
+    let choices = tree.choices(&dialogue.node, |condition| world.holds(condition, actor));
+    if !choices.is_empty() {
+        let count = choices.len();
+        if actions.just_pressed(Action::MenuUp) { dialogue.selected = (dialogue.selected + count - 1) % count; }
+        if actions.just_pressed(Action::MenuDown) { dialogue.selected = (dialogue.selected + 1) % count; }
+        dialogue.selected = dialogue.selected.min(count - 1);
+    }
+    if !actions.just_pressed(Action::MenuConfirm) && !actions.just_pressed(Action::Interact) { return; }
+    if dialogue.revealed < length {
+        dialogue.revealed = length;
+        return;
+    }
+    let next = match choices.get(dialogue.selected) {
+        Some(choice) => {
+            choice.effects.iter().for_each(|effect| world.apply(effect, actor));
+            log.send(GameLogEvent::from_template(LogCategory::Dialogue, localization.get("log.dialogue_reply"), &[("reply", &choice.text)]));
+            choice.next.clone()
+        }
+        None => node.next.clone(),
+    };
+    match next {
+        Some(next) => dialogue.go_to(next),
+        None => commands.remove_resource::<ActiveDialogue>(),
+    }
+}
+
+fn close_dialogue(mut commands: Commands, box_q: Query<Entity, With<DialogueBox>>) {
+    commands.remove_resource::<ActiveDialogue>();
+    for dialogue_box in box_q.iter() {
+        commands.entity(dialogue_box).despawn_recursive();
+    }
+}
+
+#[derive(Component)]
+struct DialogueBox;
+
+#[derive(Component)]
+struct DialogueSpeaker;
+
+#[derive(Component)]
+struct DialogueLine;
+
+#[derive(Component)]
+struct DialogueChoices;
+
+#[derive(Component)]
+struct DialoguePortrait;
+
+fn spawn_dialogue_box(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    dialogue: Option<Res<ActiveDialogue>>,
+    box_q: Query<Entity, With<DialogueBox>>,
+) {
+    match (dialogue.is_some(), box_q.get_single()) {
+        (true, Err(_)) => {}
+        (false, Ok(dialogue_box)) => {
+            commands.entity(dialogue_box).despawn_recursive();
+            return;
+        }
+        _ => return,
+    }
+    let font = asset_server.load(DIALOGUE_FONT);
+    let style = |font_size: f32, color: Color| TextStyle { font: font.clone(), font_size, color };
+    let characters: Handle<Image> = asset_server.load(CHARACTERS);
+    let portrait_size = CHARACTER_SIZE * PORTRAIT_SCALE;
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect { left: Val::Percent(20.), bottom: Val::Px(16.), ..default() },
+            size: Size::new(Val::Percent(60.), Val::Auto),
+            padding: UiRect::all(Val::Px(8.)),
+            ..default()
+        },
+        background_color: Color::rgba(0.05, 0.05, 0.08, 0.9).into(),
+        ..default()
+    }, DialogueBox)).with_children(|parent| {
+        // UI images can't show a single atlas frame, so the whole sheet is offset inside a clipping node.
+        parent.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(portrait_size.x), Val::Px(portrait_size.y)),
+                margin: UiRect::right(Val::Px(8.)),
+                flex_shrink: 0.,
+                overflow: Overflow::Hidden,
+                ..default()
+            },
+            ..default()
+        }).with_children(|frame| {
+            frame.spawn((ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(
+                        Val::Px(portrait_size.x * CHARACTER_COLUMNS as f32),
+                        Val::Px(portrait_size.y * CHARACTER_ROWS as f32),
+                    ),
+                    ..default()
+                },
+                image: UiImage { texture: characters, ..default() },
+                ..default()
+            }, DialoguePortrait));
+        });
+        parent.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                flex_grow: 1.,
+                ..default()
+            },
+            ..default()
+        }).with_children(|column| {
+            column.spawn((TextBundle::from_section("", style(12., SELECTED_CHOICE)), DialogueSpeaker));
+            column.spawn((TextBundle::from_section("", style(10., Color::WHITE)).with_style(Style {
+                margin: UiRect::vertical(Val::Px(6.)),
+                ..default()
+            }), DialogueLine));
+            column.spawn((TextBundle::default(), DialogueChoices));
+        });
+    });
+}
+
+fn update_dialogue_box(
+    asset_server: Res<AssetServer>,
+    dialogue: Option<Res<ActiveDialogue>>,
+    trees: Res<Assets<DialogueTree>>,
+    npc_q: Query<&Npc>,
+    world: DialogueWorld,
+    mut speaker_q: Query<&mut Text, (With<DialogueSpeaker>, Without<DialogueLine>, Without<DialogueChoices>)>,
+    mut line_q: Query<&mut Text, (With<DialogueLine>, Without<DialogueChoices>)>,
+    mut choices_q: Query<&mut Text, With<DialogueChoices>>,
+    mut portrait_q: Query<&mut Style, With<DialoguePortrait>>,
+) {
+    let Some(dialogue) = dialogue else { return; };
+    let (Some(tree), Ok(npc)) = (trees.get(&dialogue.tree), npc_q.get(dialogue.npc)) else { return; };
+    let Some(node) = tree.nodes.get(&dialogue.node) else { return; };
+    for mut text in speaker_q.iter_mut() {
+        text.sections[0].value = node.speaker.clone().unwrap_or(npc.name.clone());
+    }
+    for mut text in line_q.iter_mut() {
+        text.sections[0].value = node.text.chars().take(dialogue.revealed as usize).collect();
+    }
+    let portrait = node.portrait.unwrap_or(npc.portrait).min(CHARACTER_ROWS - 1);
+    for mut style in portrait_q.iter_mut() {
+        style.position.top = Val::Px(-(portrait as f32) * CHARACTER_SIZE.y * PORTRAIT_SCALE);
+    }
+    let finished = dialogue.revealed as usize >= node.text.chars().count();
+    let choices = match finished {
+        true => tree.choices(&dialogue.node, |condition| world.holds(condition, dialogue.actor)),
+        false => Vec::new(),
+    };
+    let font = asset_server.load(DIALOGUE_FONT);
+    for mut text in choices_q.iter_mut() {
+        text.sections = choices.iter().enumerate().map(|(index, choice)| {
+            let (marker, color) = match index == dialogue.selected {
+                true => ("> ", SELECTED_CHOICE),
+                false => ("  ", Color::GRAY),
+            };
+            TextSection::new(format!("{marker}{}\n", choice.text), TextStyle { font: font.clone(), font_size: 10., color })
+        }).collect();
+    }
+}
+
+#[cfg(test)]
+mod dialogue_test {
+    use std::collections::HashSet;
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy_rapier2d::prelude::Velocity;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::localization::LocalizationPlugin;
+    use crate::menu::MenuPlugin;
+    use crate::movement::MyInputPlugin;
+    use crate::player::Player;
+    use crate::remote_save::{FetchRemoteSave, RemoteSaveStatus};
+    use crate::test_utils::{enter_game, LoadTestPlugins, update};
+
+    #[test]
+    fn it_parses_the_shipped_dialogues() {
+        let tree = DialogueTree::parse(include_bytes!("../assets/dialogue/gravekeeper.dialogue.ron")).unwrap();
+        assert!(tree.nodes.contains_key(&tree.start));
+        for node in tree.nodes.values() {
+            let targets = node.choices.iter().filter_map(|choice| choice.next.as_ref()).chain(node.next.as_ref());
+            for target in targets {
+                assert!(tree.nodes.contains_key(target), "missing dialogue node {target}");
+            }
+        }
+    }
+
+    #[test]
+    fn it_leaves_out_choices_whose_conditions_fail() {
+        let tree = test_tree();
+        let flags = HashSet::from(["met".to_string()]);
+        let holds = |condition: &DialogueCondition| match condition {
+            DialogueCondition::Flag(flag) => flags.contains(flag),
+            DialogueCondition::NotFlag(flag) => !flags.contains(flag),
+            DialogueCondition::HasItem(_) => false,
+        };
+        let choices: Vec<_> = tree.choices("start", holds).iter().map(|choice| choice.text.as_str()).collect();
+        assert_eq!(choices, ["Bye."]);
+    }
+
+    #[test]
+    fn it_talks_through_a_branch_and_applies_its_effects() {
+        let (mut app, player, npc) = setup();
+        app.world.send_event(InteractionEvent { actor: player, target: npc });
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<ActiveDialogue>().node, "start");
+        // Once to reveal the whole line, once to pick the selected choice.
+        press_key(&mut app, KeyCode::Return);
+        release_key(&mut app, KeyCode::Return);
+        assert!(app.world.resource::<ActiveDialogue>().revealed > 0.);
+        press_key(&mut app, KeyCode::Return);
+        release_key(&mut app, KeyCode::Return);
+        assert_eq!(app.world.resource::<ActiveDialogue>().node, "gift");
+        app.update();
+        assert!(app.world.resource::<GameProgress>().dialogue_flags.contains("met"));
+        assert_eq!(app.world.get::<Inventory>(player).unwrap().count(&Item::Key("crypt".to_string())), 1);
+        press_key(&mut app, KeyCode::Return);
+        release_key(&mut app, KeyCode::Return);
+        press_key(&mut app, KeyCode::Return);
+        release_key(&mut app, KeyCode::Return);
+        assert!(app.world.get_resource::<ActiveDialogue>().is_none());
+    }
+
+    #[test]
+    fn it_logs_every_line_shown() {
+        let (mut app, player, npc) = setup();
+        app.world.send_event(InteractionEvent { actor: player, target: npc });
+        let mut reader = app.world.resource::<Events<GameLogEvent>>().get_reader();
+        update(&mut app, 2);
+        let logs = app.world.resource::<Events<GameLogEvent>>();
+        let lines: Vec<_> = reader.iter(logs).map(|entry| (entry.category, entry.text())).collect();
+        assert_eq!(lines, [(LogCategory::Dialogue, "Gravekeeper: Hello.".to_string())]);
+    }
+
+    #[test]
+    fn it_closes_the_dialogue_on_back() {
+        let (mut app, player, npc) = setup();
+        app.world.send_event(InteractionEvent { actor: player, target: npc });
+        update(&mut app, 2);
+        press_key(&mut app, KeyCode::Escape);
+        app.update();
+        assert!(app.world.get_resource::<ActiveDialogue>().is_none());
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::InGame);
+    }
+
+    #[test]
+    fn it_keeps_the_player_still_while_choosing() {
+        let (mut app, player, npc) = setup();
+        app.world.entity_mut(player).insert((Player, Velocity::default()));
+        app.world.send_event(InteractionEvent { actor: player, target: npc });
+        update(&mut app, 2);
+        press_key(&mut app, KeyCode::S);
+        app.update();
+        assert_eq!(app.world.resource::<ActiveDialogue>().selected, 1);
+        assert_eq!(app.world.get::<Velocity>(player).unwrap().linvel, Vec2::ZERO);
+    }
+
+    fn test_tree() -> DialogueTree {
+        DialogueTree::parse(br#"(
+            start: "start",
+            nodes: {
+                "start": (
+                    text: "Hello.",
+                    choices: [
+                        (text: "Anything for me?", next: Some("gift"), condition: Some(NotFlag("met"))),
+                        (text: "Bye.", next: None),
+                    ],
+                ),
+                "gift": (
+                    text: "Take it.",
+                    effects: [SetFlag("met"), GiveItem(Key("crypt"))],
+                ),
+            },
+        )"#).unwrap()
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Pressed,
+        });
+        app.update();
+    }
+
+    fn release_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Released,
+        });
+        app.update();
+    }
+
+    fn setup() -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
+            .add_event::<InteractionEvent>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_event::<FetchRemoteSave>()
+            .init_resource::<RemoteSaveStatus>()
+            .add_plugin(MenuPlugin)
+            .add_plugin(MyInputPlugin)
+            .init_resource::<GameProgress>()
+            .add_plugin(DialoguePlugin);
+        let tree = app.world.resource_mut::<Assets<DialogueTree>>().add(test_tree());
+        let player = app.world.spawn(Inventory::default()).id();
+        let npc = app.world.spawn((
+            Npc { name: "Gravekeeper".to_string(), dialogue: String::new(), portrait: 3 },
+            NpcDialogue(tree),
+        )).id();
+        enter_game(&mut app);
+        (app, player, npc)
+    }
+}
@@ -1,10 +1,12 @@
-use bevy::input::Input;
-use bevy::prelude::{Added, App, BuildChildren, Bundle, Changed, Children, Commands, Component, Entity, GamepadButton, GamepadButtonType, KeyCode, Mut, Or, Plugin, Query, Res, SpriteSheetBundle, Text, TextureAtlasSprite, With};
+use bevy::prelude::{App, BuildChildren, Bundle, Changed, Children, Commands, Component, Entity, EventReader, IntoSystemConfig, Mut, OnUpdate, Plugin, Query, Reflect, ReflectComponent, Res, SpriteSheetBundle, TextureAtlasSprite};
 use bevy_ecs_ldtk::prelude::{EntityInstance, LdtkEntity, LdtkEntityAppExt};
 use bevy_ecs_ldtk::ldtk::FieldValue;
 use bevy_rapier2d::prelude::{Collider};
-use crate::gamepad::MyGamepad;
-use crate::interaction::{Interaction, Interactive, InteractiveText};
+use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory, LogSeverity};
+use crate::game_state::GameState;
+use crate::interaction::{Interaction, InteractionAppExt, InteractionEvent, InteractionSet};
+use crate::inventory::{Inventory, Item};
+use crate::localization::Localization;
 use crate::physics_bundle::ObjectPhysicsBundle;
 
 pub struct DoorPlugin;
@@ -13,9 +15,13 @@ impl Plugin for DoorPlugin {
     fn build(&self, app: &mut App) {
         app
             .register_ldtk_entity::<DoorBundle>("Door")
-            .add_system(door_interaction)
-            .add_system(door_interaction_text)
-            .add_system(update_changed_doors);
+            .register_interaction::<Door>()
+            .add_system(unlock_doors
+                .after(InteractionSet::Dispatch)
+                .before(InteractionSet::React)
+                .in_set(OnUpdate(GameState::InGame)))
+            .add_system(log_door_toggles.after(InteractionSet::React).in_set(OnUpdate(GameState::InGame)))
+            .add_system(update_changed_doors.in_set(OnUpdate(GameState::InGame)));
     }
 }
 
@@ -31,83 +37,111 @@ pub struct DoorBundle {
     pub physics: ObjectPhysicsBundle,
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Door {
     is_open: bool,
+    iid: String,
+    locked: bool,
+    /// The [Item::Key] that unlocks this door, if any does.
+    key_id: Option<String>,
 }
 
 impl Door {
     pub fn is_open(&self) -> bool {
         return self.is_open;
     }
+
+    pub fn set_open(&mut self, is_open: bool) {
+        self.is_open = is_open;
+    }
+
+    /// LDtk entity iid, stable across respawns of the level.
+    pub fn iid(&self) -> &str {
+        &self.iid
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    pub fn unlock(&mut self) {
+        self.locked = false;
+    }
 }
 
 impl Interaction for Door {
     fn interact(&mut self) {
+        if self.locked { return; }
         self.is_open = !self.is_open;
     }
+
+    fn prompt(&self, text: &Localization) -> String {
+        let key = match (self.locked, self.is_open) {
+            (true, _) => "prompt.door_locked",
+            (false, true) => "prompt.door_close",
+            (false, false) => "prompt.door_open",
+        };
+        text.get(key).to_string()
+    }
 }
 
 impl From<&EntityInstance> for Door {
     fn from(value: &EntityInstance) -> Self {
-        let field_instances = &value.field_instances;
-        let default_door = Door { is_open: false };
-        match field_instances.iter()
-            .find(|f| f.identifier == *"is_open") {
-            None => { default_door }
-            Some(field_instance) => {
-                match field_instance.value {
-                    FieldValue::Bool(is_open) => { Door { is_open } }
-                    _ => { default_door }
-                }
-            }
-        }
+        let field = |identifier: &str| value.field_instances.iter()
+            .find(|f| f.identifier == identifier)
+            .map(|f| &f.value);
+        let is_open = matches!(field("is_open"), Some(FieldValue::Bool(true)));
+        let locked = matches!(field("locked"), Some(FieldValue::Bool(true)));
+        let key_id = match field("key_id") {
+            Some(FieldValue::String(key_id)) => key_id.clone(),
+            _ => None,
+        };
+        Door { is_open, iid: value.iid.clone(), locked, key_id }
     }
 }
 
-fn door_interaction_text(
-    interactive_door_q: Query<&Door, Or<(Added<Interactive>, Changed<Door>)>>,
-    mut text_q: Query<&mut Text, With<InteractiveText>>,
+/// Spends a matching key from the actor's [Inventory] on a locked door, before the door reacts.
+fn unlock_doors(
+    mut interaction_ev: EventReader<InteractionEvent>,
+    mut door_q: Query<&mut Door>,
+    mut inventory_q: Query<&mut Inventory>,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
 ) {
-    for door in interactive_door_q.iter() {
-        for mut text in text_q.iter_mut() {
-            if door.is_open() {
-                text.sections[0].value = "[E] Close".to_string();
-            } else {
-                text.sections[0].value = "[E] Open".to_string();
+    for event in interaction_ev.iter() {
+        let Ok(mut door) = door_q.get_mut(event.target) else { continue; };
+        if !door.is_locked() { continue; }
+        let key_id = door.key_id().map(str::to_string);
+        let used_key = match (key_id, inventory_q.get_mut(event.actor)) {
+            (Some(key_id), Ok(mut inventory)) if inventory.remove(&Item::Key(key_id.clone()), 1).is_ok() => Some(key_id),
+            _ => None,
+        };
+        match used_key {
+            Some(key_id) => {
+                door.unlock();
+                log.send(GameLogEvent::from_template(LogCategory::System, localization.get("log.door_unlocked"), &[("key", &key_id)]));
             }
+            None => log.send(GameLogEvent::new(LogCategory::System, localization.get("log.door_locked")).with_severity(LogSeverity::Warning)),
         }
     }
 }
 
-fn door_interaction(
-    mut interactive_door_q: Query<&mut Door, With<Interactive>>,
-    keyboard_input: Res<Input<KeyCode>>,
-    gamepad_input: Res<Input<GamepadButton>>,
-    my_gamepad: Option<Res<MyGamepad>>,
+fn log_door_toggles(
+    mut interaction_ev: EventReader<InteractionEvent>,
+    door_q: Query<&Door>,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
 ) {
-    if !interact_input_just_pressed(&keyboard_input, &gamepad_input, &my_gamepad) { return; }
-    for mut door in interactive_door_q.iter_mut() {
-        door.interact();
-    }
-}
-
-fn interact_input_just_pressed(keyboard_input: &Res<Input<KeyCode>>, gamepad_input: &Res<Input<GamepadButton>>, my_gamepad: &Option<Res<MyGamepad>>) -> bool {
-    let interact_button_just_pressed = interact_button_just_pressed(gamepad_input, my_gamepad);
-    let interact_key_just_pressed = keyboard_input.just_pressed(KeyCode::E);
-    interact_button_just_pressed || interact_key_just_pressed
-}
-
-fn interact_button_just_pressed(gamepad_input: &Res<Input<GamepadButton>>, my_gamepad: &Option<Res<MyGamepad>>) -> bool {
-    if let Some(gp) = my_gamepad {
-        let gamepad = gp.0;
-        let jump_button = GamepadButton {
-            gamepad,
-            button_type: GamepadButtonType::South,
-        };
-        gamepad_input.just_pressed(jump_button)
-    } else {
-        false
+    for event in interaction_ev.iter() {
+        let Ok(door) = door_q.get(event.target) else { continue; };
+        if door.is_locked() { continue; }
+        let key = if door.is_open() { "log.door_opens" } else { "log.door_closes" };
+        log.send(GameLogEvent::new(LogCategory::System, localization.get(key)));
     }
 }
 
@@ -161,20 +195,25 @@ mod doors_test {
     use bevy::input::keyboard::KeyboardInput;
     use bevy::math::Vec3;
     use bevy::prelude::{App, Children, Entity, Gamepad, GamepadButtonType, KeyCode,
-                        Text, TextureAtlasSprite, Transform, With, Without};
+                        Text, TextureAtlasSprite, Transform, Visibility, With, Without};
     use bevy_ecs_ldtk::LevelSelection;
     use bevy_rapier2d::prelude::*;
+    use crate::actions::{ActionPlugin, InputDevice, SettingsPath};
     use crate::door::{Door, DoorPlugin};
+    use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory, LogSeverity};
+    use crate::inventory::{Inventory, Item};
+    use crate::game_state::GameState;
     use crate::gamepad::GamepadPlugin;
-    use crate::interaction::{Interaction, InteractionPlugin, InteractiveText};
+    use crate::interaction::{Interaction, InteractionPlugin, InteractiveText, PromptGlyph};
     use crate::level::LevelPlugin;
+    use crate::localization::{Localization, LocalizationPlugin};
     use crate::player::{Player, PlayerPlugin};
     use crate::test_utils;
     use crate::test_utils::{connect_test_gamepad, LoadTestPlugins};
 
     #[test]
     fn door_has_interaction_to_open() {
-        let mut door = Door { is_open: false };
+        let mut door = Door::default();
         door.interact();
         assert!(door.is_open())
     }
@@ -302,6 +341,93 @@ mod doors_test {
         assert_eq!(text.sections[0].value, "[E] Open")
     }
 
+    #[test]
+    fn it_shows_the_gamepad_glyph_while_the_gamepad_is_in_use() {
+        let mut app = setup();
+        move_player_to_door(&mut app);
+        app.insert_resource(InputDevice::Gamepad);
+        app.update();
+        let text = app.world
+            .query_filtered::<&Text, With<InteractiveText>>()
+            .single(&app.world);
+        assert_eq!(text.sections[0].value, "Close");
+        let (sprite, visibility) = app.world
+            .query_filtered::<(&TextureAtlasSprite, &Visibility), With<PromptGlyph>>()
+            .single(&app.world);
+        assert_eq!(sprite.index, 0);
+        assert_eq!(*visibility, Visibility::Inherited);
+        app.insert_resource(InputDevice::KeyboardMouse);
+        app.update();
+        let text = app.world
+            .query_filtered::<&Text, With<InteractiveText>>()
+            .single(&app.world);
+        assert_eq!(text.sections[0].value, "[E] Close");
+        let visibility = app.world
+            .query_filtered::<&Visibility, With<PromptGlyph>>()
+            .single(&app.world);
+        assert_eq!(*visibility, Visibility::Hidden);
+    }
+
+    #[test]
+    fn it_shows_locked_text_near_locked_door() {
+        let mut app = setup();
+        lock_door(&mut app);
+        move_player_to_door(&mut app);
+        app.update();
+        let text = app.world
+            .query_filtered::<&Text, With<InteractiveText>>()
+            .single(&app.world);
+        assert_eq!(text.sections[0].value, "Locked")
+    }
+
+    #[test]
+    fn it_stays_shut_when_locked_and_player_has_no_key() {
+        let mut app = setup();
+        interact_with_door(&mut app);
+        lock_door(&mut app);
+        move_player_to_door(&mut app);
+        press_interact_key(&mut app);
+        let door = app.world.query::<&Door>().single(&app.world);
+        assert!(door.is_locked());
+        assert!(!door.is_open());
+        let logs = app.world.resource::<Events<GameLogEvent>>();
+        assert_eq!(logs.iter_current_update_events().next().unwrap().text(), "The door is locked.");
+    }
+
+    #[test]
+    fn it_unlocks_with_the_matching_key() {
+        let mut app = setup();
+        interact_with_door(&mut app);
+        lock_door(&mut app);
+        give_player_key(&mut app, "crypt");
+        move_player_to_door(&mut app);
+        press_interact_key(&mut app);
+        let door = app.world.query::<&Door>().single(&app.world);
+        assert!(!door.is_locked());
+        assert!(door.is_open());
+        let inventory = app.world.query::<&Inventory>().single(&app.world);
+        assert_eq!(inventory.count(&Item::Key("crypt".to_string())), 0);
+        let logs = app.world.resource::<Events<GameLogEvent>>();
+        let texts: Vec<_> = logs.iter_current_update_events().map(GameLogEvent::text).collect();
+        assert_eq!(texts, ["Unlocked the door with the crypt key.", "The door opens."]);
+    }
+
+    fn lock_door(app: &mut App) {
+        let mut door = app.world
+            .query::<&mut Door>()
+            .single_mut(&mut app.world);
+        door.locked = true;
+        door.key_id = Some("crypt".to_string());
+        app.update();
+    }
+
+    fn give_player_key(app: &mut App, key_id: &str) {
+        let mut inventory = app.world
+            .query_filtered::<&mut Inventory, With<Player>>()
+            .single_mut(&mut app.world);
+        inventory.add(Item::Key(key_id.to_string()), 1).unwrap();
+    }
+
     fn get_blocking_collider(app: &mut App) -> Result<&Collider, QueryEntityError> {
         let mut blocking_collider = Err(QueryEntityError::NoSuchEntity(Entity::from_raw(0)));
         let mut children_q = app.world
@@ -364,7 +490,13 @@ mod doors_test {
     fn setup() -> App {
         let mut app = App::new();
         app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
             .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
             .add_plugin(InteractionPlugin)
             .add_plugin(GamepadPlugin)
             .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
@@ -373,6 +505,7 @@ mod doors_test {
             .add_plugin(DoorPlugin)
             .insert_resource(LevelSelection::Index(0));
 
+        test_utils::enter_game(&mut app);
         test_utils::update(&mut app, 3);
         app
     }
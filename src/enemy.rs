@@ -0,0 +1,511 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::hash::{Hash, Hasher};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::{Collider, Velocity};
+use serde::Deserialize;
+use crate::animation::{AnimationLibrary, AnimationSheets, AnimationState, CharacterAnimation};
+use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory};
+use crate::game_state::GameState;
+use crate::health::{Damage, Dead, Health, Knockback};
+use crate::localization::Localization;
+use crate::network::is_offline;
+use crate::physics_bundle::CharacterPhysicsBundle;
+use crate::player::Player;
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<ZombieCatalog>()
+            .init_asset_loader::<ZombieCatalogLoader>()
+            .init_resource::<ZombieCatalog>()
+            .init_resource::<ZombieCatalogHandle>()
+            .add_event::<ZombieAttack>()
+            .register_ldtk_entity::<ZombieBundle>("Zombie")
+            .add_system(load_zombie_catalog)
+            .add_system(setup_zombies.after(load_zombie_catalog).in_set(OnUpdate(GameState::InGame)))
+            .add_systems((update_zombie_state, move_zombies)
+                .chain()
+                .after(setup_zombies)
+                .distributive_run_if(is_offline)
+                .in_set(OnUpdate(GameState::InGame)))
+            .add_systems((start_zombie_death, play_zombie_death).chain().in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+/// Variant used when a `Zombie` names one the catalog doesn't know.
+const DEFAULT_VARIANT: &str = "Base";
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct ZombieBundle {
+    #[from_entity_instance]
+    pub zombie: Zombie,
+    /// Filled in by `setup_zombies` once the variant's sprites are known.
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    #[bundle]
+    pub physics: CharacterPhysicsBundle,
+    #[from_entity_instance]
+    pub animation: CharacterAnimation,
+}
+
+#[derive(Component, Default, Debug)]
+pub struct Zombie {
+    variant: String,
+    iid: String,
+}
+
+impl Zombie {
+    pub fn new(variant: &str, iid: &str) -> Self {
+        Zombie { variant: variant.to_string(), iid: iid.to_string() }
+    }
+
+    pub fn variant(&self) -> &str {
+        &self.variant
+    }
+
+    /// LDtk entity iid, stable across respawns of the level.
+    pub fn iid(&self) -> &str {
+        &self.iid
+    }
+}
+
+impl From<&EntityInstance> for Zombie {
+    fn from(value: &EntityInstance) -> Self {
+        let variant = value.field_instances.iter()
+            .find(|f| f.identifier == "variant")
+            .and_then(|f| match &f.value {
+                FieldValue::String(variant) => variant.clone(),
+                _ => None,
+            })
+            .unwrap_or_else(|| DEFAULT_VARIANT.to_string());
+        Zombie::new(&variant, &value.iid)
+    }
+}
+
+/// How a zombie variant looks and behaves, read from `assets/data/game.zombies.json`.
+#[derive(Component, Deserialize, Clone, Debug, PartialEq)]
+pub struct ZombieStats {
+    /// Directory holding a `<sheet>/<sheet>.aseprite` for every sheet the `Zombie` animations name.
+    pub sprites: String,
+    pub health: u32,
+    pub damage: u32,
+    pub walk_speed: f32,
+    pub chase_speed: f32,
+    /// Distance at which an idle or wandering zombie notices a player.
+    pub sight_range: f32,
+    /// Distance at which a chasing zombie loses track of its player.
+    pub give_up_range: f32,
+    pub attack_range: f32,
+    pub attack_cooldown: f32,
+    pub idle_time: f32,
+    /// Zero keeps the zombie standing until a player comes into sight.
+    pub wander_time: f32,
+}
+
+/// Every zombie variant's stats, empty until `assets/data/game.zombies.json` loads.
+#[derive(Resource, TypeUuid, Deserialize, Clone, Default, Debug)]
+#[uuid = "7d2e4b91-3c6a-4f08-9e15-b8a0c4d2f613"]
+pub struct ZombieCatalog(HashMap<String, ZombieStats>);
+
+impl ZombieCatalog {
+    pub fn parse(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn get(&self, variant: &str) -> Option<&ZombieStats> {
+        self.0.get(variant)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct ZombieCatalogLoader;
+
+impl AssetLoader for ZombieCatalogLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let catalog = ZombieCatalog::parse(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(catalog));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["zombies.json"]
+    }
+}
+
+/// Keeps the catalog asset loaded so edits to it are picked up while the game runs.
+#[derive(Resource)]
+struct ZombieCatalogHandle(Handle<ZombieCatalog>);
+
+impl FromWorld for ZombieCatalogHandle {
+    fn from_world(world: &mut World) -> Self {
+        ZombieCatalogHandle(world.resource::<AssetServer>().load("data/game.zombies.json"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZombieState {
+    Idle,
+    /// Walking in a fixed direction.
+    Wander(Vec2),
+    Chase,
+    Attack,
+}
+
+#[derive(Component, Debug)]
+pub struct ZombieBrain {
+    pub state: ZombieState,
+    /// How long the zombie stays idle or keeps wandering.
+    timer: Timer,
+    /// Seconds until the zombie may attack again.
+    cooldown: f32,
+    /// xorshift state for picking wander directions.
+    seed: u32,
+}
+
+impl ZombieBrain {
+    pub fn new(stats: &ZombieStats, seed: u32) -> Self {
+        ZombieBrain {
+            state: ZombieState::Idle,
+            timer: Timer::from_seconds(stats.idle_time, TimerMode::Once),
+            cooldown: 0.,
+            seed: seed.max(1),
+        }
+    }
+
+    fn random_direction(&mut self) -> Vec2 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        let angle = self.seed as f32 / u32::MAX as f32 * TAU;
+        Vec2::new(angle.cos(), angle.sin())
+    }
+}
+
+/// Sent every time a zombie lands an attack.
+pub struct ZombieAttack {
+    pub zombie: Entity,
+    pub target: Entity,
+    pub damage: u32,
+}
+
+/// The state a zombie moves to given the distance to the nearest player, if there is one.
+pub fn next_state(
+    state: ZombieState,
+    stats: &ZombieStats,
+    player_distance: Option<f32>,
+    timer_finished: bool,
+) -> ZombieState {
+    let hunting = matches!(state, ZombieState::Chase | ZombieState::Attack);
+    match player_distance {
+        Some(distance) if distance <= stats.attack_range => return ZombieState::Attack,
+        Some(distance) if distance <= stats.sight_range => return ZombieState::Chase,
+        Some(distance) if hunting && distance <= stats.give_up_range => return ZombieState::Chase,
+        _ => {}
+    }
+    match state {
+        ZombieState::Chase | ZombieState::Attack => ZombieState::Idle,
+        ZombieState::Idle if timer_finished && stats.wander_time > 0. => ZombieState::Wander(Vec2::ZERO),
+        ZombieState::Wander(_) if timer_finished => ZombieState::Idle,
+        state => state,
+    }
+}
+
+/// Copies the catalog into its resource whenever the asset loads or changes.
+fn load_zombie_catalog(
+    mut events: EventReader<AssetEvent<ZombieCatalog>>,
+    handle: Res<ZombieCatalogHandle>,
+    assets: Res<Assets<ZombieCatalog>>,
+    mut catalog: ResMut<ZombieCatalog>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle: loaded } | AssetEvent::Modified { handle: loaded }) = event else { continue; };
+        if *loaded != handle.0 { continue; }
+        if let Some(loaded) = assets.get(loaded) {
+            *catalog = loaded.clone();
+        }
+    }
+}
+
+/// Gives zombies their variant's stats and sprites, once the catalog has loaded.
+fn setup_zombies(
+    mut commands: Commands,
+    catalog: Res<ZombieCatalog>,
+    library: Res<AnimationLibrary>,
+    asset_server: Res<AssetServer>,
+    zombie_q: Query<(Entity, &Zombie, &CharacterAnimation), Without<ZombieBrain>>,
+) {
+    if catalog.is_empty() { return; }
+    for (entity, zombie, animation) in zombie_q.iter() {
+        let stats = catalog.get(zombie.variant()).or_else(|| {
+            warn!("Unknown zombie variant {}, using {DEFAULT_VARIANT}", zombie.variant());
+            catalog.get(DEFAULT_VARIANT)
+        });
+        let Some(stats) = stats else { continue; };
+        let sheet_path = |sheet: &str| format!("{}/{sheet}/{sheet}.aseprite", stats.sprites);
+        let sheets = AnimationSheets(library.get(&animation.set).into_iter()
+            .flat_map(|set| set.values())
+            .filter_map(|clip| clip.sheet.clone())
+            .map(|sheet| (sheet.clone(), asset_server.load(sheet_path(&sheet))))
+            .collect());
+        // Shown until the sheets load and the animation takes over.
+        let idle_sheet: Handle<TextureAtlas> = library.clip(&animation.set, AnimationState::Idle)
+            .and_then(|clip| clip.sheet.as_deref())
+            .map(|sheet| asset_server.load(format!("{}#atlas", sheet_path(sheet))))
+            .unwrap_or_default();
+        let mut hasher = DefaultHasher::new();
+        zombie.iid().hash(&mut hasher);
+        commands.entity(entity).insert((
+            idle_sheet,
+            ZombieBrain::new(stats, hasher.finish() as u32),
+            Health::new(stats.health),
+            Damage(stats.damage),
+            stats.clone(),
+            sheets,
+        ));
+    }
+}
+
+fn update_zombie_state(
+    time: Res<Time>,
+    mut zombie_q: Query<(Entity, &Transform, &ZombieStats, &Damage, &mut ZombieBrain), Without<Dead>>,
+    player_q: Query<(Entity, &Transform), With<Player>>,
+    mut attack_ev: EventWriter<ZombieAttack>,
+) {
+    for (zombie, transform, stats, damage, mut brain) in zombie_q.iter_mut() {
+        let position = transform.translation.truncate();
+        let nearest = player_q.iter()
+            .map(|(player, p_transform)| (player, p_transform.translation.truncate().distance(position)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        brain.timer.tick(time.delta());
+        brain.cooldown = (brain.cooldown - time.delta_seconds()).max(0.);
+        let timer_finished = brain.timer.finished();
+        let state = next_state(brain.state, stats, nearest.map(|(_, d)| d), timer_finished);
+        if state != brain.state {
+            brain.state = match state {
+                ZombieState::Wander(_) => ZombieState::Wander(brain.random_direction()),
+                state => state,
+            };
+            match brain.state {
+                ZombieState::Idle => brain.timer = Timer::from_seconds(stats.idle_time, TimerMode::Once),
+                ZombieState::Wander(_) => brain.timer = Timer::from_seconds(stats.wander_time, TimerMode::Once),
+                _ => {}
+            }
+        }
+        if brain.state != ZombieState::Attack || brain.cooldown > 0. { continue; }
+        let Some((target, _)) = nearest else { continue; };
+        brain.cooldown = stats.attack_cooldown;
+        attack_ev.send(ZombieAttack { zombie, target, damage: damage.0 });
+    }
+}
+
+fn move_zombies(
+    mut zombie_q: Query<(&Transform, &ZombieStats, &ZombieBrain, &mut Velocity), (Without<Dead>, Without<Knockback>)>,
+    player_q: Query<&Transform, With<Player>>,
+) {
+    for (transform, stats, brain, mut velocity) in zombie_q.iter_mut() {
+        let position = transform.translation.truncate();
+        velocity.linvel = match brain.state {
+            ZombieState::Idle | ZombieState::Attack => Vec2::ZERO,
+            ZombieState::Wander(direction) => direction * stats.walk_speed,
+            ZombieState::Chase => player_q.iter()
+                .map(|p_transform| p_transform.translation.truncate() - position)
+                .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+                .map(|to_player| to_player.normalize_or_zero() * stats.chase_speed)
+                .unwrap_or(Vec2::ZERO),
+        };
+    }
+}
+
+fn start_zombie_death(
+    mut commands: Commands,
+    mut zombie_q: Query<(Entity, &mut Velocity), (With<Zombie>, Added<Dead>)>,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
+) {
+    for (entity, mut velocity) in zombie_q.iter_mut() {
+        velocity.linvel = Vec2::ZERO;
+        log.send(GameLogEvent::new(LogCategory::Combat, localization.get("log.zombie_dies")));
+        commands.entity(entity).remove::<(Collider, Knockback)>();
+    }
+}
+
+/// Despawns dead zombies once their death animation, if they have one, has played.
+fn play_zombie_death(
+    mut commands: Commands,
+    library: Res<AnimationLibrary>,
+    zombie_q: Query<(Entity, &CharacterAnimation), (With<Zombie>, With<Dead>)>,
+) {
+    for (entity, animation) in zombie_q.iter() {
+        let has_death = library.clip(&animation.set, AnimationState::Death).is_some();
+        if !has_death || (animation.state == AnimationState::Death && animation.finished()) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod enemy_test {
+    use std::thread;
+    use std::time::Duration;
+    use bevy_rapier2d::prelude::{NoUserData, RapierPhysicsPlugin};
+    use super::*;
+    use crate::animation::{AnimationPlugin, game_library};
+    use crate::aseprite::AsepritePlugin;
+    use crate::health::HealthPlugin;
+    use crate::level::LevelPlugin;
+    use crate::localization::LocalizationPlugin;
+    use crate::test_utils::{enter_game, inflict_damage, LoadTestPlugins, update};
+
+    #[test]
+    fn it_reads_every_variant_from_the_catalog() {
+        let catalog = game_catalog();
+        for variant in ["Base", "Banshee", "Muscle", "Overweight"] {
+            assert!(catalog.get(variant).is_some(), "missing {variant}");
+        }
+        let banshee = catalog.get("Banshee").unwrap();
+        let overweight = catalog.get("Overweight").unwrap();
+        assert!(banshee.chase_speed > overweight.chase_speed);
+        assert!(overweight.health > banshee.health);
+    }
+
+    #[test]
+    fn it_loads_the_catalog_as_an_asset() {
+        let mut app = setup();
+        let handle = app.world.resource::<ZombieCatalogHandle>().0.clone();
+        for _ in 0..100 {
+            if app.world.resource::<Assets<ZombieCatalog>>().contains(&handle) { break; }
+            thread::sleep(Duration::from_millis(10));
+            app.update();
+        }
+        let catalog = app.world.resource::<Assets<ZombieCatalog>>().get(&handle).unwrap();
+        assert_eq!(catalog.get("Banshee"), game_catalog().get("Banshee"));
+    }
+
+    #[test]
+    fn it_spawns_zombies_from_ldtk() {
+        let mut app = setup();
+        app.insert_resource(LevelSelection::Index(1));
+        update(&mut app, 4);
+        let mut variants: Vec<_> = app.world.query::<(&Zombie, &ZombieStats)>().iter(&app.world)
+            .map(|(zombie, stats)| (zombie.variant().to_string(), stats.health))
+            .collect();
+        variants.sort();
+        assert_eq!(variants, [
+            ("Banshee".to_string(), 2),
+            ("Base".to_string(), 3),
+            ("Muscle".to_string(), 6),
+            ("Overweight".to_string(), 8),
+        ]);
+    }
+
+    #[test]
+    fn it_chases_players_in_sight_and_attacks_in_range() {
+        let stats = base_stats();
+        assert_eq!(next_state(ZombieState::Idle, &stats, Some(80.), false), ZombieState::Chase);
+        assert_eq!(next_state(ZombieState::Chase, &stats, Some(10.), false), ZombieState::Attack);
+        assert_eq!(next_state(ZombieState::Attack, &stats, Some(20.), false), ZombieState::Chase);
+    }
+
+    #[test]
+    fn it_gives_up_on_players_out_of_range() {
+        let stats = base_stats();
+        assert_eq!(next_state(ZombieState::Chase, &stats, Some(150.), false), ZombieState::Chase);
+        assert_eq!(next_state(ZombieState::Chase, &stats, Some(200.), false), ZombieState::Idle);
+        assert_eq!(next_state(ZombieState::Idle, &stats, Some(150.), false), ZombieState::Idle);
+    }
+
+    #[test]
+    fn it_wanders_between_idle_spells() {
+        let stats = base_stats();
+        assert_eq!(next_state(ZombieState::Idle, &stats, None, false), ZombieState::Idle);
+        assert!(matches!(next_state(ZombieState::Idle, &stats, None, true), ZombieState::Wander(_)));
+        assert_eq!(next_state(ZombieState::Wander(Vec2::X), &stats, None, true), ZombieState::Idle);
+        let overweight = game_catalog().get("Overweight").unwrap().clone();
+        assert_eq!(next_state(ZombieState::Idle, &overweight, None, true), ZombieState::Idle);
+    }
+
+    #[test]
+    fn it_moves_toward_and_attacks_the_player() {
+        let mut app = setup();
+        let player = app.world.spawn((Player, TransformBundle::from_transform(Transform::from_xyz(60., 0., 0.)))).id();
+        let zombie = spawn_zombie(&mut app);
+        update(&mut app, 2);
+        let velocity = app.world.get::<Velocity>(zombie).unwrap();
+        assert!(velocity.linvel.x > 0.);
+        assert_eq!(velocity.linvel.y, 0.);
+
+        app.world.get_mut::<Transform>(player).unwrap().translation.x = 10.;
+        app.update();
+        assert_eq!(app.world.get::<ZombieBrain>(zombie).unwrap().state, ZombieState::Attack);
+        let attacks = app.world.resource::<Events<ZombieAttack>>();
+        let attack = attacks.iter_current_update_events().next().unwrap();
+        assert_eq!((attack.zombie, attack.target, attack.damage), (zombie, player, 1));
+    }
+
+    #[test]
+    fn it_plays_the_death_animation_when_killed() {
+        let mut app = setup();
+        let zombie = spawn_zombie(&mut app);
+        update(&mut app, 2);
+        assert_eq!(app.world.get::<Health>(zombie).unwrap().current, 3);
+        inflict_damage(&mut app, zombie, 3);
+        app.update();
+        assert!(app.world.get::<AnimationSheets>(zombie).unwrap().0.contains_key("Death"));
+        assert_eq!(app.world.get::<CharacterAnimation>(zombie).unwrap().state, AnimationState::Death);
+        assert!(app.world.get::<Collider>(zombie).is_none());
+        let logs = app.world.resource::<Events<GameLogEvent>>();
+        assert_eq!(logs.iter_current_update_events().next().unwrap().text(), "The zombie collapses.");
+    }
+
+    fn spawn_zombie(app: &mut App) -> Entity {
+        app.world.spawn((
+            Zombie::new("Base", "zombie-iid"),
+            SpriteSheetBundle::default(),
+            Velocity::default(),
+            Collider::capsule_y(5., 7.),
+            CharacterAnimation::new("Zombie"),
+        )).id()
+    }
+
+    fn game_catalog() -> ZombieCatalog {
+        ZombieCatalog::parse(&std::fs::read("assets/data/game.zombies.json").unwrap()).unwrap()
+    }
+
+    fn base_stats() -> ZombieStats {
+        game_catalog().get("Base").unwrap().clone()
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .insert_resource(game_catalog())
+            .insert_resource(game_library())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+            .add_plugin(LevelPlugin)
+            .add_plugin(HealthPlugin)
+            .add_plugin(AsepritePlugin)
+            .add_plugin(AnimationPlugin)
+            .add_plugin(EnemyPlugin);
+        enter_game(&mut app);
+        app
+    }
+}
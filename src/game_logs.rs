@@ -1,6 +1,14 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
 use bevy::prelude::*;
 use bevy::asset::AssetServer;
+use bevy::ecs::system::SystemParam;
 use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use crate::actions::{Action, ActionInput};
+use crate::dialogue::{no_dialogue, ActiveDialogue};
+use crate::game_state::GameState;
+use crate::inventory::{inventory_closed, InventoryCursor};
+use crate::localization::{pieces, Localization, LocalizedText, Piece};
 
 pub struct UIPlugin;
 
@@ -8,40 +16,248 @@ impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_event::<GameLogEvent>()
-            .add_startup_system(setup_ui)
-            .add_system(debug_event)
-            .add_system(add_game_logs)
-            .add_system(scroll_to_bottom_on_new_log)
-            .add_system(scroll_logs_on_mouse_scroll);
+            .init_resource::<LogFilter>()
+            .add_system(setup_ui.in_schedule(OnEnter(GameState::InGame)))
+            .add_system(despawn_ui.in_schedule(OnExit(GameState::InGame)))
+            .add_systems((
+                toggle_log_panel,
+                toggle_log_expansion,
+                add_game_logs,
+                toggle_log_filter,
+                apply_log_filter,
+                scroll_logs,
+                position_log_list,
+            ).chain().in_set(OnUpdate(GameState::InGame)));
+        #[cfg(feature = "debug-mode")]
+        app.add_system(debug_event.before(add_game_logs).in_set(OnUpdate(GameState::InGame)));
     }
 }
 
-fn debug_event(
-    mut ev: EventWriter<GameLogEvent>,
-    keyboard_input: Res<Input<KeyCode>>,
+const LOG_FONT: &str = "fonts/kongtext/kongtext.ttf";
+const LOG_FONT_SIZE: f32 = 12.;
+const TIMESTAMP_COLOR: Color = Color::GRAY;
+/// Entries kept in the panel; older ones are reused for new lines.
+const LOG_HISTORY_LIMIT: usize = 100;
+/// Pixels a mouse wheel line scrolls the log by.
+const LOG_LINE_HEIGHT: f32 = 20.;
+const LOG_SCROLL_STEP: f32 = LOG_LINE_HEIGHT * 3.;
+const COLLAPSED_LOG_HEIGHT: f32 = 10.;
+const EXPANDED_LOG_HEIGHT: f32 = 40.;
+
+/// What a log entry is about, used for its colour and icon and for filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LogCategory {
+    Combat,
+    Loot,
+    System,
+    Dialogue,
+}
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 4] = [LogCategory::Combat, LogCategory::Loot, LogCategory::System, LogCategory::Dialogue];
+
+    fn color(self) -> Color {
+        match self {
+            LogCategory::Combat => Color::rgb(1., 0.45, 0.4),
+            LogCategory::Loot => Color::rgb(1., 0.85, 0.35),
+            LogCategory::System => Color::rgb(0.7, 0.8, 1.),
+            LogCategory::Dialogue => Color::WHITE,
+        }
+    }
+
+    /// Drawn with the log font, which only has ASCII glyphs.
+    fn icon(self) -> &'static str {
+        match self {
+            LogCategory::Combat => "x",
+            LogCategory::Loot => "$",
+            LogCategory::System => "*",
+            LogCategory::Dialogue => ">",
+        }
+    }
+
+    fn label_key(self) -> &'static str {
+        match self {
+            LogCategory::Combat => "log.filter.combat",
+            LogCategory::Loot => "log.filter.loot",
+            LogCategory::System => "log.filter.system",
+            LogCategory::Dialogue => "log.filter.dialogue",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LogSeverity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+/// A run of log text, in the category's colour unless it has its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogSegment {
+    pub text: String,
+    pub color: Option<Color>,
+}
+
+/// A line of the game log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameLogEvent {
+    pub category: LogCategory,
+    pub severity: LogSeverity,
+    /// Game time the entry was logged at, filled in by [GameLogWriter].
+    pub timestamp: Duration,
+    pub segments: Vec<LogSegment>,
+}
+
+impl GameLogEvent {
+    pub fn new(category: LogCategory, text: impl Into<String>) -> Self {
+        GameLogEvent {
+            category,
+            severity: LogSeverity::Info,
+            timestamp: Duration::ZERO,
+            segments: vec![LogSegment { text: text.into(), color: None }],
+        }
+    }
+
+    /// An entry from a localized `template`, with each placeholder filled from `args` and highlighted.
+    pub fn from_template(category: LogCategory, template: &str, args: &[(&str, &str)]) -> Self {
+        let mut event = GameLogEvent::new(category, "");
+        event.segments.clear();
+        for piece in pieces(template) {
+            event = match piece {
+                Piece::Text(text) => event.then(text),
+                Piece::Placeholder(name) => match args.iter().find(|(arg, _)| *arg == name) {
+                    Some((_, value)) => event.highlight(*value, Color::WHITE),
+                    None => event.then(format!("{{{name}}}")),
+                },
+            };
+        }
+        event
+    }
+
+    pub fn with_severity(mut self, severity: LogSeverity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Appends `text`, highlighted in `color`.
+    pub fn highlight(mut self, text: impl Into<String>, color: Color) -> Self {
+        self.segments.push(LogSegment { text: text.into(), color: Some(color) });
+        self
+    }
+
+    /// Appends `text` in the category's colour.
+    pub fn then(mut self, text: impl Into<String>) -> Self {
+        self.segments.push(LogSegment { text: text.into(), color: None });
+        self
+    }
+
+    /// The entry without its formatting.
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|segment| segment.text.as_str()).collect()
+    }
+}
+
+/// Sends [GameLogEvent]s stamped with the current game time.
+#[derive(SystemParam)]
+pub struct GameLogWriter<'w> {
+    events: EventWriter<'w, GameLogEvent>,
+    time: Res<'w, Time>,
+}
+
+impl GameLogWriter<'_> {
+    pub fn send(&mut self, entry: GameLogEvent) {
+        self.events.send(GameLogEvent { timestamp: self.time.elapsed(), ..entry });
+    }
+}
+
+/// Categories currently left out of the log panel.
+#[derive(Resource, Default, Debug)]
+pub struct LogFilter {
+    pub hidden: HashSet<LogCategory>,
+}
+
+#[cfg(feature = "debug-mode")]
+fn debug_event(mut log: GameLogWriter, actions: ActionInput, localization: Res<Localization>) {
+    if actions.just_pressed(Action::DebugLog) {
+        log.send(GameLogEvent::new(LogCategory::System, localization.get("log.debug")));
+    }
+}
+
+fn toggle_log_panel(
+    actions: ActionInput,
+    mut panel_q: Query<&mut Visibility, With<GameLogPanel>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        let test_log = "Testing long text for helping me. Long Long long long long long long long long!";
-        ev.send(GameLogEvent(test_log.to_string()));
+    if !actions.just_pressed(Action::OpenLog) { return; }
+    for mut visibility in panel_q.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
     }
 }
 
-fn setup_ui(mut commands: Commands) {
-    commands.spawn(NodeBundle {
+fn toggle_log_expansion(
+    actions: ActionInput,
+    mut panel_q: Query<(&mut GameLogPanel, &mut Style)>,
+) {
+    if !actions.just_pressed(Action::ExpandLog) { return; }
+    for (mut panel, mut style) in panel_q.iter_mut() {
+        panel.expanded = !panel.expanded;
+        style.size.height = Val::Percent(panel.height());
+    }
+}
+
+fn setup_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    filter: Res<LogFilter>,
+    localization: Res<Localization>,
+) {
+    let font = asset_server.load(LOG_FONT);
+    commands.spawn((NodeBundle {
         style: Style {
             flex_direction: FlexDirection::Column,
             align_self: AlignSelf::FlexEnd,
             size: Size::new(
                 Val::Percent(35.),
-                Val::Percent(10.),
+                Val::Percent(COLLAPSED_LOG_HEIGHT),
             ),
             overflow: Overflow::Hidden,
             ..default()
         },
         ..default()
-    }).with_children(|parent| {
-        parent
-            .spawn((
+    }, GameLogPanel::default())).with_children(|parent| {
+        parent.spawn(NodeBundle::default()).with_children(|bar| {
+            for category in LogCategory::ALL {
+                bar.spawn((ButtonBundle {
+                    style: Style {
+                        margin: UiRect::right(Val::Px(4.)),
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                }, LogFilterButton(category))).with_children(|button| {
+                    let label = LocalizedText::new(category.label_key());
+                    button.spawn((TextBundle::from_section(label.text(&localization), TextStyle {
+                        font: font.clone(),
+                        font_size: 8.,
+                        color: filter_button_color(category, &filter),
+                    }), label));
+                });
+            }
+        });
+        parent.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                flex_grow: 1.,
+                overflow: Overflow::Hidden,
+                ..default()
+            },
+            ..default()
+        }).with_children(|viewport| {
+            viewport.spawn((
                 NodeBundle {
                     style: Style {
                         flex_direction: FlexDirection::Column,
@@ -51,90 +267,209 @@ fn setup_ui(mut commands: Commands) {
                 },
                 ScrollingList::default(),
             ));
+        });
     });
 }
 
+fn filter_button_color(category: LogCategory, filter: &LogFilter) -> Color {
+    match filter.hidden.contains(&category) {
+        true => Color::DARK_GRAY,
+        false => category.color(),
+    }
+}
+
+fn toggle_log_filter(
+    mut filter: ResMut<LogFilter>,
+    button_q: Query<(&Interaction, &LogFilterButton, &Children), Changed<Interaction>>,
+    mut text_q: Query<&mut Text>,
+) {
+    for (interaction, button, children) in button_q.iter() {
+        if *interaction != Interaction::Clicked { continue; }
+        if !filter.hidden.remove(&button.0) {
+            filter.hidden.insert(button.0);
+        }
+        let color = filter_button_color(button.0, &filter);
+        for child in children.iter() {
+            let Ok(mut text) = text_q.get_mut(*child) else { continue; };
+            text.sections.iter_mut().for_each(|section| section.style.color = color);
+        }
+    }
+}
+
+fn apply_log_filter(
+    filter: Res<LogFilter>,
+    mut log_q: Query<(Ref<GameLog>, &mut Style)>,
+) {
+    for (log, mut style) in log_q.iter_mut() {
+        if !filter.is_changed() && !log.is_changed() { continue; }
+        let display = match filter.hidden.contains(&log.category) {
+            true => Display::None,
+            false => Display::Flex,
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+fn despawn_ui(mut commands: Commands, panel_q: Query<Entity, With<GameLogPanel>>) {
+    for panel in panel_q.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+}
 
 fn add_game_logs(
     mut commands: Commands,
     mut log_event_reader: EventReader<GameLogEvent>,
     asset_server: Res<AssetServer>,
-    list_query: Query<(Entity, &Node), With<ScrollingList>>,
+    mut list_query: Query<(Entity, &Node, &mut ScrollingList)>,
+    log_node_q: Query<&Node, With<GameLog>>,
 ) {
+    let Ok((list, node, mut scroll)) = list_query.get_single_mut() else { return; };
+    let font = asset_server.load(LOG_FONT);
     for event in log_event_reader.iter() {
-        let log_text = event.0.clone() + "\n";
-        let list_query_result = list_query.get_single();
-        if list_query_result.is_err() { return; };
-        let (list, node) = list_query_result.unwrap();
-        commands.entity(list).with_children(|parent| {
-            parent.spawn((
-                TextBundle::from_section(
-                    log_text.to_string(),
-                    TextStyle {
-                        font: asset_server.load("fonts/kongtext/kongtext.ttf"),
-                        font_size: 12.0,
-                        color: Color::WHITE,
-                    },
-                ).with_style(Style {
+        let text = Text::from_sections(log_sections(event, &font));
+        let log = GameLog { category: event.category };
+        if scroll.entries.len() >= LOG_HISTORY_LIMIT {
+            let oldest = scroll.entries.pop_front().unwrap();
+            if !scroll.follow {
+                // Keeps the lines being read in place as the one above them goes away.
+                let height = log_node_q.get(oldest).map_or(0., |node| node.size().y);
+                scroll.position = (scroll.position + height).min(0.);
+            }
+            commands.entity(oldest).insert((text, log));
+            commands.entity(list).push_children(&[oldest]);
+            scroll.entries.push_back(oldest);
+            continue;
+        }
+        let entry = commands.spawn((
+            TextBundle {
+                text,
+                style: Style {
                     size: Size::new(
                         Val::Px(node.size().x),
                         Val::Undefined,
                     ),
                     ..default()
-                }),
-                GameLog
-            ));
-        });
+                },
+                ..default()
+            },
+            log,
+        )).id();
+        commands.entity(list).add_child(entry);
+        scroll.entries.push_back(entry);
     }
 }
 
-fn scroll_to_bottom_on_new_log(
-    log_node_changed_query: Query<Entity, (With<GameLog>, Changed<Node>)>,
-    mut query_list: Query<(&mut Style, &Node, &Parent, &mut ScrollingList)>,
-    logs_query: Query<&Node>,
-) {
-    for _ in log_node_changed_query.iter() {
-        let query_list_result = query_list.get_single_mut();
-        if query_list_result.is_err() { continue; }
-        let (mut style, list_node, parent, mut scroll) = query_list_result.unwrap();
-        let logs_height = list_node.size().y;
-        let panel_height = logs_query.get(parent.get()).unwrap().size().y;
-        scroll.position = panel_height - logs_height;
-        style.position.top = Val::Px(panel_height - logs_height);
-    }
+fn log_sections(event: &GameLogEvent, font: &Handle<Font>) -> Vec<TextSection> {
+    let style = |color: Color| TextStyle { font: font.clone(), font_size: LOG_FONT_SIZE, color };
+    let icon_color = match event.severity {
+        LogSeverity::Info => event.category.color(),
+        LogSeverity::Warning => Color::ORANGE,
+        LogSeverity::Critical => Color::RED,
+    };
+    let seconds = event.timestamp.as_secs();
+    let mut sections = vec![
+        TextSection::new(format!("{:02}:{:02} ", seconds / 60, seconds % 60), style(TIMESTAMP_COLOR)),
+        TextSection::new(format!("{} ", event.category.icon()), style(icon_color)),
+    ];
+    sections.extend(event.segments.iter().map(|segment| {
+        TextSection::new(segment.text.clone(), style(segment.color.unwrap_or(event.category.color())))
+    }));
+    sections.push(TextSection::new("\n", style(Color::WHITE)));
+    sections
 }
 
+fn max_log_scroll(list: &Node, viewport: &Node) -> f32 {
+    (list.size().y - viewport.size().y).max(0.)
+}
 
-fn scroll_logs_on_mouse_scroll(
+fn scroll_logs(
+    actions: ActionInput,
     mut mouse_wheel_events: EventReader<MouseWheel>,
+    panel_q: Query<&Visibility, With<GameLogPanel>>,
+    mut query_list: Query<(&mut ScrollingList, &Parent, &Node)>,
+    viewport_q: Query<&Node>,
+    cursor: Option<Res<InventoryCursor>>,
+    dialogue: Option<Res<ActiveDialogue>>,
+) {
+    let mut dy: f32 = mouse_wheel_events.iter().map(|event| match event.unit {
+        MouseScrollUnit::Line => event.y * LOG_LINE_HEIGHT,
+        MouseScrollUnit::Pixel => event.y,
+    }).sum();
+    // The wheel zooms the camera instead while the zoom modifier is held.
+    if actions.pressed(Action::ZoomModifier) { dy = 0.; }
+    // The gamepad scroll buttons are the d-pad, which navigates the inventory and dialogue choices while open.
+    if inventory_closed(cursor) && no_dialogue(dialogue) {
+        if actions.just_pressed(Action::ScrollLogUp) { dy += LOG_SCROLL_STEP; }
+        if actions.just_pressed(Action::ScrollLogDown) { dy -= LOG_SCROLL_STEP; }
+    }
+    if dy == 0. || panel_q.iter().any(|visibility| *visibility == Visibility::Hidden) { return; }
+    for (mut scroll, parent, list_node) in &mut query_list {
+        let Ok(viewport) = viewport_q.get(parent.get()) else { continue; };
+        let max_scroll = max_log_scroll(list_node, viewport);
+        scroll.position = (scroll.position + dy).clamp(-max_scroll, 0.);
+        scroll.follow = scroll.position <= 0.5 - max_scroll;
+    }
+}
+
+/// Keeps the newest entry in view unless the player scrolled away from it.
+fn position_log_list(
     mut query_list: Query<(&mut ScrollingList, &mut Style, &Parent, &Node)>,
-    logs_query: Query<&Node>,
+    viewport_q: Query<&Node>,
 ) {
-    for mouse_wheel_event in mouse_wheel_events.iter() {
-        for (mut scrolling_list, mut style, parent, list_node) in &mut query_list {
-            let logs_height = list_node.size().y;
-            let panel_height = logs_query.get(parent.get()).unwrap().size().y;
-            let max_scroll = (logs_height - panel_height).max(0.);
-            let dy = match mouse_wheel_event.unit {
-                MouseScrollUnit::Line => mouse_wheel_event.y * 20.,
-                MouseScrollUnit::Pixel => mouse_wheel_event.y,
-            };
-            scrolling_list.position += dy;
-            scrolling_list.position = scrolling_list.position.clamp(-max_scroll, 0.);
-            style.position.top = Val::Px(scrolling_list.position);
+    for (mut scroll, mut style, parent, list_node) in &mut query_list {
+        let Ok(viewport) = viewport_q.get(parent.get()) else { continue; };
+        let max_scroll = max_log_scroll(list_node, viewport);
+        let position = match scroll.follow {
+            true => -max_scroll,
+            false => scroll.position.clamp(-max_scroll, 0.),
+        };
+        if scroll.position != position {
+            scroll.position = position;
+        }
+        if style.position.top != Val::Px(position) {
+            style.position.top = Val::Px(position);
         }
     }
 }
 
-#[derive(Component, Default)]
+#[derive(Component)]
 struct ScrollingList {
     position: f32,
+    /// Set while the newest entry is in view, cleared while older ones are being read.
+    follow: bool,
+    /// Oldest first.
+    entries: VecDeque<Entity>,
+}
+
+impl Default for ScrollingList {
+    fn default() -> Self {
+        ScrollingList { position: 0., follow: true, entries: VecDeque::new() }
+    }
 }
 
 #[derive(Component)]
-struct GameLog;
+struct GameLog {
+    category: LogCategory,
+}
+
+#[derive(Component)]
+struct LogFilterButton(LogCategory);
 
-pub struct GameLogEvent(String);
+#[derive(Component, Default)]
+struct GameLogPanel {
+    expanded: bool,
+}
+
+impl GameLogPanel {
+    fn height(&self) -> f32 {
+        match self.expanded {
+            true => EXPANDED_LOG_HEIGHT,
+            false => COLLAPSED_LOG_HEIGHT,
+        }
+    }
+}
 
 #[cfg(test)]
 mod log_test {
@@ -142,23 +477,76 @@ mod log_test {
     use bevy::text::TextPlugin;
     use bevy::prelude::*;
     use bevy::ui::UiPlugin;
+    use bevy::input::ButtonState;
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::ecs::system::SystemState;
     use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::localization::LocalizationPlugin;
     use crate::test_utils::*;
 
     #[test]
     fn it_updates_log_text() {
         let mut app = setup();
-        app.world.send_event(GameLogEvent("Hello World!".to_string()));
+        app.world.send_event(GameLogEvent::new(LogCategory::Loot, "Found the ").highlight("red", Color::RED).then(" key."));
         app.update();
         let log_text = app.world
             .query_filtered::<&Text, With<GameLog>>().single(&app.world);
-        assert_eq!(log_text.sections[0].value, "Hello World!\n".to_string());
+        let values: Vec<_> = log_text.sections.iter().map(|section| section.value.as_str()).collect();
+        assert_eq!(values, ["00:00 ", "$ ", "Found the ", "red", " key.", "\n"]);
+        assert_eq!(log_text.sections[2].style.color, LogCategory::Loot.color());
+        assert_eq!(log_text.sections[3].style.color, Color::RED);
+    }
+
+    #[test]
+    fn it_highlights_template_arguments() {
+        let entry = GameLogEvent::from_template(LogCategory::Loot, "Picked up the {key} key.", &[("key", "crypt")]);
+        assert_eq!(entry.segments, [
+            LogSegment { text: "Picked up the ".into(), color: None },
+            LogSegment { text: "crypt".into(), color: Some(Color::WHITE) },
+            LogSegment { text: " key.".into(), color: None },
+        ]);
+    }
+
+    #[test]
+    fn it_stamps_entries_with_the_game_time() {
+        let mut app = setup();
+        app.world.resource_mut::<Time>().update_with_instant(app.world.resource::<Time>().startup() + Duration::from_secs(75));
+        let mut state: SystemState<GameLogWriter<'static>> = SystemState::new(&mut app.world);
+        state.get_mut(&mut app.world).send(GameLogEvent::new(LogCategory::Combat, "You died.").with_severity(LogSeverity::Critical));
+        state.apply(&mut app.world);
+        let elapsed = app.world.resource::<Time>().elapsed();
+        let logs = app.world.resource::<Events<GameLogEvent>>();
+        let entry = logs.iter_current_update_events().next().unwrap();
+        assert_eq!(entry.timestamp, elapsed);
+        assert!(entry.timestamp >= Duration::from_secs(75));
+        assert_eq!(entry.text(), "You died.");
+    }
+
+    #[test]
+    fn it_hides_filtered_categories() {
+        let mut app = setup();
+        app.world.send_event(GameLogEvent::new(LogCategory::Combat, "Hit!"));
+        app.world.send_event(GameLogEvent::new(LogCategory::Loot, "Gold!"));
+        app.update();
+        let combat_button = app.world.query::<(Entity, &LogFilterButton)>().iter(&app.world)
+            .find(|(_, button)| button.0 == LogCategory::Combat)
+            .map(|(entity, _)| entity)
+            .unwrap();
+        app.world.entity_mut(combat_button).insert(Interaction::Clicked);
+        app.update();
+        assert!(app.world.resource::<LogFilter>().hidden.contains(&LogCategory::Combat));
+        let displays: Vec<_> = app.world.query::<(&GameLog, &Style)>().iter(&app.world)
+            .map(|(log, style)| (log.category, style.display))
+            .collect();
+        assert!(displays.contains(&(LogCategory::Combat, Display::None)));
+        assert!(displays.contains(&(LogCategory::Loot, Display::Flex)));
     }
 
     #[test]
     fn it_scrolls_on_mouse_scroll() {
         let mut app = setup();
-        app.world.send_event(GameLogEvent("Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long".to_string()));
+        app.world.send_event(GameLogEvent::new(LogCategory::System, "Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long"));
         update(&mut app, 3);
         let top_before_mouse_scroll = get_scroll_list_top(&mut app);
         scroll_mouse_wheel(&mut app, 1.);
@@ -172,10 +560,36 @@ mod log_test {
         assert_eq!(top_before_mouse_scroll, top_after_scrolling_back);
     }
 
+    #[test]
+    fn it_leaves_the_mouse_wheel_to_the_camera_zoom() {
+        let mut app = setup();
+        app.world.send_event(GameLogEvent::new(LogCategory::System, "Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long"));
+        update(&mut app, 3);
+        let top_at_bottom = get_scroll_list_top(&mut app);
+        press_key(&mut app, KeyCode::LControl);
+        scroll_mouse_wheel(&mut app, 1.);
+        update(&mut app, 2);
+        assert_eq!(get_scroll_list_top(&mut app), top_at_bottom);
+    }
+
+    #[test]
+    fn it_toggles_the_log_panel() {
+        let mut app = setup();
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(KeyCode::Tab),
+            state: ButtonState::Pressed,
+        });
+        app.update();
+        let visibility = app.world
+            .query_filtered::<&Visibility, With<GameLogPanel>>().single(&app.world);
+        assert_eq!(*visibility, Visibility::Hidden);
+    }
+
     #[test]
     fn it_scrolls_to_bottom_on_log_event() {
         let mut app = setup();
-        app.world.send_event(GameLogEvent("Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long".to_string()));
+        app.world.send_event(GameLogEvent::new(LogCategory::System, "Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long"));
         update(&mut app, 3);
         let mut logs_query = app.world.query::<&Node>();
         let (node, style, parent, scroll) = app.world
@@ -188,6 +602,95 @@ mod log_test {
         assert_eq!(scroll.position, -expected_top_value);
     }
 
+    #[test]
+    fn it_recycles_entries_past_the_history_limit() {
+        let mut app = setup();
+        for i in 0..LOG_HISTORY_LIMIT + 5 {
+            app.world.send_event(GameLogEvent::new(LogCategory::System, format!("Entry {i}")));
+        }
+        app.update();
+        let logs: Vec<_> = app.world.query_filtered::<&Text, With<GameLog>>().iter(&app.world)
+            .map(|text| text.sections[2].value.clone())
+            .collect();
+        assert_eq!(logs.len(), LOG_HISTORY_LIMIT);
+        assert!(!logs.contains(&"Entry 4".to_string()));
+        let list = app.world.query::<&ScrollingList>().single(&app.world);
+        let newest = app.world.get::<Text>(*list.entries.back().unwrap()).unwrap();
+        assert_eq!(newest.sections[2].value, format!("Entry {}", LOG_HISTORY_LIMIT + 4));
+    }
+
+    #[test]
+    fn it_keeps_older_entries_in_view_while_scrolled_up() {
+        let mut app = setup();
+        app.world.send_event(GameLogEvent::new(LogCategory::System, "Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long"));
+        update(&mut app, 3);
+        scroll_mouse_wheel(&mut app, 1.);
+        update(&mut app, 2);
+        let top_while_reading = get_scroll_list_top(&mut app);
+        app.world.send_event(GameLogEvent::new(LogCategory::System, "Another line."));
+        update(&mut app, 3);
+        assert_eq!(get_scroll_list_top(&mut app), top_while_reading);
+        assert!(!app.world.query::<&ScrollingList>().single(&app.world).follow);
+    }
+
+    #[test]
+    fn it_scrolls_with_the_scroll_actions() {
+        let mut app = setup();
+        app.world.send_event(GameLogEvent::new(LogCategory::System, "Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long"));
+        update(&mut app, 3);
+        let top_at_bottom = get_scroll_list_top(&mut app);
+        press_key(&mut app, KeyCode::PageUp);
+        update(&mut app, 2);
+        assert_ne!(get_scroll_list_top(&mut app), top_at_bottom);
+        press_key(&mut app, KeyCode::PageDown);
+        update(&mut app, 2);
+        assert_eq!(get_scroll_list_top(&mut app), top_at_bottom);
+        assert!(app.world.query::<&ScrollingList>().single(&app.world).follow);
+    }
+
+    #[test]
+    fn it_leaves_the_scroll_actions_to_the_open_inventory() {
+        let mut app = setup();
+        app.world.send_event(GameLogEvent::new(LogCategory::System, "Hello World!Long LOng Long \n Long \n Long \n LOng\n very long! \n Very Long \n Very LOng \n Very Long"));
+        update(&mut app, 3);
+        let top_at_bottom = get_scroll_list_top(&mut app);
+        app.insert_resource(InventoryCursor { open: true, ..default() });
+        press_key(&mut app, KeyCode::PageUp);
+        update(&mut app, 2);
+        assert_eq!(get_scroll_list_top(&mut app), top_at_bottom);
+    }
+
+    #[test]
+    fn it_expands_and_collapses_the_log_panel() {
+        let mut app = setup();
+        press_key(&mut app, KeyCode::L);
+        app.update();
+        let style = app.world.query_filtered::<&Style, With<GameLogPanel>>().single(&app.world);
+        assert_eq!(style.size.height, Val::Percent(EXPANDED_LOG_HEIGHT));
+        release_key(&mut app, KeyCode::L);
+        press_key(&mut app, KeyCode::L);
+        app.update();
+        let style = app.world.query_filtered::<&Style, With<GameLogPanel>>().single(&app.world);
+        assert_eq!(style.size.height, Val::Percent(COLLAPSED_LOG_HEIGHT));
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Pressed,
+        });
+    }
+
+    fn release_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Released,
+        });
+        app.update();
+    }
+
     fn get_scroll_list_top(app: &mut App) -> Val {
         app.world
             .query_filtered::<&Style, With<ScrollingList>>().single(&app.world)
@@ -206,10 +709,16 @@ mod log_test {
     fn setup() -> App {
         let mut app = App::new();
         app.add_plugins(LoadTestPlugins);
+        app.add_plugin(LocalizationPlugin);
+        app.insert_resource(Localization::shipped());
+        app.add_state::<GameState>();
         app.add_plugin(TextPlugin::default());
         app.add_plugin(UiPlugin::default());
         app.add_plugin(InputPlugin);
+        app.add_plugin(ActionPlugin);
+        app.insert_resource(SettingsPath::temporary());
         app.add_plugin(UIPlugin);
+        enter_game(&mut app);
         app
     }
 }
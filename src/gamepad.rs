@@ -38,7 +38,7 @@ pub fn gamepad_detection(
     }
 }
 
-pub fn get_right_axis_direction(axes: Res<Axis<GamepadAxis>>, gamepad: Gamepad) -> Vec2 {
+pub fn get_right_axis_direction(axes: &Axis<GamepadAxis>, gamepad: Gamepad) -> Vec2 {
     let axis_rx = GamepadAxis {
         gamepad,
         axis_type: GamepadAxisType::RightStickX,
@@ -51,7 +51,7 @@ pub fn get_right_axis_direction(axes: Res<Axis<GamepadAxis>>, gamepad: Gamepad)
 }
 
 
-pub fn get_left_axis_direction(axes: Res<Axis<GamepadAxis>>, gamepad: Gamepad) -> Vec2 {
+pub fn get_left_axis_direction(axes: &Axis<GamepadAxis>, gamepad: Gamepad) -> Vec2 {
     let axis_lx = GamepadAxis {
         gamepad,
         axis_type: GamepadAxisType::LeftStickX,
@@ -63,7 +63,7 @@ pub fn get_left_axis_direction(axes: Res<Axis<GamepadAxis>>, gamepad: Gamepad) -
     get_axis_direction(axes, axis_lx, axis_ly)
 }
 
-fn get_axis_direction(axes: Res<Axis<GamepadAxis>>, axis_rx: GamepadAxis, axis_ry: GamepadAxis) -> Vec2 {
+fn get_axis_direction(axes: &Axis<GamepadAxis>, axis_rx: GamepadAxis, axis_ry: GamepadAxis) -> Vec2 {
     let mut direction = Vec2::ZERO;
     if let Some(x) = axes.get(axis_rx) {
         direction.x = x;
@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use crate::enemy::ZombieAttack;
+use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory, LogSeverity};
+use crate::game_state::GameState;
+use crate::localization::Localization;
+use crate::player::Player;
+use crate::spell::SpellHit;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<DamageEvent>()
+            .add_event::<SpellHit>()
+            .add_event::<ZombieAttack>()
+            .add_systems((
+                set_respawn_points,
+                damage_from_spells,
+                damage_from_zombies,
+                apply_damage,
+                tick_damage_effects,
+                apply_system_buffers,
+                respawn_players,
+            ).chain().in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+pub const PLAYER_HEALTH: u32 = 5;
+/// Seconds a hit entity ignores further damage.
+const INVULNERABILITY: f32 = 0.5;
+/// Seconds a hit entity is pushed back instead of moving on its own.
+const KNOCKBACK: f32 = 0.15;
+const KNOCKBACK_SPEED: f32 = 150.;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Health { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health::new(1)
+    }
+}
+
+impl From<&EntityInstance> for Health {
+    fn from(entity_instance: &EntityInstance) -> Health {
+        match entity_instance.identifier.as_ref() {
+            "Player" => Health::new(PLAYER_HEALTH),
+            _ => Health::default(),
+        }
+    }
+}
+
+/// Damage dealt by each hit of the entity holding it.
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq)]
+pub struct Damage(pub u32);
+
+/// Every hit goes through this event so invulnerability, knockback and death are handled in one place.
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: u32,
+    /// What dealt the damage, knocking the target away from it.
+    pub source: Option<Entity>,
+}
+
+/// Marks an entity whose health ran out. Enemies play their death animation, players respawn.
+#[derive(Component)]
+pub struct Dead;
+
+#[derive(Component)]
+pub struct Invulnerable(Timer);
+
+/// While present, the entity's own movement systems leave its `Velocity` alone.
+#[derive(Component)]
+pub struct Knockback(Timer);
+
+/// Where a player comes back after dying: its LDtk spawn point or the last checkpoint touched.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct RespawnPoint(pub Vec3);
+
+fn set_respawn_points(
+    mut commands: Commands,
+    player_q: Query<(Entity, &Transform), (Added<Player>, Without<RespawnPoint>)>,
+) {
+    for (player, transform) in player_q.iter() {
+        commands.entity(player).insert(RespawnPoint(transform.translation));
+    }
+}
+
+fn damage_from_spells(mut hit_ev: EventReader<SpellHit>, mut damage_ev: EventWriter<DamageEvent>) {
+    for hit in hit_ev.iter() {
+        damage_ev.send(DamageEvent { target: hit.target, amount: hit.damage, source: Some(hit.caster) });
+    }
+}
+
+fn damage_from_zombies(mut attack_ev: EventReader<ZombieAttack>, mut damage_ev: EventWriter<DamageEvent>) {
+    for attack in attack_ev.iter() {
+        damage_ev.send(DamageEvent { target: attack.target, amount: attack.damage, source: Some(attack.zombie) });
+    }
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_ev: EventReader<DamageEvent>,
+    mut target_q: Query<(&mut Health, &GlobalTransform, Option<&mut Velocity>),
+        (Without<Invulnerable>, Without<Dead>)>,
+    source_q: Query<&GlobalTransform>,
+) {
+    // Invulnerable is only inserted once commands apply, so later hits this frame are dropped here.
+    let mut hit = HashSet::new();
+    for event in damage_ev.iter() {
+        let Ok((mut health, transform, velocity)) = target_q.get_mut(event.target) else { continue; };
+        if !hit.insert(event.target) { continue; }
+        health.current = health.current.saturating_sub(event.amount);
+        let mut target = commands.entity(event.target);
+        target.insert(Invulnerable(Timer::from_seconds(INVULNERABILITY, TimerMode::Once)));
+        if let (Some(mut velocity), Some(Ok(source))) = (velocity, event.source.map(|s| source_q.get(s))) {
+            let away = (transform.translation() - source.translation()).truncate().normalize_or_zero();
+            velocity.linvel = away * KNOCKBACK_SPEED;
+            target.insert(Knockback(Timer::from_seconds(KNOCKBACK, TimerMode::Once)));
+        }
+        if health.is_dead() {
+            target.insert(Dead);
+        }
+    }
+}
+
+fn tick_damage_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut invulnerable_q: Query<(Entity, &mut Invulnerable)>,
+    mut knockback_q: Query<(Entity, &mut Knockback, Option<&mut Velocity>)>,
+) {
+    for (entity, mut invulnerable) in invulnerable_q.iter_mut() {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+    for (entity, mut knockback, velocity) in knockback_q.iter_mut() {
+        if knockback.0.tick(time.delta()).finished() {
+            if let Some(mut velocity) = velocity {
+                velocity.linvel = Vec2::ZERO;
+            }
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+fn respawn_players(
+    mut commands: Commands,
+    mut player_q: Query<(Entity, &mut Health, &mut Transform, &RespawnPoint, Option<&mut Velocity>),
+        (With<Player>, Added<Dead>)>,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
+) {
+    for (player, mut health, mut transform, respawn_point, velocity) in player_q.iter_mut() {
+        health.current = health.max;
+        transform.translation = respawn_point.0;
+        if let Some(mut velocity) = velocity {
+            velocity.linvel = Vec2::ZERO;
+        }
+        commands.entity(player).remove::<(Dead, Knockback)>();
+        log.send(GameLogEvent::new(LogCategory::Combat, localization.get("log.player_died")).with_severity(LogSeverity::Critical));
+    }
+}
+
+#[cfg(test)]
+mod health_test {
+    use super::*;
+    use crate::localization::LocalizationPlugin;
+    use crate::test_utils::{enter_game, inflict_damage, LoadTestPlugins, update};
+
+    #[test]
+    fn it_loses_health_when_damaged() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        inflict_damage(&mut app, target, 2);
+        assert_eq!(app.world.get::<Health>(target).unwrap().current, 3);
+    }
+
+    #[test]
+    fn it_ignores_damage_while_invulnerable() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        inflict_damage(&mut app, target, 1);
+        inflict_damage(&mut app, target, 1);
+        assert_eq!(app.world.get::<Health>(target).unwrap().current, 4);
+        assert!(app.world.get::<Invulnerable>(target).is_some());
+    }
+
+    #[test]
+    fn it_knocks_targets_away_from_the_source() {
+        let mut app = setup();
+        let source = app.world.spawn(TransformBundle::from_transform(Transform::from_xyz(-10., 0., 0.))).id();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        app.update();
+        app.world.send_event(DamageEvent { target, amount: 1, source: Some(source) });
+        app.update();
+        let velocity = app.world.get::<Velocity>(target).unwrap();
+        assert_eq!(velocity.linvel, Vec2::new(KNOCKBACK_SPEED, 0.));
+        assert!(app.world.get::<Knockback>(target).is_some());
+    }
+
+    #[test]
+    fn it_marks_targets_dead_when_health_runs_out() {
+        let mut app = setup();
+        let target = spawn_target(&mut app, Vec3::ZERO);
+        inflict_damage(&mut app, target, 9);
+        assert_eq!(app.world.get::<Health>(target).unwrap().current, 0);
+        assert!(app.world.get::<Dead>(target).is_some());
+    }
+
+    #[test]
+    fn it_respawns_dead_players_at_their_respawn_point() {
+        let mut app = setup();
+        let player = spawn_target(&mut app, Vec3::new(20., 30., 1.));
+        app.world.entity_mut(player).insert(Player);
+        app.update();
+        app.world.get_mut::<Transform>(player).unwrap().translation = Vec3::new(90., 90., 1.);
+        inflict_damage(&mut app, player, PLAYER_HEALTH);
+        assert_eq!(app.world.get::<Transform>(player).unwrap().translation, Vec3::new(20., 30., 1.));
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, PLAYER_HEALTH);
+        assert!(app.world.get::<Dead>(player).is_none());
+    }
+
+    #[test]
+    fn it_turns_spell_hits_into_damage() {
+        let mut app = setup();
+        let caster = app.world.spawn(TransformBundle::default()).id();
+        let target = spawn_target(&mut app, Vec3::X);
+        app.world.send_event(SpellHit { caster, target, damage: 2 });
+        update(&mut app, 2);
+        assert_eq!(app.world.get::<Health>(target).unwrap().current, 3);
+    }
+
+    fn spawn_target(app: &mut App, translation: Vec3) -> Entity {
+        app.world.spawn((
+            Health::new(5),
+            Velocity::default(),
+            TransformBundle::from_transform(Transform::from_translation(translation)),
+        )).id()
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
+            .add_plugin(HealthPlugin);
+        enter_game(&mut app);
+        app
+    }
+}
@@ -1,16 +1,79 @@
-use bevy::prelude::{App, Commands, Component, Entity, EventReader, Plugin, Query, Text, With};
+use std::collections::HashMap;
+use bevy::prelude::{apply_system_buffers, default, Added, App, AssetServer, Assets, BuildChildren, DetectChanges, FromWorld, Handle, IntoSystemSetConfig, Resource, SystemSet, Changed, Color, Commands, Component, DetectChangesMut, Entity, EventReader, EventWriter, GlobalTransform, IntoSystemConfig, IntoSystemConfigs, OnUpdate, Parent, Plugin, Query, Ref, RemovedComponents, Res, Sprite, SpriteSheetBundle, Text, TextureAtlas, TextureAtlasSprite, Transform, Vec2, Vec3, Visibility, With, World};
+use bevy::text::TextLayoutInfo;
 use bevy_rapier2d::pipeline::CollisionEvent;
+use crate::actions::{Action, ActionInput, ActionMap, ButtonPrompt, InputDevice};
+use crate::cursor_indicator::CursorIndicator;
+use crate::dialogue::no_dialogue;
+use crate::game_state::GameState;
+use crate::inventory::inventory_closed;
+use crate::localization::{fill, Localization};
+use crate::network::is_offline;
 
 pub struct InteractionPlugin;
 
 impl Plugin for InteractionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(handle_interactive_collisions);
+        app
+            .add_event::<InteractionEvent>()
+            .init_resource::<ButtonGlyphs>()
+            .add_system(spawn_prompt_glyphs.before(InteractionSet::Dispatch).in_set(OnUpdate(GameState::InGame)))
+            .add_system(place_prompt_glyphs.after(InteractionSet::React).in_set(OnUpdate(GameState::InGame)))
+            .add_systems((
+                handle_interactive_collisions,
+                apply_system_buffers,
+                update_focus,
+                apply_system_buffers,
+                dispatch_interactions.run_if(is_offline).run_if(inventory_closed).run_if(no_dialogue),
+            ).chain().in_set(InteractionSet::Dispatch).in_set(OnUpdate(GameState::InGame)))
+            .configure_set(InteractionSet::React.after(InteractionSet::Dispatch))
+            .add_system(highlight_focus.after(InteractionSet::Dispatch).in_set(OnUpdate(GameState::InGame)));
     }
 }
 
+const HIGHLIGHT: Color = Color::rgb(1., 1., 0.5);
+/// How far off the cursor indicator, in radians, a target still counts as pointed at.
+const AIM_CONE: f32 = 0.5;
+/// Gap between a prompt's gamepad glyph and its text, in text units.
+const GLYPH_MARGIN: f32 = 14.;
+/// Brings the 16px glyphs to about the height of the prompt font.
+const GLYPH_SCALE: f32 = 1.25;
+
 pub trait Interaction {
     fn interact(&mut self);
+    /// Shown above the actor while this is what they would interact with. A `{button}` placeholder
+    /// is replaced with the interact button of the [InputDevice] in use.
+    fn prompt(&self, text: &Localization) -> String;
+}
+
+/// Registers a component as something actors can interact with, see [InteractionAppExt].
+pub trait InteractionAppExt {
+    fn register_interaction<T: Component + Interaction>(&mut self) -> &mut Self;
+}
+
+impl InteractionAppExt for App {
+    fn register_interaction<T: Component + Interaction>(&mut self) -> &mut Self {
+        self.init_resource::<ActionMap>()
+            .init_resource::<InputDevice>()
+            .add_systems((
+            react_to_interactions::<T>,
+            update_interaction_prompt::<T>,
+        ).chain().in_set(InteractionSet::React).in_set(OnUpdate(GameState::InGame)))
+    }
+}
+
+/// Systems that need to see an [InteractionEvent] before or after the target reacts to it
+/// order themselves against these.
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InteractionSet {
+    Dispatch,
+    React,
+}
+
+/// `actor` used the interact action on `target`.
+pub struct InteractionEvent {
+    pub actor: Entity,
+    pub target: Entity,
 }
 
 #[derive(Component, Default)]
@@ -19,50 +82,264 @@ pub struct Interactor;
 #[derive(Component, Default)]
 pub struct InteractiveText;
 
+/// The gamepad button of an [InteractiveText] prompt, a child of the text.
 #[derive(Component)]
-pub struct Interactive;
+pub struct PromptGlyph;
 
+/// `ui/gamepad_buttons.png`, laid out as [gamepad_glyph](crate::actions::gamepad_glyph) indexes it.
+#[derive(Resource)]
+pub struct ButtonGlyphs(Handle<TextureAtlas>);
+
+impl FromWorld for ButtonGlyphs {
+    fn from_world(world: &mut World) -> Self {
+        let image = world.resource::<AssetServer>().load("ui/gamepad_buttons.png");
+        let atlas = TextureAtlas::from_grid(image, Vec2::new(16., 16.), 8, 2, None, None);
+        ButtonGlyphs(world.resource_mut::<Assets<TextureAtlas>>().add(atlas))
+    }
+}
+
+/// Marks something in reach of an [Interactor], the `actor`.
+#[derive(Component)]
+pub struct Interactive {
+    pub actor: Entity,
+}
+
+/// The one [Interactive] its actor would interact with. `pinned` once picked by cycling, so
+/// moving around doesn't take it away until it goes out of reach.
+#[derive(Component, Default)]
+pub struct Focused {
+    pub pinned: bool,
+}
+
+/// Only the actor an [Interactive] belongs to leaving takes it away, and only losing the focused
+/// target clears that actor's prompt; [update_focus] has the prompt of the next one written.
 pub fn handle_interactive_collisions(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
     interactor_q: Query<&Interactor>,
-    mut interactive_text_q: Query<&mut Text, With<InteractiveText>>
+    interactive_q: Query<(&Interactive, Option<&Focused>)>,
+    mut interactive_text_q: Query<(Entity, &mut Text, &Parent), With<InteractiveText>>,
+    mut glyph_q: Query<(&mut Visibility, &Parent), With<PromptGlyph>>,
 ) {
+    // Pairs that touched earlier in the same batch, whose `Interactive` isn't inserted yet.
+    let mut started = Vec::new();
     for collision_event in collision_events.iter() {
-        match collision_event {
+        match *collision_event {
             CollisionEvent::Started(e1, e2, _) => {
-                add_interactive_to_object(
-                    &mut commands,
-                    &interactor_q,
-                    vec![e1, e2],
-                );
+                for (actor, object) in [(e1, e2), (e2, e1)] {
+                    if add_interactive_to_object(&mut commands, &interactor_q, actor, object) {
+                        started.push((actor, object));
+                    }
+                }
             }
             CollisionEvent::Stopped(e1, e2, _) => {
-                remove_interactive_from_object(
-                    &mut commands,
-                    &interactor_q,
-                    vec![e1, e2],
-                );
-                remove_interactive_text(&mut interactive_text_q);
+                for (actor, object) in [(e1, e2), (e2, e1)] {
+                    let current = interactive_q.get(object).ok();
+                    let in_reach = matches!(current, Some((interactive, _)) if interactive.actor == actor);
+                    if !in_reach && !started.contains(&(actor, object)) { continue; }
+                    commands.entity(object).remove::<Interactive>();
+                    if matches!(current, Some((_, Some(_)))) {
+                        remove_interactive_text(actor, &mut interactive_text_q, &mut glyph_q);
+                    }
+                }
             }
         }
     }
 }
 
-fn remove_interactive_text(interactive_text_q: &mut Query<&mut Text, With<InteractiveText>>) {
-    for mut text in interactive_text_q.iter_mut() {
-        text.sections[0].value = "".to_string();
+/// Picks the target pointed at by `aim`, or else the nearest one.
+/// `candidates` must be sorted nearest first.
+pub fn pick_target(actor: Vec2, aim: Option<Vec2>, candidates: &[(Entity, Vec2)]) -> Option<Entity> {
+    let pointed_at = aim.and_then(|aim| candidates.iter()
+        .map(|&(target, position)| (target, aim.angle_between(position - actor).abs()))
+        .filter(|(_, angle)| *angle <= AIM_CONE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(target, _)| target));
+    pointed_at.or_else(|| candidates.first().map(|&(target, _)| target))
+}
+
+fn update_focus(
+    mut commands: Commands,
+    actions: ActionInput,
+    actor_q: Query<(Entity, &GlobalTransform), With<Interactor>>,
+    indicator_q: Query<(&Parent, &GlobalTransform), With<CursorIndicator>>,
+    candidate_q: Query<(Entity, &Interactive, &GlobalTransform)>,
+    mut focused_q: Query<(Entity, &mut Focused, Option<&Interactive>)>,
+    mut removed_interactive: RemovedComponents<Interactive>,
+) {
+    let candidates_left = removed_interactive.iter().count() > 0;
+    let mut candidates: HashMap<Entity, Vec<(Entity, Vec2)>> = HashMap::new();
+    for (target, interactive, transform) in candidate_q.iter() {
+        candidates.entry(interactive.actor).or_default()
+            .push((target, transform.translation().truncate()));
+    }
+    let mut current: HashMap<Entity, (Entity, bool)> = HashMap::new();
+    for (target, mut focused, interactive) in focused_q.iter_mut() {
+        match interactive {
+            Some(interactive) => {
+                // Other candidates leaving wipe the prompt, so have it written again.
+                if candidates_left { focused.set_changed(); }
+                current.insert(interactive.actor, (target, focused.pinned));
+            }
+            None => { commands.entity(target).remove::<Focused>(); }
+        }
+    }
+    let cycle = actions.just_pressed(Action::CycleTarget);
+    for (actor, actor_transform) in actor_q.iter() {
+        let position = actor_transform.translation().truncate();
+        let mut targets = candidates.remove(&actor).unwrap_or_default();
+        targets.sort_by(|(a, a_pos), (b, b_pos)| position.distance_squared(*a_pos)
+            .total_cmp(&position.distance_squared(*b_pos))
+            .then(a.cmp(b)));
+        let current_target = current.get(&actor).copied();
+        let (next, pinned) = match current_target {
+            _ if targets.is_empty() => continue,
+            Some((target, _)) if cycle => {
+                let index = targets.iter().position(|(t, _)| *t == target).unwrap_or(0);
+                (targets[(index + 1) % targets.len()].0, true)
+            }
+            Some((target, true)) => (target, true),
+            _ => {
+                let aim = indicator_q.iter()
+                    .find(|(parent, _)| parent.get() == actor)
+                    .map(|(_, transform)| {
+                        let (_, rotation, _) = transform.to_scale_rotation_translation();
+                        (rotation * Vec3::X).truncate()
+                    });
+                (pick_target(position, aim, &targets).unwrap(), false)
+            }
+        };
+        if current_target.map(|(target, _)| target) == Some(next) { continue; }
+        if let Some((target, _)) = current_target {
+            commands.entity(target).remove::<Focused>();
+        }
+        commands.entity(next).insert(Focused { pinned });
     }
 }
 
-fn remove_interactive_from_object(
-    commands: &mut Commands,
-    interactor_q: &Query<&Interactor>,
-    entities: Vec<&Entity>,
+fn dispatch_interactions(
+    actions: ActionInput,
+    interactive_q: Query<(Entity, &Interactive), With<Focused>>,
+    mut interaction_ev: EventWriter<InteractionEvent>,
+) {
+    if !actions.just_pressed(Action::Interact) { return; }
+    for (target, interactive) in interactive_q.iter() {
+        interaction_ev.send(InteractionEvent { actor: interactive.actor, target });
+    }
+}
+
+fn highlight_focus(
+    focused_q: Query<Entity, Changed<Focused>>,
+    mut unfocused: RemovedComponents<Focused>,
+    mut atlas_sprite_q: Query<&mut TextureAtlasSprite>,
+    mut sprite_q: Query<&mut Sprite>,
+) {
+    let mut tint = |entity: Entity, color: Color| {
+        if let Ok(mut sprite) = atlas_sprite_q.get_mut(entity) {
+            sprite.color = color;
+        }
+        if let Ok(mut sprite) = sprite_q.get_mut(entity) {
+            sprite.color = color;
+        }
+    };
+    for entity in unfocused.iter() {
+        tint(entity, Color::WHITE);
+    }
+    for entity in focused_q.iter() {
+        tint(entity, HIGHLIGHT);
+    }
+}
+
+fn react_to_interactions<T: Component + Interaction>(
+    mut interaction_ev: EventReader<InteractionEvent>,
+    mut target_q: Query<&mut T>,
 ) {
-    for e in entities.into_iter() {
-        if interactor_q.get(*e).is_err() {
-            commands.entity(*e).remove::<Interactive>();
+    for event in interaction_ev.iter() {
+        if let Ok(mut target) = target_q.get_mut(event.target) {
+            target.interact();
+        }
+    }
+}
+
+fn update_interaction_prompt<T: Component + Interaction>(
+    localization: Res<Localization>,
+    action_map: Res<ActionMap>,
+    device: Res<InputDevice>,
+    target_q: Query<(Ref<T>, Ref<Focused>, &Interactive)>,
+    mut text_q: Query<(Entity, &mut Text, &Parent), With<InteractiveText>>,
+    mut glyph_q: Query<(&mut TextureAtlasSprite, &mut Visibility, &Parent), With<PromptGlyph>>,
+) {
+    let refresh = localization.is_changed() || action_map.is_changed() || device.is_changed();
+    let button = action_map.prompt(Action::Interact, *device);
+    for (target, focused, interactive) in target_q.iter() {
+        if !refresh && !focused.is_changed() && !target.is_changed() { continue; }
+        let template = target.prompt(&localization);
+        let (label, glyph) = match &button {
+            Some(ButtonPrompt::Text(label)) => (label.as_str(), None),
+            Some(ButtonPrompt::Glyph(glyph)) if template.contains("{button}") => ("", Some(*glyph)),
+            _ => ("", None),
+        };
+        let prompt = fill(&template, &[("button", label)]).trim().to_string();
+        for (text_entity, mut text, parent) in text_q.iter_mut() {
+            if parent.get() != interactive.actor { continue; }
+            text.sections[0].value = prompt.clone();
+            for (mut sprite, mut visibility, _) in glyph_q.iter_mut().filter(|(_, _, parent)| parent.get() == text_entity) {
+                match glyph {
+                    Some(glyph) => {
+                        sprite.index = glyph;
+                        *visibility = Visibility::Inherited;
+                    }
+                    None => *visibility = Visibility::Hidden,
+                }
+            }
+        }
+    }
+}
+
+fn spawn_prompt_glyphs(
+    mut commands: Commands,
+    glyphs: Res<ButtonGlyphs>,
+    text_q: Query<Entity, Added<InteractiveText>>,
+) {
+    for text in text_q.iter() {
+        commands.entity(text).with_children(|parent| {
+            parent.spawn((
+                SpriteSheetBundle {
+                    texture_atlas: glyphs.0.clone(),
+                    transform: Transform::from_xyz(0., 0., 0.1).with_scale(Vec3::splat(GLYPH_SCALE)),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                PromptGlyph,
+            ));
+        });
+    }
+}
+
+/// Keeps glyphs just left of their prompt text, which is centred on the actor.
+fn place_prompt_glyphs(
+    text_q: Query<&TextLayoutInfo, With<InteractiveText>>,
+    mut glyph_q: Query<(&mut Transform, &Parent), With<PromptGlyph>>,
+) {
+    for (mut transform, parent) in glyph_q.iter_mut() {
+        let Ok(layout) = text_q.get(parent.get()) else { continue; };
+        let x = -layout.size.x / 2. - GLYPH_MARGIN;
+        if transform.translation.x != x {
+            transform.translation.x = x;
+        }
+    }
+}
+
+fn remove_interactive_text(
+    actor: Entity,
+    interactive_text_q: &mut Query<(Entity, &mut Text, &Parent), With<InteractiveText>>,
+    glyph_q: &mut Query<(&mut Visibility, &Parent), With<PromptGlyph>>,
+) {
+    for (text_entity, mut text, parent) in interactive_text_q.iter_mut() {
+        if parent.get() != actor { continue; }
+        text.sections[0].value = "".to_string();
+        for (mut visibility, _) in glyph_q.iter_mut().filter(|(_, parent)| parent.get() == text_entity) {
+            *visibility = Visibility::Hidden;
         }
     }
 }
@@ -70,25 +347,31 @@ fn remove_interactive_from_object(
 fn add_interactive_to_object(
     commands: &mut Commands,
     interactor_q: &Query<&Interactor>,
-    entities: Vec<&Entity>,
-) {
-    for e in entities.into_iter() {
-        if interactor_q.get(*e).is_err() {
-            commands.entity(*e).insert(Interactive);
-        }
-    };
+    actor: Entity,
+    object: Entity,
+) -> bool {
+    let added = interactor_q.get(actor).is_ok() && interactor_q.get(object).is_err();
+    if added {
+        commands.entity(object).insert(Interactive { actor });
+    }
+    added
 }
 
 #[cfg(test)]
 mod interaction_tests {
-    use bevy::prelude::{App, Entity, Text, TextBundle, Transform};
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::prelude::{App, BuildWorldChildren, Color, Component, Entity, KeyCode, Sprite, Text, TextBundle, Transform, TransformBundle, Vec2, With};
     use bevy::text::TextStyle;
     use bevy_rapier2d::dynamics::LockedAxes;
     use bevy_rapier2d::prelude::{ActiveEvents, Collider, CollisionEvent, GravityScale, NoUserData, RapierPhysicsPlugin, RigidBody, Sensor};
     use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
-    use crate::interaction::{InteractionPlugin, Interactive, InteractiveText, Interactor};
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::game_state::GameState;
+    use crate::interaction::{Focused, HIGHLIGHT, Interaction, InteractionAppExt, InteractionPlugin, Interactive, InteractiveText, Interactor, pick_target};
+    use crate::localization::{Localization, LocalizationPlugin};
     use crate::physics_bundle::{CharacterPhysicsBundle, ObjectPhysicsBundle};
-    use crate::test_utils::LoadTestPlugins;
+    use crate::test_utils::{enter_game, LoadTestPlugins, update};
 
     #[test]
     fn it_adds_interactive_to_object_when_near_interactor() {
@@ -115,18 +398,205 @@ mod interaction_tests {
     #[test]
     fn it_removes_interactive_text_when_player_moves_away_from_door() {
         let (mut app, player, object) = setup();
-        app.update();
+        app.world.entity_mut(object).insert(Lever::default());
+        let prompt = spawn_prompt(&mut app, player);
+        app.world.send_event(CollisionEvent::Started(player, object, CollisionEventFlags::SENSOR));
+        update(&mut app, 2);
+        assert_eq!(app.world.get::<Text>(prompt).unwrap().sections[0].value, "Pull");
         app.world.send_event(CollisionEvent::Stopped(player, object, CollisionEventFlags::SENSOR));
         app.update();
-        let text = app.world.get::<Text>(player).unwrap();
-        assert_eq!(text.sections[0].value, "");
+        assert_eq!(app.world.get::<Text>(prompt).unwrap().sections[0].value, "");
+    }
+
+    #[test]
+    fn it_keeps_the_focused_prompt_when_another_target_goes_out_of_reach() {
+        let (mut app, player, near) = setup();
+        let prompt = spawn_prompt(&mut app, player);
+        let far = spawn_lever_in_reach(&mut app, player, near);
+        app.update();
+        app.world.send_event(CollisionEvent::Stopped(player, far, CollisionEventFlags::SENSOR));
+        update(&mut app, 2);
+        assert!(app.world.get::<Interactive>(far).is_none());
+        assert!(app.world.get::<Focused>(near).is_some());
+        assert_eq!(app.world.get::<Text>(prompt).unwrap().sections[0].value, "Pull");
+    }
+
+    #[test]
+    fn it_keeps_targets_in_reach_when_something_else_leaves_them() {
+        let (mut app, player, object) = setup();
+        let zombie = app.world.spawn(Transform::default()).id();
+        app.world.send_event(CollisionEvent::Started(player, object, CollisionEventFlags::SENSOR));
+        app.update();
+        app.world.send_event(CollisionEvent::Stopped(zombie, object, CollisionEventFlags::SENSOR));
+        app.update();
+        assert_eq!(app.world.get::<Interactive>(object).unwrap().actor, player);
+    }
+
+    #[test]
+    fn it_only_marks_objects_touched_by_an_interactor() {
+        let (mut app, _, object) = setup();
+        let other = app.world.spawn(Transform::default()).id();
+        app.update();
+        app.world.entity_mut(object).remove::<Interactive>();
+        app.world.send_event(CollisionEvent::Started(other, object, CollisionEventFlags::SENSOR));
+        app.update();
+        assert!(app.world.get::<Interactive>(object).is_none());
+    }
+
+    #[test]
+    fn it_reacts_with_the_registered_interaction() {
+        let (mut app, player, object) = setup();
+        app.world.entity_mut(object).insert(Lever::default());
+        app.world.send_event(CollisionEvent::Started(player, object, CollisionEventFlags::SENSOR));
+        app.update();
+        press_interact_key(&mut app);
+        app.update();
+        assert!(app.world.get::<Lever>(object).unwrap().pulled);
+    }
+
+    #[test]
+    fn it_ignores_interact_without_a_target() {
+        let (mut app, _, object) = setup();
+        app.world.entity_mut(object).insert(Lever::default());
+        app.update();
+        app.world.entity_mut(object).remove::<Interactive>();
+        press_interact_key(&mut app);
+        app.update();
+        assert!(!app.world.get::<Lever>(object).unwrap().pulled);
+    }
+
+    #[test]
+    fn it_shows_the_target_prompt_above_the_actor() {
+        let (mut app, player, object) = setup();
+        app.world.entity_mut(object).insert(Lever::default());
+        let prompt = spawn_prompt(&mut app, player);
+        app.world.send_event(CollisionEvent::Started(player, object, CollisionEventFlags::SENSOR));
+        app.update();
+        app.update();
+        assert_eq!(app.world.get::<Text>(prompt).unwrap().sections[0].value, "Pull");
+        press_interact_key(&mut app);
+        app.update();
+        assert_eq!(app.world.get::<Text>(prompt).unwrap().sections[0].value, "Push");
+        let unrelated = app.world
+            .query_filtered::<&Text, (With<InteractiveText>, With<Interactor>)>()
+            .single(&app.world);
+        assert_eq!(unrelated.sections[0].value, "text");
+    }
+
+    #[test]
+    fn it_picks_the_nearest_target_without_aim() {
+        let (near, far) = (Entity::from_raw(1), Entity::from_raw(2));
+        let candidates = [(near, Vec2::new(5., 0.)), (far, Vec2::new(0., 10.))];
+        assert_eq!(pick_target(Vec2::ZERO, None, &candidates), Some(near));
+    }
+
+    #[test]
+    fn it_prefers_the_target_being_aimed_at() {
+        let (near, far) = (Entity::from_raw(1), Entity::from_raw(2));
+        let candidates = [(near, Vec2::new(5., 0.)), (far, Vec2::new(0., 10.))];
+        assert_eq!(pick_target(Vec2::ZERO, Some(Vec2::Y), &candidates), Some(far));
+        assert_eq!(pick_target(Vec2::ZERO, Some(Vec2::NEG_Y), &candidates), Some(near));
+    }
+
+    #[test]
+    fn it_only_interacts_with_the_focused_target() {
+        let (mut app, player, near) = setup();
+        let far = spawn_lever_in_reach(&mut app, player, near);
+        press_interact_key(&mut app);
+        app.update();
+        assert!(app.world.get::<Lever>(near).unwrap().pulled);
+        assert!(!app.world.get::<Lever>(far).unwrap().pulled);
+    }
+
+    #[test]
+    fn it_highlights_only_the_focused_target() {
+        let (mut app, player, near) = setup();
+        let far = spawn_lever_in_reach(&mut app, player, near);
+        app.update();
+        assert!(app.world.get::<Focused>(far).is_none());
+        assert_eq!(app.world.get::<Sprite>(near).unwrap().color, HIGHLIGHT);
+        assert_eq!(app.world.get::<Sprite>(far).unwrap().color, Color::WHITE);
+    }
+
+    #[test]
+    fn it_cycles_focus_between_targets() {
+        let (mut app, player, near) = setup();
+        let far = spawn_lever_in_reach(&mut app, player, near);
+        press_key(&mut app, KeyCode::Q);
+        update(&mut app, 2);
+        assert!(app.world.get::<Focused>(far).unwrap().pinned);
+        assert!(app.world.get::<Focused>(near).is_none());
+        assert_eq!(app.world.get::<Sprite>(near).unwrap().color, Color::WHITE);
+        press_interact_key(&mut app);
+        app.update();
+        assert!(app.world.get::<Lever>(far).unwrap().pulled);
+        assert!(!app.world.get::<Lever>(near).unwrap().pulled);
+    }
+
+    fn spawn_prompt(app: &mut App, actor: Entity) -> Entity {
+        let prompt = app.world.spawn((
+            TextBundle::from_section("", TextStyle::default()),
+            InteractiveText,
+        )).id();
+        app.world.entity_mut(actor).push_children(&[prompt]);
+        prompt
+    }
+
+    /// Puts levers on `near` and on a new entity a bit further away, both in reach of `player`.
+    fn spawn_lever_in_reach(app: &mut App, player: Entity, near: Entity) -> Entity {
+        app.world.entity_mut(near).insert((Lever::default(), Sprite::default()));
+        let far = app.world.spawn((
+            Lever::default(),
+            Sprite::default(),
+            TransformBundle::from_transform(Transform::from_xyz(20., 0., 0.)),
+        )).id();
+        app.update();
+        for object in [near, far] {
+            app.world.send_event(CollisionEvent::Started(player, object, CollisionEventFlags::SENSOR));
+        }
+        app.update();
+        far
+    }
+
+    #[derive(Component, Default)]
+    struct Lever {
+        pulled: bool,
+    }
+
+    impl Interaction for Lever {
+        fn interact(&mut self) {
+            self.pulled = !self.pulled;
+        }
+
+        fn prompt(&self, _: &Localization) -> String {
+            if self.pulled { "Push".to_string() } else { "Pull".to_string() }
+        }
+    }
+
+    fn press_interact_key(app: &mut App) {
+        press_key(app, KeyCode::E);
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Pressed,
+        });
     }
 
     fn setup() -> (App, Entity, Entity) {
         let mut app = App::new();
         app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
             .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.))
-            .add_plugin(InteractionPlugin);
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(InteractionPlugin)
+            .register_interaction::<Lever>();
         let interactor = app.world.spawn((
             Interactor,
             CharacterPhysicsBundle {
@@ -146,8 +616,9 @@ mod interaction_tests {
                 sensor: Sensor,
                 events: ActiveEvents::COLLISION_EVENTS,
             },
-            Transform::default(),
+            TransformBundle::default(),
         )).id();
+        enter_game(&mut app);
         (app, interactor, object)
     }
 }
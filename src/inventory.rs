@@ -0,0 +1,516 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::actions::{Action, ActionInput};
+use crate::game_state::GameState;
+use crate::localization::Localization;
+use crate::network::NetworkPeer;
+use crate::player::Player;
+use crate::weapon::WeaponStats;
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<InventoryChanged>()
+            .add_event::<ItemPickedUp>()
+            .add_event::<UseItem>()
+            .init_resource::<InventoryCursor>()
+            .add_system(spawn_inventory_panel.in_schedule(OnEnter(GameState::InGame)))
+            .add_system(despawn_inventory_panel.in_schedule(OnExit(GameState::InGame)))
+            .add_systems((
+                announce_inventory_changes,
+                toggle_inventory_panel,
+                inventory_mouse_input,
+                inventory_action_input,
+                highlight_selected_slot,
+                refresh_inventory_slots,
+            ).chain().in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+pub const PLAYER_CAPACITY: usize = 12;
+const GRID_COLUMNS: usize = 4;
+const NORMAL_SLOT: Color = Color::rgba(0.15, 0.15, 0.15, 0.8);
+const SELECTED_SLOT: Color = Color::rgba(0.35, 0.35, 0.35, 0.9);
+const HELD_SLOT: Color = Color::rgba(0.45, 0.4, 0.15, 0.9);
+
+/// Anything that can be carried. New kinds of items are added as variants here.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Item {
+    /// Opens locked doors with the same `key_id`.
+    Key(String),
+    Weapon(WeaponStats),
+}
+
+impl Item {
+    pub fn max_stack(&self) -> u32 {
+        match self {
+            Item::Key(_) => 9,
+            Item::Weapon(_) => 1,
+        }
+    }
+
+    pub fn label(&self, text: &Localization) -> String {
+        match self {
+            Item::Key(key_id) => text.format("item.key", &[("key", key_id)]),
+            Item::Weapon(stats) => stats.name.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: Item,
+    pub count: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InventoryError {
+    Full,
+    Missing,
+    InvalidSlot,
+}
+
+/// A fixed number of slots, each holding a stack of one kind of item.
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory::new(PLAYER_CAPACITY)
+    }
+}
+
+impl Inventory {
+    pub fn new(capacity: usize) -> Self {
+        Inventory { slots: vec![None; capacity] }
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn count(&self, item: &Item) -> u32 {
+        self.slots.iter().flatten()
+            .filter(|stack| stack.item == *item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Tops up existing stacks of `item` before filling empty slots. Nothing is added unless all of it fits.
+    pub fn add(&mut self, item: Item, count: u32) -> Result<(), InventoryError> {
+        let max = item.max_stack();
+        let room: u32 = self.slots.iter()
+            .map(|slot| match slot {
+                Some(stack) if stack.item == item => max.saturating_sub(stack.count),
+                Some(_) => 0,
+                None => max,
+            })
+            .sum();
+        if room < count { return Err(InventoryError::Full); }
+        let mut left = count;
+        for stack in self.slots.iter_mut().flatten().filter(|stack| stack.item == item) {
+            let moved = left.min(max.saturating_sub(stack.count));
+            stack.count += moved;
+            left -= moved;
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if left == 0 { break; }
+            let moved = left.min(max);
+            *slot = Some(ItemStack { item: item.clone(), count: moved });
+            left -= moved;
+        }
+        Ok(())
+    }
+
+    /// Takes `count` of `item` from the last stacks first. Nothing is removed unless there is enough.
+    pub fn remove(&mut self, item: &Item, count: u32) -> Result<(), InventoryError> {
+        if self.count(item) < count { return Err(InventoryError::Missing); }
+        let mut left = count;
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot else { continue; };
+            if stack.item != *item || left == 0 { continue; }
+            let moved = left.min(stack.count);
+            stack.count -= moved;
+            left -= moved;
+            if stack.count == 0 {
+                *slot = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the stack in `from` onto `to`, merging matching stacks and swapping different ones.
+    pub fn move_item(&mut self, from: usize, to: usize) -> Result<(), InventoryError> {
+        if from >= self.slots.len() || to >= self.slots.len() { return Err(InventoryError::InvalidSlot); }
+        if from == to { return Ok(()); }
+        match (self.slots[from].take(), self.slots[to].take()) {
+            (Some(mut source), Some(mut target)) if source.item == target.item => {
+                let moved = source.count.min(target.item.max_stack().saturating_sub(target.count));
+                target.count += moved;
+                source.count -= moved;
+                self.slots[to] = Some(target);
+                self.slots[from] = (source.count > 0).then_some(source);
+            }
+            (source, target) => {
+                self.slots[to] = source;
+                self.slots[from] = target;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sent whenever an inventory's contents change.
+pub struct InventoryChanged {
+    pub owner: Entity,
+}
+
+/// Sent when an LDtk pickup is taken into an inventory, so it isn't respawned with its level.
+pub struct ItemPickedUp {
+    pub iid: String,
+}
+
+/// Sent when the player uses an item from the inventory panel.
+pub struct UseItem {
+    pub owner: Entity,
+    pub item: Item,
+}
+
+/// Whether the panel is open, the slot the keyboard, gamepad or mouse is on and the slot being moved.
+#[derive(Resource, Default)]
+pub struct InventoryCursor {
+    pub open: bool,
+    pub selected: usize,
+    pub held: Option<usize>,
+}
+
+/// Run condition for gameplay controls, which share keys and buttons with the open panel.
+pub fn inventory_closed(cursor: Option<Res<InventoryCursor>>) -> bool {
+    cursor.map_or(true, |cursor| !cursor.open)
+}
+
+impl InventoryCursor {
+    fn step(&mut self, offset: isize) {
+        self.selected = (self.selected as isize + offset).rem_euclid(PLAYER_CAPACITY as isize) as usize;
+    }
+
+    /// Picks up the selected stack, or drops the one already held onto the selected slot.
+    fn grab_or_drop(&mut self, inventory: &mut Inventory) {
+        match self.held.take() {
+            Some(from) => {
+                let _ = inventory.move_item(from, self.selected);
+            }
+            None if matches!(inventory.slots().get(self.selected), Some(Some(_))) => {
+                self.held = Some(self.selected);
+            }
+            None => {}
+        }
+    }
+}
+
+#[derive(Component)]
+struct InventoryPanel;
+
+#[derive(Component)]
+struct InventorySlot(usize);
+
+fn spawn_inventory_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut cursor: ResMut<InventoryCursor>,
+) {
+    *cursor = InventoryCursor::default();
+    let font = asset_server.load("fonts/kongtext/kongtext.ttf");
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect { right: Val::Px(16.), top: Val::Px(16.), ..default() },
+                size: Size::new(Val::Px(GRID_COLUMNS as f32 * 72.), Val::Auto),
+                flex_wrap: FlexWrap::Wrap,
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        InventoryPanel,
+    )).with_children(|parent| {
+        for index in 0..PLAYER_CAPACITY {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(64.), Val::Px(64.)),
+                        margin: UiRect::all(Val::Px(4.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_SLOT.into(),
+                    ..default()
+                },
+                InventorySlot(index),
+            )).with_children(|parent| {
+                parent.spawn(TextBundle::from_section("", TextStyle {
+                    font: font.clone(),
+                    font_size: 8.0,
+                    color: Color::WHITE,
+                }).with_text_alignment(TextAlignment::Center));
+            });
+        }
+    });
+}
+
+fn despawn_inventory_panel(mut commands: Commands, panel_q: Query<Entity, With<InventoryPanel>>) {
+    for panel in panel_q.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+}
+
+fn announce_inventory_changes(
+    inventory_q: Query<Entity, Changed<Inventory>>,
+    mut changed_ev: EventWriter<InventoryChanged>,
+) {
+    for owner in inventory_q.iter() {
+        changed_ev.send(InventoryChanged { owner });
+    }
+}
+
+fn toggle_inventory_panel(
+    actions: ActionInput,
+    mut cursor: ResMut<InventoryCursor>,
+    mut panel_q: Query<&mut Visibility, With<InventoryPanel>>,
+) {
+    if !actions.just_pressed(Action::Inventory) { return; }
+    cursor.open = !cursor.open;
+    cursor.held = None;
+    for mut visibility in panel_q.iter_mut() {
+        *visibility = if cursor.open { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+fn inventory_mouse_input(
+    slot_q: Query<(&Interaction, &InventorySlot), Changed<Interaction>>,
+    mut cursor: ResMut<InventoryCursor>,
+    mut inventory_q: Query<&mut Inventory, (With<Player>, Without<NetworkPeer>)>,
+) {
+    if !cursor.open { return; }
+    for (interaction, slot) in slot_q.iter() {
+        match interaction {
+            Interaction::Clicked => {
+                cursor.selected = slot.0;
+                let Ok(mut inventory) = inventory_q.get_single_mut() else { continue; };
+                cursor.grab_or_drop(&mut inventory);
+            }
+            Interaction::Hovered => cursor.selected = slot.0,
+            Interaction::None => {}
+        }
+    }
+}
+
+fn inventory_action_input(
+    actions: ActionInput,
+    mut cursor: ResMut<InventoryCursor>,
+    mut inventory_q: Query<(Entity, &mut Inventory), (With<Player>, Without<NetworkPeer>)>,
+    mut use_ev: EventWriter<UseItem>,
+) {
+    if !cursor.open { return; }
+    let columns = GRID_COLUMNS as isize;
+    for (action, offset) in [
+        (Action::MenuLeft, -1),
+        (Action::MenuRight, 1),
+        (Action::MenuUp, -columns),
+        (Action::MenuDown, columns),
+    ] {
+        if actions.just_pressed(action) {
+            cursor.step(offset);
+        }
+    }
+    let Ok((owner, mut inventory)) = inventory_q.get_single_mut() else { return; };
+    if actions.just_pressed(Action::MenuConfirm) {
+        cursor.grab_or_drop(&mut inventory);
+    }
+    if actions.just_pressed(Action::UseItem) {
+        if let Some(Some(stack)) = inventory.slots().get(cursor.selected) {
+            use_ev.send(UseItem { owner, item: stack.item.clone() });
+        }
+    }
+}
+
+fn highlight_selected_slot(
+    cursor: Res<InventoryCursor>,
+    mut slot_q: Query<(&InventorySlot, &mut BackgroundColor)>,
+) {
+    if !cursor.is_changed() { return; }
+    for (slot, mut background) in slot_q.iter_mut() {
+        *background = if cursor.held == Some(slot.0) {
+            HELD_SLOT.into()
+        } else if cursor.selected == slot.0 {
+            SELECTED_SLOT.into()
+        } else {
+            NORMAL_SLOT.into()
+        };
+    }
+}
+
+fn refresh_inventory_slots(
+    mut changed_ev: EventReader<InventoryChanged>,
+    inventory_q: Query<&Inventory, (With<Player>, Without<NetworkPeer>)>,
+    slot_q: Query<(&InventorySlot, &Children)>,
+    added_slot_q: Query<(), Added<InventorySlot>>,
+    mut text_q: Query<&mut Text>,
+    localization: Res<Localization>,
+) {
+    let changed = changed_ev.iter().filter(|event| inventory_q.contains(event.owner)).count() > 0;
+    if !changed && added_slot_q.is_empty() && !localization.is_changed() { return; }
+    let Ok(inventory) = inventory_q.get_single() else { return; };
+    for (slot, children) in slot_q.iter() {
+        let label = match inventory.slots().get(slot.0) {
+            Some(Some(stack)) if stack.count > 1 => localization.format("inventory.stack", &[
+                ("item", &stack.item.label(&localization)),
+                ("count", &stack.count.to_string()),
+            ]),
+            Some(Some(stack)) => stack.item.label(&localization),
+            _ => String::new(),
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = text_q.get_mut(*child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod inventory_test {
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::text::TextPlugin;
+    use bevy::ui::UiPlugin;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::localization::LocalizationPlugin;
+    use crate::test_utils::{enter_game, LoadTestPlugins};
+
+    #[test]
+    fn it_stacks_items_up_to_their_limit() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(Item::Key("crypt".to_string()), 12).unwrap();
+        assert_eq!(inventory.slots()[0].as_ref().unwrap().count, 9);
+        assert_eq!(inventory.slots()[1].as_ref().unwrap().count, 3);
+        assert_eq!(inventory.count(&Item::Key("crypt".to_string())), 12);
+    }
+
+    #[test]
+    fn it_refuses_items_that_do_not_fit() {
+        let mut inventory = Inventory::new(1);
+        inventory.add(Item::Weapon(WeaponStats::default()), 1).unwrap();
+        assert_eq!(inventory.add(Item::Key("crypt".to_string()), 1), Err(InventoryError::Full));
+        assert_eq!(inventory.count(&Item::Key("crypt".to_string())), 0);
+    }
+
+    #[test]
+    fn it_removes_items_and_frees_empty_slots() {
+        let mut inventory = Inventory::new(2);
+        let key = Item::Key("crypt".to_string());
+        inventory.add(key.clone(), 2).unwrap();
+        assert_eq!(inventory.remove(&key, 3), Err(InventoryError::Missing));
+        inventory.remove(&key, 2).unwrap();
+        assert_eq!(inventory.slots(), [None, None]);
+    }
+
+    #[test]
+    fn it_moves_swaps_and_merges_stacks() {
+        let mut inventory = Inventory::new(3);
+        let key = Item::Key("crypt".to_string());
+        let weapon = Item::Weapon(WeaponStats::default());
+        inventory.add(key.clone(), 9).unwrap();
+        inventory.add(weapon.clone(), 1).unwrap();
+        inventory.add(key.clone(), 2).unwrap();
+        inventory.move_item(0, 1).unwrap();
+        assert_eq!(inventory.slots()[0].as_ref().unwrap().item, weapon);
+        assert_eq!(inventory.slots()[1].as_ref().unwrap().count, 9);
+        inventory.remove(&key, 1).unwrap();
+        inventory.move_item(2, 1).unwrap();
+        assert_eq!(inventory.slots()[1].as_ref().unwrap().count, 9);
+        assert_eq!(inventory.slots()[2].as_ref().unwrap().count, 1);
+        assert_eq!(inventory.move_item(0, 3), Err(InventoryError::InvalidSlot));
+    }
+
+    #[test]
+    fn it_round_trips_through_json() {
+        let mut inventory = Inventory::default();
+        inventory.add(Item::Key("crypt".to_string()), 2).unwrap();
+        inventory.add(Item::Weapon(WeaponStats::default()), 1).unwrap();
+        let json = serde_json::to_string(&inventory).unwrap();
+        assert_eq!(serde_json::from_str::<Inventory>(&json).unwrap(), inventory);
+    }
+
+    #[test]
+    fn it_toggles_the_panel_and_navigates_the_grid() {
+        let mut app = setup();
+        press_key(&mut app, KeyCode::I);
+        let visibility = app.world
+            .query_filtered::<&Visibility, With<InventoryPanel>>()
+            .single(&app.world);
+        assert_eq!(*visibility, Visibility::Inherited);
+        press_key(&mut app, KeyCode::Right);
+        press_key(&mut app, KeyCode::Down);
+        assert_eq!(app.world.resource::<InventoryCursor>().selected, 1 + GRID_COLUMNS);
+    }
+
+    #[test]
+    fn it_moves_items_with_the_panel() {
+        let mut app = setup();
+        let player = app.world.spawn((Player, Inventory::default())).id();
+        app.world.get_mut::<Inventory>(player).unwrap()
+            .add(Item::Key("crypt".to_string()), 1).unwrap();
+        press_key(&mut app, KeyCode::I);
+        press_key(&mut app, KeyCode::Return);
+        press_key(&mut app, KeyCode::Right);
+        press_key(&mut app, KeyCode::Return);
+        let inventory = app.world.get::<Inventory>(player).unwrap();
+        assert!(inventory.slots()[0].is_none());
+        assert_eq!(inventory.slots()[1].as_ref().unwrap().item, Item::Key("crypt".to_string()));
+        let slots: Vec<_> = app.world.query::<(&InventorySlot, &Children)>().iter(&app.world)
+            .filter(|(slot, _)| slot.0 == 1)
+            .map(|(_, children)| children[0])
+            .collect();
+        let text = app.world.get::<Text>(slots[0]).unwrap();
+        assert_eq!(text.sections[0].value, "crypt key");
+    }
+
+    fn press_key(app: &mut App, key_code: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key_code),
+            state: ButtonState::Pressed,
+        });
+        app.update();
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key_code),
+            state: ButtonState::Released,
+        });
+        app.update();
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_plugin(TextPlugin::default())
+            .add_plugin(UiPlugin::default())
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(InventoryPlugin);
+        enter_game(&mut app);
+        app
+    }
+}
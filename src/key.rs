@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory, LogSeverity};
+use crate::game_state::GameState;
+use crate::interaction::{Interaction, InteractionAppExt, InteractionEvent, InteractionSet};
+use crate::inventory::{Inventory, Item, ItemPickedUp};
+use crate::localization::Localization;
+use crate::physics_bundle::ObjectPhysicsBundle;
+
+pub struct KeyPlugin;
+
+impl Plugin for KeyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ItemPickedUp>()
+            .register_ldtk_entity::<KeyBundle>("Key")
+            .register_interaction::<Key>()
+            .add_system(pick_up_keys.after(InteractionSet::React).in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct KeyBundle {
+    #[from_entity_instance]
+    pub key: Key,
+    /// The tileset has no key art yet, so keys borrow the big yellow flask.
+    #[bundle]
+    #[sprite_sheet_bundle("dungeon/0x72_DungeonTilesetII_v1.4.png", 16.0, 16.0, 32, 32, 0.0, 0.0, 469)]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    #[bundle]
+    pub physics: ObjectPhysicsBundle,
+}
+
+#[derive(Component, Default, Debug)]
+pub struct Key {
+    key_id: String,
+    iid: String,
+    taken: bool,
+}
+
+impl Key {
+    pub fn new(key_id: &str, iid: &str) -> Self {
+        Key { key_id: key_id.to_string(), iid: iid.to_string(), taken: false }
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// LDtk entity iid, stable across respawns of the level.
+    pub fn iid(&self) -> &str {
+        &self.iid
+    }
+}
+
+impl Interaction for Key {
+    fn interact(&mut self) {
+        self.taken = true;
+    }
+
+    fn prompt(&self, text: &Localization) -> String {
+        text.get("prompt.take_key").to_string()
+    }
+}
+
+impl From<&EntityInstance> for Key {
+    fn from(value: &EntityInstance) -> Self {
+        let key_id = value.field_instances.iter()
+            .find(|f| f.identifier == "key_id")
+            .and_then(|f| match &f.value {
+                FieldValue::String(key_id) => key_id.clone(),
+                _ => None,
+            })
+            .unwrap_or_default();
+        Key::new(&key_id, &value.iid)
+    }
+}
+
+fn pick_up_keys(
+    mut commands: Commands,
+    mut interaction_ev: EventReader<InteractionEvent>,
+    mut key_q: Query<&mut Key>,
+    mut inventory_q: Query<&mut Inventory>,
+    mut picked_up_ev: EventWriter<ItemPickedUp>,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
+) {
+    for event in interaction_ev.iter() {
+        let Ok(mut key) = key_q.get_mut(event.target) else { continue; };
+        let Ok(mut inventory) = inventory_q.get_mut(event.actor) else { continue; };
+        if !key.taken { continue; }
+        if inventory.add(Item::Key(key.key_id().to_string()), 1).is_err() {
+            key.taken = false;
+            log.send(GameLogEvent::new(LogCategory::Loot, localization.get("log.inventory_full")).with_severity(LogSeverity::Warning));
+            continue;
+        }
+        commands.entity(event.target).despawn_recursive();
+        log.send(GameLogEvent::from_template(LogCategory::Loot, localization.get("log.key_picked_up"), &[("key", key.key_id())]));
+        picked_up_ev.send(ItemPickedUp { iid: key.iid().to_string() });
+    }
+}
+
+#[cfg(test)]
+mod key_test {
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy_rapier2d::prelude::{CollisionEvent, NoUserData, RapierPhysicsPlugin};
+    use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::door::{Door, DoorPlugin};
+    use crate::interaction::{InteractionPlugin, Interactor};
+    use crate::level::LevelPlugin;
+    use crate::localization::LocalizationPlugin;
+    use crate::test_utils::{enter_game, LoadTestPlugins, update};
+
+    #[test]
+    fn it_spawns_keys_and_locked_doors_from_ldtk() {
+        let mut app = setup();
+        app.insert_resource(LevelSelection::Index(1));
+        update(&mut app, 3);
+        let key = app.world.query::<&Key>().single(&app.world);
+        assert_eq!(key.key_id(), "crypt");
+        let locked_doors: Vec<_> = app.world.query::<&Door>().iter(&app.world)
+            .filter(|door| door.is_locked())
+            .map(|door| door.key_id().map(str::to_string))
+            .collect();
+        assert_eq!(locked_doors, [Some("crypt".to_string())]);
+    }
+
+    #[test]
+    fn it_adds_picked_up_keys_to_the_inventory() {
+        let mut app = setup();
+        let player = app.world.spawn((Interactor, Inventory::default(), TransformBundle::default())).id();
+        let key = app.world.spawn((Key::new("crypt", "key-iid"), TransformBundle::default())).id();
+        pick_up(&mut app, player, key);
+        assert_eq!(app.world.get::<Inventory>(player).unwrap().count(&Item::Key("crypt".to_string())), 1);
+        assert!(app.world.get_entity(key).is_none());
+        let picked_up = app.world.resource::<Events<ItemPickedUp>>();
+        assert_eq!(picked_up.iter_current_update_events().next().unwrap().iid, "key-iid");
+    }
+
+    #[test]
+    fn it_leaves_keys_behind_when_the_inventory_is_full() {
+        let mut app = setup();
+        let player = app.world.spawn((Interactor, Inventory::new(0), TransformBundle::default())).id();
+        let key = app.world.spawn((Key::new("crypt", "key-iid"), TransformBundle::default())).id();
+        pick_up(&mut app, player, key);
+        assert!(!app.world.get::<Key>(key).unwrap().taken);
+        let logs = app.world.resource::<Events<GameLogEvent>>();
+        assert_eq!(logs.iter_current_update_events().next().unwrap().text(), "Your inventory is full.");
+    }
+
+    fn pick_up(app: &mut App, player: Entity, key: Entity) {
+        app.world.send_event(CollisionEvent::Started(player, key, CollisionEventFlags::SENSOR));
+        app.update();
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(KeyCode::E),
+            state: ButtonState::Pressed,
+        });
+        app.update();
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+            .add_plugin(InteractionPlugin)
+            .add_plugin(LevelPlugin)
+            .add_plugin(DoorPlugin)
+            .add_plugin(KeyPlugin);
+        enter_game(&mut app);
+        app
+    }
+}
@@ -2,6 +2,13 @@ use std::collections::*;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
+use crate::game_state::GameState;
+use crate::level_exit::LevelWarp;
+use crate::network::NetworkPeer;
+use crate::player::Player;
+
+/// The level new games start in. Level exits and walking into neighbours select others.
+pub const START_LEVEL: LevelSelection = LevelSelection::Index(0);
 
 #[derive(Component, Default)]
 pub struct Ground;
@@ -26,15 +33,34 @@ pub fn load_level(mut commands: Commands, asset_server: Res<AssetServer>) {
     });
 }
 
+pub fn despawn_level(mut commands: Commands, world_q: Query<Entity, With<Handle<LdtkAsset>>>) {
+    for world in world_q.iter() {
+        commands.entity(world).despawn_recursive();
+    }
+}
+
+/// Sent when the local player walks into another level and it becomes the selected one.
+pub struct LevelEntered {
+    pub iid: String,
+    pub identifier: String,
+}
+
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugin(LdtkPlugin)
-            .add_startup_system(load_level)
-            .add_system(spawn_wall_collision)
-            .insert_resource(LevelSelection::Index(0))
+            .add_system(load_level.in_schedule(OnEnter(GameState::InGame)))
+            .add_system(despawn_level.in_schedule(OnExit(GameState::InGame)))
+            .add_event::<LevelEntered>()
+            .add_system(spawn_wall_collision.in_set(OnUpdate(GameState::InGame)))
+            .add_system(follow_player_across_levels
+                .run_if(not(resource_exists::<LevelWarp>()))
+                .before(log_entered_levels)
+                .in_set(OnUpdate(GameState::InGame)))
+            .add_system(log_entered_levels.in_set(OnUpdate(GameState::InGame)))
+            .insert_resource(START_LEVEL)
             .insert_resource(LdtkSettings {
                 level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
                     load_level_neighbors: true,
@@ -47,6 +73,50 @@ impl Plugin for LevelPlugin {
     }
 }
 
+/// Iid of the level `selection` points at, resolving indices and identifiers through the loaded project.
+pub fn selected_level_iid(
+    selection: &LevelSelection,
+    world_q: &Query<&Handle<LdtkAsset>>,
+    ldtk_assets: &Assets<LdtkAsset>,
+) -> Option<String> {
+    world_q.iter()
+        .filter_map(|handle| ldtk_assets.get(handle))
+        .find_map(|asset| asset.get_level(selection))
+        .map(|level| level.iid.clone())
+}
+
+/// Selects whichever loaded level the local player stands in, so its neighbours load in turn.
+pub fn follow_player_across_levels(
+    mut level_selection: ResMut<LevelSelection>,
+    world_q: Query<&Handle<LdtkAsset>>,
+    level_q: Query<(&Handle<LdtkLevel>, &GlobalTransform)>,
+    player_q: Query<&GlobalTransform, (With<Player>, Without<NetworkPeer>)>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    levels: Res<Assets<LdtkLevel>>,
+    mut entered_ev: EventWriter<LevelEntered>,
+) {
+    let Ok(player) = player_q.get_single() else { return; };
+    let position = player.translation().truncate();
+    let selected = selected_level_iid(&level_selection, &world_q, &ldtk_assets);
+    for (handle, transform) in level_q.iter() {
+        let Some(ldtk_level) = levels.get(handle) else { continue; };
+        let level = &ldtk_level.level;
+        let min = transform.translation().truncate();
+        let max = min + Vec2::new(level.px_wid as f32, level.px_hei as f32);
+        let inside = position.x >= min.x && position.x < max.x && position.y >= min.y && position.y < max.y;
+        if !inside || selected.as_ref() == Some(&level.iid) { continue; }
+        *level_selection = LevelSelection::Iid(level.iid.clone());
+        entered_ev.send(LevelEntered { iid: level.iid.clone(), identifier: level.identifier.clone() });
+        return;
+    }
+}
+
+fn log_entered_levels(mut entered_ev: EventReader<LevelEntered>) {
+    for LevelEntered { iid, identifier } in entered_ev.iter() {
+        info!("Entered level {identifier} ({iid})");
+    }
+}
+
 /// Spawns heron collisions for the walls of a level
 ///
 /// You could just insert a ColliderBundle in to the WallBundle,
@@ -219,8 +289,11 @@ mod level_tests {
     use bevy::prelude::*;
     use bevy_ecs_ldtk::LevelSelection;
     use bevy_rapier2d::prelude::Collider;
-    use crate::level::{Ground, LevelPlugin};
-    use crate::test_utils::{update, LoadTestPlugins};
+    use bevy_ecs_ldtk::prelude::LdtkAsset;
+    use crate::game_state::GameState;
+    use crate::level::{Ground, LevelEntered, LevelPlugin};
+    use crate::player::Player;
+    use crate::test_utils::{enter_game, update, LoadTestPlugins};
 
     #[test]
     fn did_spawn_test_level() {
@@ -234,12 +307,46 @@ mod level_tests {
         assert_eq!(app.world.query::<&Collider>().iter(&app.world).len(), 4);
     }
 
+    #[test]
+    fn it_despawns_level_when_leaving_game() {
+        let mut app = setup();
+        app.world.insert_resource(NextState(Some(GameState::MainMenu)));
+        update(&mut app, 2);
+        assert_eq!(app.world.query::<&Handle<LdtkAsset>>().iter(&app.world).len(), 0);
+        assert_eq!(app.world.query::<&Ground>().iter(&app.world).len(), 0);
+    }
+
+    #[test]
+    fn it_selects_the_level_the_player_walks_into() {
+        let mut app = setup();
+        app.insert_resource(LevelSelection::Index(1));
+        update(&mut app, 3);
+        app.world.spawn((Player, TransformBundle::from_transform(Transform::from_xyz(1500., -1700., 0.))));
+        update(&mut app, 2);
+        let level_2 = "a3cc0930-c640-11ed-97c4-a9c8b945c405";
+        assert_eq!(*app.world.resource::<LevelSelection>(), LevelSelection::Iid(level_2.to_string()));
+        let entered = app.world.resource::<Events<LevelEntered>>();
+        let event = entered.iter_current_update_events().next().unwrap();
+        assert_eq!((event.iid.as_str(), event.identifier.as_str()), (level_2, "Level_2"));
+    }
+
+    #[test]
+    fn it_keeps_the_selection_while_the_player_stays_in_its_level() {
+        let mut app = setup();
+        app.world.spawn((Player, TransformBundle::from_transform(Transform::from_xyz(100., -600., 0.))));
+        update(&mut app, 2);
+        assert_eq!(*app.world.resource::<LevelSelection>(), LevelSelection::Index(0));
+        assert_eq!(app.world.resource::<Events<LevelEntered>>().len(), 0);
+    }
+
     fn setup() -> App {
         let mut app = App::new();
         app
             .add_plugins(LoadTestPlugins)
+            .add_state::<GameState>()
             .add_plugin(LevelPlugin)
             .insert_resource(LevelSelection::Index(0));
+        enter_game(&mut app);
         update(&mut app, 3);
         app
     }
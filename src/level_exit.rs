@@ -0,0 +1,430 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::{CollisionEvent, Velocity};
+use crate::game_state::GameState;
+use crate::health::RespawnPoint;
+use crate::interaction::{Interaction, InteractionAppExt, InteractionEvent, InteractionSet};
+use crate::level::{LevelEntered, selected_level_iid};
+use crate::localization::Localization;
+use crate::network::NetworkPeer;
+use crate::physics_bundle::ObjectPhysicsBundle;
+use crate::player::Player;
+
+pub struct LevelExitPlugin;
+
+impl Plugin for LevelExitPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<LevelEntered>()
+            .register_ldtk_entity::<LevelExitBundle>("LevelExit")
+            .register_ldtk_entity::<LevelEntranceBundle>("LevelEntrance")
+            .register_interaction::<LevelExit>()
+            .add_systems((start_warps, fade_screen, place_arriving_player)
+                .chain()
+                .after(InteractionSet::React)
+                .in_set(OnUpdate(GameState::InGame)))
+            .add_system(cancel_warp.in_schedule(OnExit(GameState::InGame)));
+    }
+}
+
+/// Seconds the screen takes to fade to black, and back again once the player has arrived.
+const FADE_TIME: f32 = 0.4;
+/// Seconds to wait for the destination before taking the player back to where it left from.
+const LOAD_TIMEOUT: f32 = 5.;
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct LevelExitBundle {
+    #[from_entity_instance]
+    pub exit: LevelExit,
+    #[bundle]
+    #[sprite_sheet_bundle("dungeon/0x72_DungeonTilesetII_v1.4.png", 16.0, 16.0, 32, 32, 0.0, 0.0, 195)]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    #[bundle]
+    pub physics: ObjectPhysicsBundle,
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct LevelEntranceBundle {
+    #[from_entity_instance]
+    pub entrance: LevelEntrance,
+}
+
+/// The LDtk entity a [LevelExit] leads to, usually a [LevelEntrance] in another level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Destination {
+    pub level_iid: String,
+    pub entity_iid: String,
+}
+
+/// Takes the player to its `destination` when interacted with, or as soon as it is touched if `on_touch` is set.
+#[derive(Component, Default, Debug)]
+pub struct LevelExit {
+    destination: Option<Destination>,
+    on_touch: bool,
+    used: bool,
+}
+
+impl LevelExit {
+    pub fn destination(&self) -> Option<&Destination> {
+        self.destination.as_ref()
+    }
+}
+
+impl Interaction for LevelExit {
+    fn interact(&mut self) {
+        self.used = true;
+    }
+
+    fn prompt(&self, text: &Localization) -> String {
+        text.get("prompt.enter").to_string()
+    }
+}
+
+impl From<&EntityInstance> for LevelExit {
+    fn from(value: &EntityInstance) -> Self {
+        let field = |name: &str| value.field_instances.iter()
+            .find(|f| f.identifier == name)
+            .map(|f| &f.value);
+        let destination = match field("destination") {
+            Some(FieldValue::EntityRef(Some(reference))) => Some(Destination {
+                level_iid: reference.level_iid.clone(),
+                entity_iid: reference.entity_iid.clone(),
+            }),
+            _ => None,
+        };
+        let on_touch = matches!(field("on_touch"), Some(FieldValue::Bool(true)));
+        LevelExit { destination, on_touch, used: false }
+    }
+}
+
+/// Where players arrive through a [LevelExit].
+#[derive(Component, Default, Debug)]
+pub struct LevelEntrance {
+    iid: String,
+}
+
+impl From<&EntityInstance> for LevelEntrance {
+    fn from(value: &EntityInstance) -> Self {
+        LevelEntrance { iid: value.iid.clone() }
+    }
+}
+
+/// A warp in progress. Neighbour level switching is paused while it exists.
+#[derive(Resource)]
+pub struct LevelWarp {
+    destination: Destination,
+    player: Entity,
+    origin: Origin,
+    phase: WarpPhase,
+}
+
+/// Where the player stood when the warp started, to go back to if the destination never loads.
+struct Origin {
+    selection: LevelSelection,
+    level_iid: Option<String>,
+    position: Vec3,
+}
+
+enum WarpPhase {
+    FadeOut(Timer),
+    /// Waiting for the destination level to spawn, up to [LOAD_TIMEOUT].
+    Loading(Timer),
+    /// Waiting for the origin level to spawn again after the destination never did.
+    Returning,
+    FadeIn(Timer),
+}
+
+#[derive(Component)]
+struct ScreenFade;
+
+fn start_warps(
+    mut commands: Commands,
+    warp: Option<Res<LevelWarp>>,
+    mut interaction_ev: EventReader<InteractionEvent>,
+    mut collision_ev: EventReader<CollisionEvent>,
+    mut exit_q: Query<&mut LevelExit>,
+    mut player_q: Query<(&GlobalTransform, Option<&mut Velocity>), (With<Player>, Without<NetworkPeer>)>,
+    level_selection: Res<LevelSelection>,
+    world_q: Query<&Handle<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+) {
+    let interacted = interaction_ev.iter().map(|event| (event.actor, event.target, false));
+    let touched = collision_ev.iter().filter_map(|event| match *event {
+        CollisionEvent::Started(a, b, _) => Some([(a, b, true), (b, a, true)]),
+        CollisionEvent::Stopped(..) => None,
+    }).flatten();
+    let triggers: Vec<_> = interacted.chain(touched).collect();
+    if warp.is_some() { return; }
+    for (actor, target, touch) in triggers {
+        let Ok(mut exit) = exit_q.get_mut(target) else { continue; };
+        let used = if touch { exit.on_touch } else { std::mem::take(&mut exit.used) };
+        let Some(destination) = exit.destination().cloned() else { continue; };
+        let Ok((global, velocity)) = player_q.get_mut(actor) else { continue; };
+        if !used { continue; }
+        if let Some(mut velocity) = velocity {
+            velocity.linvel = Vec2::ZERO;
+        }
+        commands.insert_resource(LevelWarp {
+            destination,
+            player: actor,
+            origin: Origin {
+                selection: level_selection.clone(),
+                level_iid: selected_level_iid(&level_selection, &world_q, &ldtk_assets),
+                position: global.translation(),
+            },
+            phase: WarpPhase::FadeOut(Timer::from_seconds(FADE_TIME, TimerMode::Once)),
+        });
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.).into(),
+                z_index: ZIndex::Global(i32::MAX),
+                ..default()
+            },
+            ScreenFade,
+        ));
+        return;
+    }
+}
+
+/// Darkens the screen before switching [LevelSelection], and clears it once the player has arrived.
+/// Meanwhile the player is a child of the LDtk world, so it outlives the level it leaves.
+fn fade_screen(
+    mut commands: Commands,
+    time: Res<Time>,
+    warp: Option<ResMut<LevelWarp>>,
+    mut level_selection: ResMut<LevelSelection>,
+    world_q: Query<(Entity, &GlobalTransform), With<Handle<LdtkAsset>>>,
+    mut player_q: Query<(&mut Transform, &GlobalTransform)>,
+    mut fade_q: Query<(Entity, &mut BackgroundColor), With<ScreenFade>>,
+) {
+    let Some(mut warp) = warp else { return; };
+    let warp = &mut *warp;
+    let alpha = match &mut warp.phase {
+        WarpPhase::FadeOut(timer) => {
+            let alpha = timer.tick(time.delta()).percent();
+            if timer.finished() {
+                if let (Ok((world, world_global)), Ok((mut transform, global))) = (world_q.get_single(), player_q.get_mut(warp.player)) {
+                    transform.translation = world_global.affine().inverse().transform_point3(global.translation());
+                    commands.entity(world).add_child(warp.player);
+                }
+                *level_selection = LevelSelection::Iid(warp.destination.level_iid.clone());
+                warp.phase = WarpPhase::Loading(Timer::from_seconds(LOAD_TIMEOUT, TimerMode::Once));
+            }
+            alpha
+        }
+        WarpPhase::Loading(timer) => {
+            if timer.tick(time.delta()).finished() {
+                warn!("Level {} did not load, going back", warp.destination.level_iid);
+                *level_selection = warp.origin.selection.clone();
+                warp.phase = match warp.origin.level_iid {
+                    Some(_) => WarpPhase::Returning,
+                    None => WarpPhase::FadeIn(Timer::from_seconds(FADE_TIME, TimerMode::Once)),
+                };
+            }
+            1.
+        }
+        WarpPhase::Returning => 1.,
+        WarpPhase::FadeIn(timer) => {
+            if timer.tick(time.delta()).finished() {
+                commands.remove_resource::<LevelWarp>();
+                for (fade, _) in fade_q.iter() {
+                    commands.entity(fade).despawn_recursive();
+                }
+                return;
+            }
+            timer.percent_left()
+        }
+    };
+    for (_, mut background) in fade_q.iter_mut() {
+        background.0.set_a(alpha);
+    }
+}
+
+/// Once the level it goes to has spawned, moves the player into it, onto the destination entrance or back
+/// where it stood when returning, and despawns the `Player` that level brought along.
+fn place_arriving_player(
+    mut commands: Commands,
+    warp: Option<ResMut<LevelWarp>>,
+    entrance_q: Query<(&LevelEntrance, Ref<GlobalTransform>)>,
+    level_q: Query<(Entity, &Handle<LdtkLevel>, Ref<GlobalTransform>, Option<&Children>)>,
+    mut player_q: Query<(Entity, &mut Transform, Option<&mut Velocity>), (With<Player>, Without<NetworkPeer>)>,
+    levels: Res<Assets<LdtkLevel>>,
+    mut entered_ev: EventWriter<LevelEntered>,
+) {
+    let Some(mut warp) = warp else { return; };
+    let returning = match warp.phase {
+        WarpPhase::Loading(_) => false,
+        WarpPhase::Returning => true,
+        _ => return,
+    };
+    if player_q.get(warp.player).is_err() {
+        // Loading a save respawned the world, player included, during the warp.
+        warp.phase = WarpPhase::FadeIn(Timer::from_seconds(FADE_TIME, TimerMode::Once));
+        return;
+    }
+    let (level_iid, position) = if returning {
+        let Some(level_iid) = warp.origin.level_iid.clone() else { return; };
+        (level_iid, warp.origin.position)
+    } else {
+        let Some((_, target)) = entrance_q.iter().find(|(entrance, _)| entrance.iid == warp.destination.entity_iid) else { return; };
+        // Transforms of freshly spawned entities are only propagated at the end of the frame.
+        if target.is_added() { return; }
+        (warp.destination.level_iid.clone(), target.translation())
+    };
+    let level = level_q.iter()
+        .filter(|(_, _, global, children)| !global.is_added() && children.is_some())
+        .find(|(_, handle, ..)| matches!(levels.get(handle), Some(level) if level.level.iid == level_iid));
+    let Some((level, handle, level_global, _)) = level else { return; };
+    for (other, ..) in player_q.iter().filter(|(player, ..)| *player != warp.player) {
+        commands.entity(other).despawn_recursive();
+    }
+    let Ok((player, mut transform, velocity)) = player_q.get_mut(warp.player) else { return; };
+    let local = level_global.affine().inverse().transform_point3(position);
+    transform.translation = local.truncate().extend(transform.translation.z);
+    if let Some(mut velocity) = velocity {
+        velocity.linvel = Vec2::ZERO;
+    }
+    commands.entity(level).add_child(player);
+    if !returning {
+        commands.entity(player).insert(RespawnPoint(transform.translation));
+        let identifier = levels.get(handle).map(|level| level.level.identifier.clone()).unwrap_or_default();
+        entered_ev.send(LevelEntered { iid: level_iid, identifier });
+    }
+    warp.phase = WarpPhase::FadeIn(Timer::from_seconds(FADE_TIME, TimerMode::Once));
+}
+
+fn cancel_warp(mut commands: Commands, fade_q: Query<Entity, With<ScreenFade>>) {
+    commands.remove_resource::<LevelWarp>();
+    for fade in fade_q.iter() {
+        commands.entity(fade).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod level_exit_test {
+    use bevy::input::InputPlugin;
+    use bevy_rapier2d::prelude::{NoUserData, RapierPhysicsPlugin};
+    use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::health::Health;
+    use crate::interaction::InteractionPlugin;
+    use crate::inventory::{Inventory, Item};
+    use crate::level::LevelPlugin;
+    use crate::localization::LocalizationPlugin;
+    use crate::player::PlayerPlugin;
+    use crate::test_utils::{enter_game, LoadTestPlugins, update};
+
+    const LEVEL_0: &str = "e81a7aa0-7820-11ed-a36b-f35dff77576c";
+    const LEVEL_0_ENTRANCE: &str = "e5a1c3d1-d9f2-11ed-8b41-5f0c7d2a9e11";
+
+    #[test]
+    fn it_reads_exit_destinations_from_ldtk() {
+        let mut app = setup();
+        let exit = app.world.query::<&LevelExit>().single(&app.world);
+        assert_eq!(exit.destination(), Some(&Destination {
+            level_iid: LEVEL_0.to_string(),
+            entity_iid: LEVEL_0_ENTRANCE.to_string(),
+        }));
+        assert!(exit.on_touch);
+    }
+
+    #[test]
+    fn it_warps_the_player_to_the_destination_keeping_its_state() {
+        let mut app = setup();
+        let player = app.world.query_filtered::<Entity, With<Player>>().single(&app.world);
+        app.world.get_mut::<Inventory>(player).unwrap()
+            .add(Item::Key("crypt".to_string()), 1).unwrap();
+        app.world.get_mut::<Health>(player).unwrap().current = 2;
+        app.world.entity_mut(player).insert(Carried);
+        start_warp(&mut app, player);
+        wait_for_fade_in(&mut app);
+        assert_eq!(*app.world.resource::<LevelSelection>(), LevelSelection::Iid(LEVEL_0.to_string()));
+        app.update();
+        let (arrived, inventory, health, transform) = app.world
+            .query_filtered::<(Entity, &Inventory, &Health, &GlobalTransform), With<Player>>()
+            .single(&app.world);
+        assert_eq!(arrived, player);
+        assert!(app.world.get::<Carried>(player).is_some());
+        assert_eq!(inventory.count(&Item::Key("crypt".to_string())), 1);
+        assert_eq!(health.current, 2);
+        let entrance = app.world
+            .query::<(&LevelEntrance, &GlobalTransform)>()
+            .iter(&app.world)
+            .find(|(entrance, _)| entrance.iid == LEVEL_0_ENTRANCE)
+            .map(|(_, global)| global.translation())
+            .unwrap();
+        assert_eq!(transform.translation().truncate(), entrance.truncate());
+    }
+
+    #[test]
+    fn it_takes_the_player_back_when_the_destination_never_loads() {
+        let mut app = setup();
+        let player = app.world.query_filtered::<Entity, With<Player>>().single(&app.world);
+        let origin = app.world.get::<GlobalTransform>(player).unwrap().translation();
+        start_warp(&mut app, player);
+        app.world.resource_mut::<LevelWarp>().destination.entity_iid = "nowhere".to_string();
+        for _ in 0..10 {
+            app.update();
+            let mut warp = app.world.resource_mut::<LevelWarp>();
+            if let WarpPhase::Loading(_) = warp.phase {
+                warp.phase = WarpPhase::Loading(Timer::from_seconds(0., TimerMode::Once));
+                break;
+            }
+        }
+        wait_for_fade_in(&mut app);
+        assert_eq!(*app.world.resource::<LevelSelection>(), LevelSelection::Index(1));
+        app.update();
+        let (arrived, transform) = app.world
+            .query_filtered::<(Entity, &GlobalTransform), With<Player>>()
+            .single(&app.world);
+        assert_eq!(arrived, player);
+        assert_eq!(transform.translation().truncate(), origin.truncate());
+    }
+
+    /// Stands in for any component the player has besides the ones it spawns with.
+    #[derive(Component)]
+    struct Carried;
+
+    /// Walks `player` into the level exit and skips the fade out.
+    fn start_warp(app: &mut App, player: Entity) {
+        let exit = app.world.query_filtered::<Entity, With<LevelExit>>().single(&app.world);
+        app.world.send_event(CollisionEvent::Started(player, exit, CollisionEventFlags::SENSOR));
+        app.update();
+        app.world.resource_mut::<LevelWarp>().phase = WarpPhase::FadeOut(Timer::from_seconds(0., TimerMode::Once));
+    }
+
+    fn wait_for_fade_in(app: &mut App) {
+        for _ in 0..20 {
+            app.update();
+            if let WarpPhase::FadeIn(_) = app.world.resource::<LevelWarp>().phase { return; }
+        }
+        panic!("the player never arrived");
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+            .add_plugin(InteractionPlugin)
+            .add_plugin(LevelPlugin)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(LevelExitPlugin)
+            .insert_resource(LevelSelection::Index(1));
+        enter_game(&mut app);
+        update(&mut app, 3);
+        app
+    }
+}
@@ -0,0 +1,464 @@
+use bevy::prelude::*;
+use crate::actions::{Action, ActionInput};
+use crate::game_state::GameState;
+use crate::localization::Localization;
+use crate::save_game::{delete_save, LastSession, list_slots, LoadGame, SaveDirectory, SaveSlot, write_save};
+
+pub struct LoadMenuPlugin;
+
+impl Plugin for LoadMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SlotAction>()
+            .init_resource::<SlotSelection>()
+            .init_resource::<SlotNotice>()
+            .add_system(refresh_slots.in_schedule(OnEnter(GameState::LoadMenu)))
+            .add_system(despawn_load_menu.in_schedule(OnExit(GameState::LoadMenu)))
+            .add_systems((
+                load_menu_input,
+                handle_slot_actions,
+                refresh_slots_on_change,
+                rebuild_load_menu,
+                update_slot_notice,
+                highlight_selected_slot,
+            ).chain().in_set(OnUpdate(GameState::LoadMenu)));
+    }
+}
+
+const NORMAL_ROW: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_ROW: Color = Color::rgb(0.35, 0.35, 0.35);
+const UNUSABLE_TEXT: Color = Color::rgb(0.8, 0.3, 0.3);
+
+#[derive(Resource, Default)]
+struct SaveSlots {
+    slots: Vec<SaveSlot>,
+    /// Set when a slot was written or removed and the list needs reading again.
+    stale: bool,
+}
+
+#[derive(Resource, Default)]
+struct SlotSelection(usize);
+
+/// A line under the slots about the last action.
+#[derive(Resource, Default, Clone, PartialEq, Eq, Debug)]
+enum SlotNotice {
+    #[default]
+    None,
+    NothingToOverwrite,
+    /// Deleting the named slot waits for a second delete.
+    ConfirmDelete(String),
+}
+
+impl SlotNotice {
+    fn text(&self, localization: &Localization) -> String {
+        match self {
+            SlotNotice::None => String::new(),
+            SlotNotice::NothingToOverwrite => localization.get("load.nothing_to_overwrite").to_string(),
+            SlotNotice::ConfirmDelete(slot) => localization.format("load.confirm_delete", &[("slot", slot)]),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SlotAction {
+    Load,
+    Overwrite,
+    Delete,
+    Back,
+    Step(isize),
+}
+
+#[derive(Component)]
+struct LoadMenu;
+
+#[derive(Component)]
+struct SlotRow(usize);
+
+#[derive(Component)]
+struct SlotNoticeText;
+
+fn refresh_slots(
+    mut commands: Commands,
+    save_directory: Res<SaveDirectory>,
+    mut selection: ResMut<SlotSelection>,
+    mut notice: ResMut<SlotNotice>,
+) {
+    selection.0 = 0;
+    *notice = SlotNotice::None;
+    commands.insert_resource(SaveSlots {
+        slots: list_slots(&save_directory),
+        stale: false,
+    });
+}
+
+fn refresh_slots_on_change(
+    save_directory: Res<SaveDirectory>,
+    mut save_slots: ResMut<SaveSlots>,
+    mut selection: ResMut<SlotSelection>,
+) {
+    if !save_slots.stale { return; }
+    save_slots.slots = list_slots(&save_directory);
+    save_slots.stale = false;
+    selection.0 = selection.0.min(save_slots.slots.len().saturating_sub(1));
+}
+
+fn rebuild_load_menu(
+    mut commands: Commands,
+    save_slots: Res<SaveSlots>,
+    menu_q: Query<Entity, With<LoadMenu>>,
+    asset_server: Res<AssetServer>,
+    localization: Res<Localization>,
+    notice: Res<SlotNotice>,
+) {
+    if !save_slots.is_changed() && !localization.is_changed() { return; }
+    for menu in menu_q.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+    let font = asset_server.load("fonts/kongtext/kongtext.ttf");
+    let text_style = |color| TextStyle { font: font.clone(), font_size: 14.0, color };
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        },
+        LoadMenu,
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(localization.get("load.title"), TextStyle {
+            font_size: 24.0,
+            ..text_style(Color::WHITE)
+        }));
+        let slot_count = localization.plural("load.slot_count", save_slots.slots.len() as u64, &[]);
+        parent.spawn(TextBundle::from_section(slot_count, text_style(Color::GRAY)));
+        for (index, slot) in save_slots.slots.iter().enumerate() {
+            let (label, color) = match &slot.save {
+                Ok(save) => (localization.format("load.slot", &[
+                    ("slot", &slot.name),
+                    ("level", save.level_name.as_deref().unwrap_or(localization.get("load.unknown_level"))),
+                    ("time", &format_play_time(save.play_time)),
+                    ("date", &format_timestamp(save.saved_at)),
+                ]), Color::WHITE),
+                Err(error) => (localization.format("load.unusable", &[
+                    ("slot", &slot.name),
+                    ("error", &error.message(&localization)),
+                ]), UNUSABLE_TEXT),
+            };
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(80.), Val::Px(32.)),
+                        margin: UiRect::all(Val::Px(4.)),
+                        align_items: AlignItems::Center,
+                        padding: UiRect::horizontal(Val::Px(8.)),
+                        ..default()
+                    },
+                    background_color: NORMAL_ROW.into(),
+                    ..default()
+                },
+                SlotRow(index),
+            )).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(label, text_style(color)));
+            });
+        }
+        parent.spawn((
+            TextBundle::from_section(notice.text(&localization), text_style(UNUSABLE_TEXT)),
+            SlotNoticeText,
+        ));
+        parent.spawn(TextBundle::from_section(localization.get("load.help"), text_style(Color::GRAY)));
+    });
+}
+
+fn update_slot_notice(
+    notice: Res<SlotNotice>,
+    localization: Res<Localization>,
+    mut text_q: Query<&mut Text, With<SlotNoticeText>>,
+) {
+    if !notice.is_changed() { return; }
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = notice.text(&localization);
+    }
+}
+
+fn despawn_load_menu(mut commands: Commands, menu_q: Query<Entity, With<LoadMenu>>) {
+    for menu in menu_q.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+    commands.remove_resource::<SaveSlots>();
+}
+
+fn load_menu_input(
+    actions: ActionInput,
+    mut slot_actions: EventWriter<SlotAction>,
+) {
+    let bindings = [
+        (Action::MenuUp, SlotAction::Step(-1)),
+        (Action::MenuDown, SlotAction::Step(1)),
+        (Action::MenuConfirm, SlotAction::Load),
+        (Action::MenuOverwrite, SlotAction::Overwrite),
+        (Action::MenuDelete, SlotAction::Delete),
+        (Action::MenuBack, SlotAction::Back),
+    ];
+    for (action, slot_action) in bindings {
+        if actions.just_pressed(action) {
+            slot_actions.send(slot_action);
+        }
+    }
+}
+
+fn handle_slot_actions(
+    mut actions: EventReader<SlotAction>,
+    mut selection: ResMut<SlotSelection>,
+    mut save_slots: ResMut<SaveSlots>,
+    save_directory: Res<SaveDirectory>,
+    last_session: Option<Res<LastSession>>,
+    mut load_ev: EventWriter<LoadGame>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut notice: ResMut<SlotNotice>,
+) {
+    for action in actions.iter() {
+        let slot = save_slots.slots.get(selection.0);
+        let confirming = std::mem::take(&mut *notice);
+        match action {
+            SlotAction::Step(offset) => {
+                let len = save_slots.slots.len() as isize;
+                if len > 0 {
+                    selection.0 = (selection.0 as isize + offset).rem_euclid(len) as usize;
+                }
+            }
+            SlotAction::Load => {
+                if let Some(SaveSlot { name, save: Ok(_) }) = slot {
+                    load_ev.send(LoadGame { slot: name.clone() });
+                }
+            }
+            SlotAction::Overwrite => {
+                let Some(slot) = slot else { continue; };
+                let Some(last_session) = &last_session else {
+                    *notice = SlotNotice::NothingToOverwrite;
+                    continue;
+                };
+                if let Err(error) = write_save(&save_directory.slot_path(&slot.name), &last_session.0) {
+                    error!("Failed to overwrite slot {}: {error}", slot.name);
+                }
+                save_slots.stale = true;
+            }
+            SlotAction::Delete => {
+                let Some(slot) = slot else { continue; };
+                if confirming != SlotNotice::ConfirmDelete(slot.name.clone()) {
+                    *notice = SlotNotice::ConfirmDelete(slot.name.clone());
+                    continue;
+                }
+                if let Err(error) = delete_save(&save_directory.slot_path(&slot.name)) {
+                    error!("Failed to delete slot {}: {error}", slot.name);
+                }
+                save_slots.stale = true;
+            }
+            // Backing out of a delete only cancels it.
+            SlotAction::Back if matches!(confirming, SlotNotice::ConfirmDelete(_)) => {}
+            SlotAction::Back => next_state.set(GameState::MainMenu),
+        }
+    }
+}
+
+fn highlight_selected_slot(
+    selection: Res<SlotSelection>,
+    save_slots: Res<SaveSlots>,
+    mut row_q: Query<(&SlotRow, &mut BackgroundColor)>,
+) {
+    if !selection.is_changed() && !save_slots.is_changed() { return; }
+    for (row, mut background) in row_q.iter_mut() {
+        *background = if row.0 == selection.0 { SELECTED_ROW.into() } else { NORMAL_ROW.into() };
+    }
+}
+
+fn format_play_time(seconds: f32) -> String {
+    let seconds = seconds as u64;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Formats unix seconds as a UTC `YYYY-MM-DD HH:MM` date.
+fn format_timestamp(unix_seconds: u64) -> String {
+    let days = (unix_seconds / 86_400) as i64;
+    let time_of_day = unix_seconds % 86_400;
+    // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+    )
+}
+
+#[cfg(test)]
+mod load_menu_test {
+    use std::fs;
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::text::TextPlugin;
+    use bevy::ui::UiPlugin;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::gamepad::GamepadPlugin;
+    use crate::level::LevelPlugin;
+    use crate::localization::LocalizationPlugin;
+    use crate::save_game::{LevelSave, SAVE_VERSION, SaveFile, SaveGamePlugin};
+    use crate::test_utils::{LoadTestPlugins, update};
+
+    #[test]
+    fn it_formats_timestamps_as_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13");
+    }
+
+    #[test]
+    fn it_formats_play_time() {
+        assert_eq!(format_play_time(3723.4), "01:02:03");
+    }
+
+    #[test]
+    fn it_lists_a_row_per_slot() {
+        let mut app = setup("rows");
+        assert_eq!(app.world.query::<&SlotRow>().iter(&app.world).len(), 3);
+    }
+
+    #[test]
+    fn it_marks_corrupt_and_newer_saves_as_unusable() {
+        let mut app = setup("unusable");
+        let texts: Vec<String> = app.world.query::<&Text>()
+            .iter(&app.world)
+            .map(|text| text.sections[0].value.clone())
+            .collect();
+        assert!(texts.iter().any(|t| t.starts_with("b_corrupt  Unusable: save is corrupt")));
+        assert!(texts.iter().any(|t| t.starts_with("c_newer  Unusable: save version")));
+    }
+
+    #[test]
+    fn it_deletes_the_selected_slot() {
+        let mut app = setup("delete");
+        press_key(&mut app, KeyCode::Delete);
+        update(&mut app, 2);
+        assert!(app.world.resource::<SaveDirectory>().slot_path("a_valid").exists());
+        assert_eq!(notice_text(&mut app), "Delete a_valid? Press [Del] again to confirm");
+        press_key(&mut app, KeyCode::Delete);
+        update(&mut app, 2);
+        assert!(!app.world.resource::<SaveDirectory>().slot_path("a_valid").exists());
+        assert_eq!(app.world.query::<&SlotRow>().iter(&app.world).len(), 2);
+    }
+
+    #[test]
+    fn it_cancels_a_delete_on_back() {
+        let mut app = setup("delete_cancel");
+        press_key(&mut app, KeyCode::Delete);
+        update(&mut app, 2);
+        press_key(&mut app, KeyCode::Escape);
+        update(&mut app, 2);
+        press_key(&mut app, KeyCode::Delete);
+        update(&mut app, 2);
+        assert!(app.world.resource::<SaveDirectory>().slot_path("a_valid").exists());
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::LoadMenu);
+    }
+
+    #[test]
+    fn it_says_when_there_is_no_session_to_overwrite_with() {
+        let mut app = setup("overwrite_nothing");
+        press_key(&mut app, KeyCode::O);
+        update(&mut app, 2);
+        assert_eq!(notice_text(&mut app), "No game played yet to save over this slot");
+    }
+
+    #[test]
+    fn it_does_not_load_unusable_slots() {
+        let mut app = setup("unusable_load");
+        press_key(&mut app, KeyCode::Down);
+        app.update();
+        press_key(&mut app, KeyCode::Return);
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::LoadMenu);
+    }
+
+    #[test]
+    fn it_loads_the_selected_slot() {
+        let mut app = setup("load");
+        press_key(&mut app, KeyCode::Return);
+        update(&mut app, 3);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::InGame);
+    }
+
+    #[test]
+    fn it_overwrites_the_selected_slot_with_the_last_session() {
+        let mut app = setup("overwrite");
+        let mut last_session = valid_save();
+        last_session.play_time = 99.;
+        app.world.insert_resource(LastSession(last_session));
+        press_key(&mut app, KeyCode::O);
+        update(&mut app, 2);
+        let slots = app.world.resource::<SaveSlots>();
+        assert_eq!(slots.slots[0].save.as_ref().unwrap().play_time, 99.);
+    }
+
+    fn notice_text(app: &mut App) -> String {
+        app.world.query_filtered::<&Text, With<SlotNoticeText>>().single(&app.world).sections[0].value.clone()
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Pressed,
+        });
+    }
+
+    fn valid_save() -> SaveFile {
+        SaveFile {
+            version: SAVE_VERSION,
+            saved_at: 1_700_000_000,
+            play_time: 10.,
+            level_name: Some("Level_0".to_string()),
+            level: LevelSave::Index(0),
+            player: None,
+            progress: Default::default(),
+        }
+    }
+
+    fn setup(name: &str) -> App {
+        let dir = std::env::temp_dir().join(format!("onyx_load_menu_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let save_directory = SaveDirectory(dir);
+        write_save(&save_directory.slot_path("a_valid"), &valid_save()).unwrap();
+        fs::write(save_directory.slot_path("b_corrupt"), "{ not json").unwrap();
+        let mut newer = serde_json::to_value(valid_save()).unwrap();
+        newer["version"] = serde_json::Value::from(SAVE_VERSION + 1);
+        fs::write(save_directory.slot_path("c_newer"), newer.to_string()).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_plugin(TextPlugin::default())
+            .add_plugin(UiPlugin::default())
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(GamepadPlugin)
+            .add_state::<GameState>()
+            .add_plugin(LevelPlugin)
+            .add_plugin(SaveGamePlugin)
+            .add_plugin(LoadMenuPlugin)
+            .insert_resource(save_directory)
+            .insert_resource(NextState(Some(GameState::LoadMenu)));
+        update(&mut app, 2);
+        app
+    }
+}
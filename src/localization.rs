@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<StringTable>()
+            .init_asset_loader::<StringTableLoader>()
+            .init_resource::<Localization>()
+            .init_resource::<StringTableHandles>()
+            .add_system(load_string_tables)
+            .add_system(refresh_localized_text.after(load_string_tables));
+    }
+}
+
+/// Languages with a string table in `assets/locales`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+    French,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::English, Language::Spanish, Language::French];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+            Language::French => "fr",
+        }
+    }
+
+    /// The language after this one, for cycling through them in the menu.
+    pub fn next(self) -> Language {
+        let index = Language::ALL.iter().position(|language| *language == self).unwrap();
+        Language::ALL[(index + 1) % Language::ALL.len()]
+    }
+
+    fn plural_form(self, count: u64) -> PluralForm {
+        match self {
+            Language::English | Language::Spanish if count == 1 => PluralForm::One,
+            Language::French if count <= 1 => PluralForm::One,
+            _ => PluralForm::Other,
+        }
+    }
+
+    /// Asset path of the language's string table.
+    fn table_path(self) -> String {
+        format!("locales/{}.locale.json", self.code())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PluralForm {
+    One,
+    Other,
+}
+
+/// A translated string, or its forms by count.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Entry {
+    Text(String),
+    Plural {
+        /// Used for a count of zero instead of the language's own rule, when given.
+        #[serde(default)]
+        zero: Option<String>,
+        one: String,
+        other: String,
+    },
+}
+
+/// One language's strings by key, read from `assets/locales/<code>.locale.json`.
+#[derive(TypeUuid, Deserialize, Clone, Default, Debug, PartialEq)]
+#[uuid = "e8b3d6f2-41a7-4c95-8d0e-6a2f9c1b7e34"]
+pub struct StringTable(HashMap<String, Entry>);
+
+impl StringTable {
+    pub fn parse(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.0.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Entry)> {
+        self.0.iter()
+    }
+}
+
+#[derive(Default)]
+pub struct StringTableLoader;
+
+impl AssetLoader for StringTableLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let table = StringTable::parse(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(table));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locale.json"]
+    }
+}
+
+/// Keeps every language's table loaded so edits to them are picked up while the game runs.
+#[derive(Resource)]
+struct StringTableHandles(Vec<(Language, Handle<StringTable>)>);
+
+impl FromWorld for StringTableHandles {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        StringTableHandles(Language::ALL.into_iter()
+            .map(|language| (language, asset_server.load(language.table_path())))
+            .collect())
+    }
+}
+
+/// The string tables loaded so far and the language being shown.
+#[derive(Resource, Debug)]
+pub struct Localization {
+    language: Language,
+    tables: HashMap<Language, StringTable>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Localization { language: Language::default(), tables: HashMap::new() }
+    }
+}
+
+impl Localization {
+    /// The shipped tables read straight from disk, for tests that don't wait on the asset server.
+    #[cfg(test)]
+    pub fn shipped() -> Self {
+        let tables = Language::ALL.into_iter()
+            .map(|language| {
+                let path = format!("assets/{}", language.table_path());
+                let table = StringTable::parse(&std::fs::read(&path).unwrap())
+                    .unwrap_or_else(|error| panic!("{path} is not a valid string table: {error}"));
+                (language, table)
+            })
+            .collect();
+        Localization { language: Language::default(), tables }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    pub fn table(&self, language: Language) -> Option<&StringTable> {
+        self.tables.get(&language)
+    }
+
+    /// The entry for `key` in the current language, falling back to English.
+    fn entry(&self, key: &str) -> Option<&Entry> {
+        [self.language, Language::English].into_iter()
+            .find_map(|language| self.tables.get(&language)?.get(key))
+    }
+
+    /// The string for `key` with its placeholders left in, or `key` itself if no table has it.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        match self.entry(key) {
+            Some(Entry::Text(text)) | Some(Entry::Plural { other: text, .. }) => text.as_str(),
+            None => {
+                // Every string is missing until the tables load.
+                if self.tables.contains_key(&Language::English) {
+                    warn!("Missing string {key}");
+                }
+                key
+            }
+        }
+    }
+
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        fill(self.get(key), args)
+    }
+
+    /// The form of `key` for `count`, with `{count}` and `args` filled in.
+    pub fn plural(&self, key: &str, count: u64, args: &[(&str, &str)]) -> String {
+        let template = match self.entry(key) {
+            Some(Entry::Plural { zero: Some(zero), .. }) if count == 0 => zero.as_str(),
+            Some(Entry::Plural { one, other, .. }) => match self.language.plural_form(count) {
+                PluralForm::One => one.as_str(),
+                PluralForm::Other => other.as_str(),
+            },
+            _ => self.get(key),
+        };
+        let count = count.to_string();
+        let mut all_args = vec![("count", count.as_str())];
+        all_args.extend_from_slice(args);
+        fill(template, &all_args)
+    }
+}
+
+/// A run of a template: literal text or the name of a `{placeholder}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Piece<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Splits `template` around its `{name}` placeholders.
+pub fn pieces(template: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else { break; };
+        if start > 0 {
+            pieces.push(Piece::Text(&rest[..start]));
+        }
+        pieces.push(Piece::Placeholder(&rest[start + 1..start + length]));
+        rest = &rest[start + length + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    pieces
+}
+
+/// Replaces the placeholders named in `args`, keeping any others for a later fill.
+pub fn fill(template: &str, args: &[(&str, &str)]) -> String {
+    pieces(template).into_iter().map(|piece| match piece {
+        Piece::Text(text) => text.to_string(),
+        Piece::Placeholder(name) => match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => value.to_string(),
+            None => format!("{{{name}}}"),
+        },
+    }).collect()
+}
+
+/// Keeps the first section of a [Text] showing `key` in the current language.
+#[derive(Component, Clone, Debug, Default)]
+pub struct LocalizedText {
+    pub key: String,
+    pub args: Vec<(String, String)>,
+}
+
+impl LocalizedText {
+    pub fn new(key: &str) -> Self {
+        LocalizedText { key: key.to_string(), args: Vec::new() }
+    }
+
+    pub fn with_arg(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.args.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn text(&self, localization: &Localization) -> String {
+        let args: Vec<_> = self.args.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        localization.format(&self.key, &args)
+    }
+}
+
+/// Puts tables into [Localization] as they load or change on disk.
+fn load_string_tables(
+    mut events: EventReader<AssetEvent<StringTable>>,
+    handles: Res<StringTableHandles>,
+    tables: Res<Assets<StringTable>>,
+    mut localization: ResMut<Localization>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else { continue; };
+        let Some((language, _)) = handles.0.iter().find(|(_, loaded)| loaded == handle) else { continue; };
+        if let Some(table) = tables.get(handle) {
+            localization.tables.insert(*language, table.clone());
+        }
+    }
+}
+
+fn refresh_localized_text(
+    localization: Res<Localization>,
+    mut text_q: Query<(Ref<LocalizedText>, &mut Text)>,
+) {
+    for (localized, mut text) in text_q.iter_mut() {
+        if !localization.is_changed() && !localized.is_changed() { continue; }
+        if let Some(section) = text.sections.first_mut() {
+            section.value = localized.text(&localization);
+        }
+    }
+}
+
+#[cfg(test)]
+mod localization_test {
+    use std::thread;
+    use std::time::Duration;
+    use super::*;
+    use crate::test_utils::LoadTestPlugins;
+
+    #[test]
+    fn it_ships_every_key_in_every_language() {
+        let localization = Localization::shipped();
+        let english = localization.table(Language::English).unwrap();
+        for language in Language::ALL {
+            let table = localization.table(language).unwrap();
+            for (key, entry) in english.iter() {
+                let translated = table.get(key);
+                assert!(translated.is_some(), "{key} is missing from {}.locale.json", language.code());
+                assert_eq!(
+                    matches!(translated, Some(Entry::Plural { .. })),
+                    matches!(entry, Entry::Plural { .. }),
+                    "{key} in {}.locale.json should be plural exactly when it is in en.locale.json", language.code(),
+                );
+            }
+            for (key, _) in table.iter() {
+                assert!(english.get(key).is_some(), "{key} in {}.locale.json is not in en.locale.json", language.code());
+            }
+        }
+    }
+
+    #[test]
+    fn it_fills_placeholders() {
+        let localization = Localization::shipped();
+        assert_eq!(localization.format("log.key_picked_up", &[("key", "crypt")]), "Picked up the crypt key.");
+        assert_eq!(fill("{button} Open", &[]), "{button} Open");
+        assert_eq!(pieces("Equipped the {weapon}."), [
+            Piece::Text("Equipped the "),
+            Piece::Placeholder("weapon"),
+            Piece::Text("."),
+        ]);
+    }
+
+    #[test]
+    fn it_follows_each_language_plural_rules() {
+        let mut localization = Localization::shipped();
+        assert_eq!(localization.plural("load.slot_count", 0, &[]), "No saves yet");
+        assert_eq!(localization.plural("load.slot_count", 1, &[]), "1 save");
+        assert_eq!(localization.plural("load.slot_count", 3, &[]), "3 saves");
+        localization.set_language(Language::French);
+        let tables = localization.table(Language::French).unwrap();
+        let Some(Entry::Plural { one, .. }) = tables.get("load.slot_count") else { panic!("load.slot_count is not plural"); };
+        assert_eq!(localization.plural("load.slot_count", 1, &[]), fill(one, &[("count", "1")]));
+    }
+
+    #[test]
+    fn it_loads_the_string_tables_as_assets() {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin);
+        for _ in 0..100 {
+            if app.world.resource::<Localization>().tables.len() == Language::ALL.len() { break; }
+            thread::sleep(Duration::from_millis(10));
+            app.update();
+        }
+        let shipped = Localization::shipped();
+        let localization = app.world.resource::<Localization>();
+        for language in Language::ALL {
+            assert_eq!(localization.table(language), shipped.table(language), "{} did not load", language.code());
+        }
+    }
+
+    #[test]
+    fn it_shows_keys_until_the_tables_load() {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin);
+        let label = app.world.spawn((
+            Text::from_section("", TextStyle::default()),
+            LocalizedText::new("menu.new_game"),
+        )).id();
+        app.update();
+        assert_eq!(app.world.get::<Text>(label).unwrap().sections[0].value, "menu.new_game");
+        for _ in 0..100 {
+            if app.world.resource::<Localization>().table(Language::English).is_some() { break; }
+            thread::sleep(Duration::from_millis(10));
+            app.update();
+        }
+        app.update();
+        assert_eq!(app.world.get::<Text>(label).unwrap().sections[0].value, "New Game");
+    }
+
+    #[test]
+    fn it_refreshes_localized_text_on_language_change() {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped());
+        let label = app.world.spawn((
+            Text::from_section("", TextStyle::default()),
+            LocalizedText::new("menu.new_game"),
+        )).id();
+        app.update();
+        assert_eq!(app.world.get::<Text>(label).unwrap().sections[0].value, "New Game");
+        app.world.resource_mut::<Localization>().set_language(Language::Spanish);
+        app.update();
+        let spanish = app.world.resource::<Localization>().get("menu.new_game").to_string();
+        assert_eq!(app.world.get::<Text>(label).unwrap().sections[0].value, spanish);
+        assert_ne!(spanish, "New Game");
+    }
+}
@@ -4,17 +4,36 @@ use bevy_rapier2d::prelude::*;
 use game_state::GameState;
 use level::LevelPlugin;
 use player::PlayerPlugin;
-use crate::animation::animation_system;
+use crate::actions::ActionPlugin;
+use crate::animation::AnimationPlugin;
+use crate::aseprite::AsepritePlugin;
 
 use crate::camera::CameraPlugin;
+use crate::checkpoint::CheckpointPlugin;
 use crate::cursor_indicator::CursorIndicatorPlugin;
+use crate::dialogue::DialoguePlugin;
 use crate::door::DoorPlugin;
+use crate::enemy::EnemyPlugin;
 use crate::game_logs::UIPlugin;
 use crate::gamepad::GamepadPlugin;
+use crate::health::HealthPlugin;
 use crate::movement::MyInputPlugin;
 use crate::interaction::InteractionPlugin;
+use crate::inventory::InventoryPlugin;
+use crate::key::KeyPlugin;
+use crate::level_exit::LevelExitPlugin;
+use crate::load_menu::LoadMenuPlugin;
+use crate::localization::LocalizationPlugin;
+use crate::menu::MenuPlugin;
+use crate::network::{NetworkArgs, NetworkPlugin, start_session};
+use crate::remote_save::RemoteSavePlugin;
+use crate::save_game::SaveGamePlugin;
+use crate::spell::SpellPlugin;
+use crate::weapon::WeaponPlugin;
 
+mod actions;
 mod level;
+mod level_exit;
 mod camera;
 mod player;
 mod movement;
@@ -22,11 +41,27 @@ mod cursor_indicator;
 mod physics_bundle;
 mod game_logs;
 mod door;
+mod dialogue;
+mod key;
+mod inventory;
+mod enemy;
+mod spell;
+mod health;
+mod checkpoint;
+mod weapon;
 mod interaction;
 mod gamepad;
 mod game_state;
+#[cfg(test)]
 mod test_utils;
 mod animation;
+mod aseprite;
+mod menu;
+mod load_menu;
+mod localization;
+mod save_game;
+mod remote_save;
+mod network;
 
 fn main() {
     let mut app = App::new();
@@ -41,6 +76,10 @@ fn main() {
             ..default()
         }))
         .add_state::<GameState>()
+        .add_plugin(ActionPlugin)
+        .add_plugin(LocalizationPlugin)
+        .add_plugin(MenuPlugin)
+        .add_plugin(LoadMenuPlugin)
         .add_plugin(LevelPlugin)
         .add_plugin(PlayerPlugin)
         .add_plugin(CameraPlugin)
@@ -49,10 +88,33 @@ fn main() {
         .add_plugin(CursorIndicatorPlugin)
         .add_plugin(UIPlugin)
         .add_plugin(DoorPlugin)
+        .add_plugin(DialoguePlugin)
+        .add_plugin(KeyPlugin)
+        .add_plugin(InventoryPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(SpellPlugin)
+        .add_plugin(HealthPlugin)
+        .add_plugin(AsepritePlugin)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(CheckpointPlugin)
+        .add_plugin(WeaponPlugin)
+        .add_plugin(LevelExitPlugin)
         .add_plugin(InteractionPlugin)
+        .add_plugin(SaveGamePlugin)
+        .add_plugin(RemoteSavePlugin)
+        .add_plugin(NetworkPlugin)
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .insert_resource(LevelSelection::Index(1))
-        .add_system(animation_system);
+        .insert_resource(LevelSelection::Index(1));
+
+    match NetworkArgs::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => {
+            if let Err(error) = start_session(&mut app, &args) {
+                error!("Failed to start network session, playing offline: {error}");
+            }
+        }
+        Ok(None) => {}
+        Err(error) => error!("Invalid network arguments, playing offline: {error}"),
+    }
 
     #[cfg(feature = "debug-mode")]
     {
@@ -0,0 +1,369 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use crate::actions::{Action, ActionInput};
+use crate::dialogue::{no_dialogue, DialogueSet};
+use crate::game_state::GameState;
+use crate::localization::{Localization, LocalizedText};
+use crate::remote_save::{FetchRemoteSave, RemoteSaveStatus};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<MenuButtonPressed>()
+            .init_resource::<MenuSelection>()
+            .add_system(spawn_main_menu.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(despawn_main_menu.in_schedule(OnExit(GameState::MainMenu)))
+            .add_systems((
+                menu_mouse_input,
+                menu_action_input,
+                highlight_selected_button,
+                handle_menu_buttons,
+                update_remote_status_text,
+            ).chain().in_set(OnUpdate(GameState::MainMenu)))
+            .add_system(return_to_menu.run_if(no_dialogue).before(DialogueSet).in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_BUTTON: Color = Color::rgb(0.35, 0.35, 0.35);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuButton {
+    NewGame,
+    Load,
+    LoadRemote,
+    /// Switches to the next [Language](crate::localization::Language).
+    Language,
+    Quit,
+}
+
+impl MenuButton {
+    const ALL: [MenuButton; 5] = [
+        MenuButton::NewGame,
+        MenuButton::Load,
+        MenuButton::LoadRemote,
+        MenuButton::Language,
+        MenuButton::Quit,
+    ];
+
+    fn label_key(&self) -> &'static str {
+        match self {
+            MenuButton::NewGame => "menu.new_game",
+            MenuButton::Load => "menu.load",
+            MenuButton::LoadRemote => "menu.load_remote",
+            MenuButton::Language => "menu.language",
+            MenuButton::Quit => "menu.quit",
+        }
+    }
+}
+
+/// Index into [MenuButton::ALL] of the button the keyboard or gamepad is on.
+#[derive(Resource, Default)]
+pub struct MenuSelection(pub usize);
+
+impl MenuSelection {
+    fn button(&self) -> MenuButton {
+        MenuButton::ALL[self.0]
+    }
+
+    fn step(&mut self, offset: isize) {
+        let len = MenuButton::ALL.len() as isize;
+        self.0 = (self.0 as isize + offset).rem_euclid(len) as usize;
+    }
+}
+
+pub struct MenuButtonPressed(pub MenuButton);
+
+#[derive(Component)]
+struct MainMenu;
+
+#[derive(Component)]
+struct RemoteStatusText;
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut selection: ResMut<MenuSelection>,
+    localization: Res<Localization>,
+) {
+    selection.0 = 0;
+    let font = asset_server.load("fonts/kongtext/kongtext.ttf");
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        },
+        MainMenu,
+    )).with_children(|parent| {
+        for button in MenuButton::ALL {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(250.), Val::Px(50.)),
+                        margin: UiRect::all(Val::Px(8.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                },
+                button,
+            )).with_children(|parent| {
+                let label = LocalizedText::new(button.label_key());
+                parent.spawn((TextBundle::from_section(
+                    label.text(&localization),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ), label));
+            });
+        }
+        parent.spawn((
+            TextBundle::from_section("", TextStyle {
+                font: font.clone(),
+                font_size: 14.0,
+                color: Color::rgb(0.8, 0.3, 0.3),
+            }),
+            RemoteStatusText,
+        ));
+    });
+}
+
+fn despawn_main_menu(mut commands: Commands, menu_q: Query<Entity, With<MainMenu>>) {
+    for menu in menu_q.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+fn menu_mouse_input(
+    interaction_q: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut selection: ResMut<MenuSelection>,
+    mut pressed: EventWriter<MenuButtonPressed>,
+) {
+    for (interaction, button) in interaction_q.iter() {
+        let index = MenuButton::ALL.iter().position(|b| b == button).unwrap();
+        match interaction {
+            Interaction::Clicked => {
+                selection.0 = index;
+                pressed.send(MenuButtonPressed(*button));
+            }
+            Interaction::Hovered => selection.0 = index,
+            Interaction::None => {}
+        }
+    }
+}
+
+fn menu_action_input(
+    actions: ActionInput,
+    mut selection: ResMut<MenuSelection>,
+    mut pressed: EventWriter<MenuButtonPressed>,
+) {
+    if actions.just_pressed(Action::MenuUp) {
+        selection.step(-1);
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        selection.step(1);
+    }
+    if actions.just_pressed(Action::MenuConfirm) {
+        pressed.send(MenuButtonPressed(selection.button()));
+    }
+}
+
+fn highlight_selected_button(
+    selection: Res<MenuSelection>,
+    mut button_q: Query<(&MenuButton, &mut BackgroundColor)>,
+) {
+    if !selection.is_changed() { return; }
+    for (button, mut background) in button_q.iter_mut() {
+        *background = if *button == selection.button() {
+            SELECTED_BUTTON.into()
+        } else {
+            NORMAL_BUTTON.into()
+        };
+    }
+}
+
+fn handle_menu_buttons(
+    mut pressed: EventReader<MenuButtonPressed>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut fetch_ev: EventWriter<FetchRemoteSave>,
+    mut localization: ResMut<Localization>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for MenuButtonPressed(button) in pressed.iter() {
+        match button {
+            MenuButton::NewGame => next_state.set(GameState::InGame),
+            MenuButton::Load => next_state.set(GameState::LoadMenu),
+            MenuButton::LoadRemote => fetch_ev.send(FetchRemoteSave),
+            MenuButton::Language => {
+                let language = localization.language().next();
+                localization.set_language(language);
+            }
+            MenuButton::Quit => exit.send(AppExit),
+        }
+    }
+}
+
+fn update_remote_status_text(
+    status: Res<RemoteSaveStatus>,
+    localization: Res<Localization>,
+    mut text_q: Query<&mut Text, With<RemoteStatusText>>,
+) {
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = match &*status {
+            RemoteSaveStatus::Idle => String::new(),
+            RemoteSaveStatus::Fetching => localization.get("menu.fetching_remote").to_string(),
+            RemoteSaveStatus::Failed(error) => error.message(&localization),
+        };
+    }
+}
+
+fn return_to_menu(
+    actions: ActionInput,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+#[cfg(test)]
+mod menu_test {
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::gamepad::GamepadButtonChangedEvent;
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::text::TextPlugin;
+    use bevy::ui::UiPlugin;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::gamepad::GamepadPlugin;
+    use crate::localization::{Language, LocalizationPlugin};
+    use crate::remote_save::RemoteSaveError;
+    use crate::test_utils::{connect_test_gamepad, LoadTestPlugins, update};
+
+    #[test]
+    fn it_spawns_a_button_for_each_menu_option() {
+        let mut app = setup();
+        let buttons = app.world.query::<&MenuButton>().iter(&app.world).len();
+        assert_eq!(buttons, MenuButton::ALL.len());
+    }
+
+    #[test]
+    fn it_starts_the_game_when_new_game_is_confirmed_with_keyboard() {
+        let mut app = setup();
+        press_key(&mut app, KeyCode::Return);
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::InGame);
+    }
+
+    #[test]
+    fn it_starts_the_game_when_new_game_is_confirmed_with_gamepad() {
+        let mut app = setup();
+        connect_test_gamepad(&mut app);
+        app.world.send_event(GamepadButtonChangedEvent::new(
+            Gamepad { id: 1 },
+            GamepadButtonType::South,
+            1.0,
+        ));
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::InGame);
+    }
+
+    #[test]
+    fn it_opens_load_menu_when_load_is_confirmed() {
+        let mut app = setup();
+        press_key(&mut app, KeyCode::Down);
+        app.update();
+        assert_eq!(app.world.resource::<MenuSelection>().button(), MenuButton::Load);
+        press_key(&mut app, KeyCode::Return);
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::LoadMenu);
+    }
+
+    #[test]
+    fn it_wraps_selection_when_navigating_up() {
+        let mut app = setup();
+        press_key(&mut app, KeyCode::Up);
+        app.update();
+        assert_eq!(app.world.resource::<MenuSelection>().button(), MenuButton::Quit);
+    }
+
+    #[test]
+    fn it_shows_remote_save_errors() {
+        let mut app = setup();
+        app.world.insert_resource(RemoteSaveStatus::Failed(RemoteSaveError::TokenExpired));
+        app.update();
+        let text = app.world
+            .query_filtered::<&Text, With<RemoteStatusText>>()
+            .single(&app.world);
+        let message = RemoteSaveError::TokenExpired.message(app.world.resource::<Localization>());
+        assert_eq!(text.sections[0].value, message);
+    }
+
+    #[test]
+    fn it_quits_when_quit_is_confirmed() {
+        let mut app = setup();
+        app.world.resource_mut::<MenuSelection>().0 = MenuButton::ALL.len() - 1;
+        press_key(&mut app, KeyCode::Return);
+        app.update();
+        let exit_events = app.world.resource::<Events<AppExit>>();
+        assert_eq!(exit_events.len(), 1);
+    }
+
+    #[test]
+    fn it_cycles_the_language_when_language_is_confirmed() {
+        let mut app = setup();
+        app.world.resource_mut::<MenuSelection>().0 = 3;
+        assert_eq!(app.world.resource::<MenuSelection>().button(), MenuButton::Language);
+        press_key(&mut app, KeyCode::Return);
+        app.update();
+        assert_eq!(app.world.resource::<Localization>().language(), Language::Spanish);
+    }
+
+    #[test]
+    fn it_despawns_menu_when_game_starts() {
+        let mut app = setup();
+        press_key(&mut app, KeyCode::Return);
+        update(&mut app, 2);
+        let menus = app.world.query::<&MainMenu>().iter(&app.world).len();
+        assert_eq!(menus, 0);
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Pressed,
+        });
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_plugin(TextPlugin::default())
+            .add_plugin(UiPlugin::default())
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(GamepadPlugin)
+            .add_state::<GameState>()
+            .add_event::<FetchRemoteSave>()
+            .init_resource::<RemoteSaveStatus>()
+            .add_plugin(MenuPlugin);
+        app.update();
+        app
+    }
+}
@@ -1,53 +1,77 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::Velocity;
-use crate::gamepad;
-use crate::gamepad::MyGamepad;
+use bytemuck::{Pod, Zeroable};
+use crate::actions::{Action, ActionInput};
+use crate::game_state::GameState;
+use crate::dialogue::{no_dialogue, ActiveDialogue};
+use crate::health::Knockback;
+use crate::inventory::{inventory_closed, InventoryCursor};
+use crate::network::is_offline;
 use crate::player::Player;
 
 pub struct MyInputPlugin;
 
 impl Plugin for MyInputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(movement_input);
+        app.add_system(movement_input.run_if(is_offline).in_set(OnUpdate(GameState::InGame)));
     }
 }
 
-fn movement_input(
-    mut player_q: Query<&mut Velocity, With<Player>>,
-    keyboard_input: Res<Input<KeyCode>>,
-    axes: Res<Axis<GamepadAxis>>,
-    my_gamepad: Option<Res<MyGamepad>>,
-) {
-    let velocity_res = player_q.get_single_mut();
-    if velocity_res.is_err() { return; }
-    let mut velocity = velocity_res.unwrap();
-    let mut direction = Vec2::default();
-    let max_speed = 70.0;
-    handle_gamepad_input(axes, my_gamepad, &mut direction);
-    handle_keyboard_input(keyboard_input, &mut direction);
-    velocity.linvel = direction.normalize_or_zero() * max_speed;
+pub const MAX_SPEED: f32 = 70.0;
+const INTERACT_BUTTON: u8 = 1 << 0;
+
+/// One frame of a player's controls, plain enough to be sent to rollback peers.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Pod, Zeroable, Default, Debug)]
+pub struct PlayerInput {
+    pub buttons: u8,
+    pub move_x: i8,
+    pub move_y: i8,
+    padding: u8,
 }
 
-fn handle_keyboard_input(keyboard_input: Res<Input<KeyCode>>, direction: &mut Vec2) {
-    if keyboard_input.pressed(KeyCode::W) {
-        direction.y += 1.0;
+impl PlayerInput {
+    pub fn new(direction: Vec2, interact: bool) -> Self {
+        let axis = |value: f32| (value.clamp(-1., 1.) * i8::MAX as f32).round() as i8;
+        PlayerInput {
+            buttons: if interact { INTERACT_BUTTON } else { 0 },
+            move_x: axis(direction.x),
+            move_y: axis(direction.y),
+            padding: 0,
+        }
     }
-    if keyboard_input.pressed(KeyCode::S) {
-        direction.y -= 1.0;
+
+    pub fn direction(&self) -> Vec2 {
+        Vec2::new(self.move_x as f32, self.move_y as f32) / i8::MAX as f32
     }
-    if keyboard_input.pressed(KeyCode::A) {
-        direction.x -= 1.0;
+
+    pub fn interact(&self) -> bool {
+        self.buttons & INTERACT_BUTTON != 0
     }
-    if keyboard_input.pressed(KeyCode::D) {
-        direction.x += 1.0;
+
+    pub fn velocity(&self) -> Vec2 {
+        self.direction().normalize_or_zero() * MAX_SPEED
     }
 }
 
-fn handle_gamepad_input(axes: Res<Axis<GamepadAxis>>, my_gamepad: Option<Res<MyGamepad>>, direction: &mut Vec2) {
-    if my_gamepad.is_none() { return; }
-    let gamepad = my_gamepad.unwrap().0;
-    let new_direction = gamepad::get_left_axis_direction(axes, gamepad);
-    *direction = new_direction;
+/// Controls of the player sitting at this machine, as they would be sent to rollback peers.
+pub fn read_player_input(actions: &ActionInput) -> PlayerInput {
+    PlayerInput::new(actions.axis(Action::Move), actions.pressed(Action::Interact))
+}
+
+fn movement_input(
+    mut player_q: Query<&mut Velocity, (With<Player>, Without<Knockback>)>,
+    actions: ActionInput,
+    cursor: Option<Res<InventoryCursor>>,
+    dialogue: Option<Res<ActiveDialogue>>,
+) {
+    let velocity_res = player_q.get_single_mut();
+    if velocity_res.is_err() { return; }
+    let mut velocity = velocity_res.unwrap();
+    velocity.linvel = match inventory_closed(cursor) && no_dialogue(dialogue) {
+        true => read_player_input(&actions).velocity(),
+        false => Vec2::ZERO,
+    };
 }
 
 
@@ -59,10 +83,19 @@ mod input_tests {
     use bevy::input::{ButtonState, InputPlugin};
     use bevy::input::keyboard::KeyboardInput;
     use bevy::prelude::GamepadAxisType::{LeftStickX, LeftStickY};
+    use crate::actions::{ActionMap, ActionPlugin, Binding, SettingsPath};
     use crate::gamepad::GamepadPlugin;
     use crate::test_utils;
     use crate::player::*;
 
+    #[test]
+    fn player_input_round_trips_direction_and_buttons() {
+        let input = PlayerInput::new(Vec2::new(1., -1.), true);
+        assert_eq!(input.direction(), Vec2::new(1., -1.));
+        assert!(input.interact());
+        assert!(!PlayerInput::new(Vec2::ZERO, false).interact());
+    }
+
     #[test]
     fn moves_player_up() {
         let (mut app, player_entity) = setup();
@@ -99,6 +132,31 @@ mod input_tests {
         assert!(new_transform.translation.x > 0.);
     }
 
+    #[test]
+    fn moves_player_with_rebound_keys() {
+        let (mut app, player_entity) = setup();
+        app.world.resource_mut::<ActionMap>().bind(Action::Move, Binding::KeyAxis {
+            up: KeyCode::Up,
+            down: KeyCode::Down,
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+        });
+        press_key(&mut app, KeyCode::Right);
+        test_utils::update(&mut app, 2);
+        let new_transform = get_player_transform(&app, player_entity);
+        assert!(new_transform.translation.x > 0.);
+    }
+
+    #[test]
+    fn player_stands_still_while_the_inventory_is_open() {
+        let (mut app, player_entity) = setup();
+        app.insert_resource(InventoryCursor { open: true, ..default() });
+        press_key(&mut app, KeyCode::D);
+        test_utils::update(&mut app, 2);
+        let new_transform = get_player_transform(&app, player_entity);
+        assert_eq!(new_transform.translation.x, 0.);
+    }
+
     #[test]
     fn gamepad_moves_player_up() {
         let (mut app, player_entity) = setup();
@@ -154,11 +212,15 @@ mod input_tests {
         let mut app = App::new();
         app
             .add_plugin(TimePlugin)
+            .add_state::<GameState>()
             .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
             .add_plugin(GamepadPlugin)
             .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
             .add_plugin(MyInputPlugin);
         let player_entity = app.world.spawn(PlayerBundle::default()).id();
+        test_utils::enter_game(&mut app);
         (app, player_entity)
     }
 
@@ -0,0 +1,466 @@
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::{LdtkAsset, Respawn};
+use bevy_ggrs::{GGRSPlugin, GGRSSchedule, PlayerInputs, Rollback, RollbackIdProvider, Session};
+use bevy_rapier2d::prelude::{QueryFilter, RapierContext, Velocity};
+use ggrs::{Config, GGRSError, PlayerHandle, PlayerType, SessionBuilder, UdpNonBlockingSocket};
+use crate::animation::CharacterAnimation;
+use crate::dialogue::{no_dialogue, ActiveDialogue};
+use crate::door::Door;
+use crate::game_state::GameState;
+use crate::interaction::{Interaction, Interactor};
+use crate::actions::ActionInput;
+use crate::inventory::{inventory_closed, InventoryCursor};
+use crate::movement::{PlayerInput, read_player_input};
+use crate::physics_bundle::CharacterPhysicsBundle;
+use crate::health::{Health, PLAYER_HEALTH};
+use crate::player::{Player, PlayerBundle};
+
+pub const FPS: usize = 60;
+pub const NUM_PLAYERS: usize = 2;
+const INPUT_DELAY: usize = 2;
+const INTERACT_RANGE: f32 = 24.;
+/// Keeps a sliding player just short of the wall it ran into.
+const SKIN_WIDTH: f32 = 0.5;
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        GGRSPlugin::<GgrsConfig>::new()
+            .with_update_frequency(FPS)
+            .with_input_system(read_local_input)
+            .register_rollback_component::<Transform>()
+            .register_rollback_component::<Velocity>()
+            .register_rollback_component::<Door>()
+            .register_rollback_component::<PreviousInput>()
+            .build(app);
+        app
+            .add_system(assign_player_handles)
+            .add_system(despawn_peers.in_schedule(OnExit(GameState::InGame)))
+            .add_system(despawn_peers.run_if(respawning).in_base_set(CoreSet::PostUpdate))
+            .add_systems((
+                apply_network_inputs,
+                move_network_players,
+                network_door_interaction,
+                store_previous_inputs,
+            ).chain().in_schedule(GGRSSchedule));
+    }
+}
+
+#[derive(Debug)]
+pub struct GgrsConfig;
+
+impl Config for GgrsConfig {
+    type Input = PlayerInput;
+    type State = u8;
+    type Address = SocketAddr;
+}
+
+/// The handle of the peer in a rollback session this [Player] is controlled by.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkPlayer {
+    pub handle: PlayerHandle,
+}
+
+/// A [Player] spawned for a remote peer rather than from the LDtk level.
+#[derive(Component, Default)]
+pub struct NetworkPeer;
+
+/// Which session handle is played at this machine.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LocalPlayerHandle(pub PlayerHandle);
+
+/// Last confirmed buttons, so held buttons only trigger once even across rollbacks.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct PreviousInput(u8);
+
+/// Run condition for systems that read local devices directly instead of rollback inputs.
+pub fn is_offline(session: Option<Res<Session<GgrsConfig>>>) -> bool {
+    session.is_none()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NetworkArgs {
+    pub local_port: u16,
+    /// `None` marks the local player, in handle order.
+    pub players: Vec<Option<SocketAddr>>,
+    pub sync_test: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NetworkArgsError {
+    MissingLocalPort,
+    InvalidPort(String),
+    InvalidAddress(String),
+}
+
+impl Display for NetworkArgsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkArgsError::MissingLocalPort => write!(f, "--players needs a --local-port"),
+            NetworkArgsError::InvalidPort(port) => write!(f, "invalid local port {port:?}"),
+            NetworkArgsError::InvalidAddress(addr) => write!(f, "invalid player address {addr:?}"),
+        }
+    }
+}
+
+impl NetworkArgs {
+    /// Parses `--local-port 7000 --players localhost 127.0.0.1:7001 [--synctest]`, or `None`
+    /// without any of them.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<NetworkArgs>, NetworkArgsError> {
+        let mut args = args.into_iter().peekable();
+        let mut local_port = None;
+        let mut players = Vec::new();
+        let mut networked = false;
+        let mut sync_test = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--local-port" => {
+                    let port = args.next().unwrap_or_default();
+                    local_port = Some(port.parse().map_err(|_| NetworkArgsError::InvalidPort(port))?);
+                }
+                "--synctest" => sync_test = true,
+                "--players" => {
+                    networked = true;
+                    while let Some(player) = args.next_if(|a| !a.starts_with("--")) {
+                        players.push(match player.as_str() {
+                            "localhost" => None,
+                            addr => Some(addr.parse().map_err(|_| NetworkArgsError::InvalidAddress(player.clone()))?),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        if sync_test {
+            return Ok(Some(NetworkArgs { local_port: 0, players: vec![None; NUM_PLAYERS], sync_test }));
+        }
+        match (local_port, networked) {
+            (Some(local_port), _) => Ok(Some(NetworkArgs { local_port, players, sync_test })),
+            (None, true) => Err(NetworkArgsError::MissingLocalPort),
+            (None, false) => Ok(None),
+        }
+    }
+}
+
+pub fn start_session(app: &mut App, args: &NetworkArgs) -> Result<(), GGRSError> {
+    let mut builder = SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(args.players.len())
+        .with_input_delay(INPUT_DELAY);
+    if args.sync_test {
+        app.insert_resource(LocalPlayerHandle(0))
+            .insert_resource(Session::SyncTestSession(builder.start_synctest_session()?));
+        return Ok(());
+    }
+    let mut local_handle = 0;
+    for (handle, player) in args.players.iter().enumerate() {
+        builder = match player {
+            None => {
+                local_handle = handle;
+                builder.add_player(PlayerType::Local, handle)?
+            }
+            Some(addr) => builder.add_player(PlayerType::Remote(*addr), handle)?,
+        };
+    }
+    let socket = UdpNonBlockingSocket::bind_to_port(args.local_port)
+        .map_err(|_| GGRSError::SocketCreationFailed)?;
+    app.insert_resource(LocalPlayerHandle(local_handle))
+        .insert_resource(Session::P2PSession(builder.start_p2p_session(socket)?));
+    Ok(())
+}
+
+fn read_local_input(
+    _handle: In<PlayerHandle>,
+    actions: ActionInput,
+    cursor: Option<Res<InventoryCursor>>,
+    dialogue: Option<Res<ActiveDialogue>>,
+) -> PlayerInput {
+    match inventory_closed(cursor) && no_dialogue(dialogue) {
+        true => read_player_input(&actions),
+        false => PlayerInput::default(),
+    }
+}
+
+/// The LDtk spawn point becomes the local player; every other peer gets a copy of it next to it in the same level.
+fn assign_player_handles(
+    mut commands: Commands,
+    session: Option<Res<Session<GgrsConfig>>>,
+    local_handle: Option<Res<LocalPlayerHandle>>,
+    mut rip: Option<ResMut<RollbackIdProvider>>,
+    player_q: Query<(Entity, &Transform, Option<&Parent>, &Handle<TextureAtlas>, &TextureAtlasSprite),
+        (Added<Player>, Without<NetworkPeer>)>,
+    local_q: Query<(), (With<NetworkPlayer>, Without<NetworkPeer>)>,
+) {
+    let (Some(session), Some(local_handle), Some(rip)) = (session, local_handle, rip.as_mut()) else { return; };
+    // A level's own `Player` spawning while the local one is still around is despawned by the level warp.
+    if !local_q.is_empty() { return; }
+    let num_players = match &*session {
+        Session::SyncTestSession(s) => s.num_players(),
+        Session::P2PSession(s) => s.num_players(),
+        Session::SpectatorSession(s) => s.num_players(),
+    };
+    for (player, transform, parent, texture_atlas, sprite) in player_q.iter().take(1) {
+        for handle in 0..num_players {
+            let network_player = NetworkPlayer { handle };
+            let rollback = Rollback::new(rip.next_id());
+            if handle == local_handle.0 {
+                commands.entity(player).insert((
+                    network_player,
+                    rollback,
+                    PreviousInput::default(),
+                    CharacterPhysicsBundle::network_player().rigid_body,
+                ));
+                continue;
+            }
+            let offset = Vec3::new(16. * handle as f32, 0., 0.);
+            let peer = commands.spawn((
+                PlayerBundle {
+                    sprite_sheet_bundle: SpriteSheetBundle {
+                        texture_atlas: texture_atlas.clone(),
+                        sprite: sprite.clone(),
+                        transform: Transform::from_translation(transform.translation + offset),
+                        ..default()
+                    },
+                    physics: CharacterPhysicsBundle::network_player(),
+                    interactor: Interactor,
+                    animation: CharacterAnimation::new("Player"),
+                    health: Health::new(PLAYER_HEALTH),
+                    ..default()
+                },
+                network_player,
+                rollback,
+                PreviousInput::default(),
+                NetworkPeer,
+            )).id();
+            if let Some(parent) = parent {
+                commands.entity(parent.get()).add_child(peer);
+            }
+        }
+    }
+}
+
+/// Run condition for the frame between a world being told to [Respawn] and its levels spawning again.
+fn respawning(world_q: Query<(), (With<Respawn>, With<Handle<LdtkAsset>>)>) -> bool {
+    !world_q.is_empty()
+}
+
+/// Peers are spawned again next to the next local `Player`, so the old ones go with the one they were copied from.
+fn despawn_peers(mut commands: Commands, peer_q: Query<Entity, With<NetworkPeer>>) {
+    for peer in peer_q.iter() {
+        commands.entity(peer).despawn_recursive();
+    }
+}
+
+fn apply_network_inputs(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    mut player_q: Query<(&NetworkPlayer, &mut Velocity)>,
+) {
+    for (network_player, mut velocity) in player_q.iter_mut() {
+        let (input, _) = inputs[network_player.handle];
+        velocity.linvel = input.velocity();
+    }
+}
+
+/// Integrates velocity inside the rollback schedule so re-simulated frames land in the same
+/// place, sliding along colliders instead of handing the step to the physics engine.
+fn move_network_players(
+    rapier_context: Res<RapierContext>,
+    mut player_q: Query<(Entity, &Velocity, &mut Transform), With<NetworkPlayer>>,
+) {
+    let delta = 1. / FPS as f32;
+    for (player, velocity, mut transform) in player_q.iter_mut() {
+        let mut translation = transform.translation.truncate();
+        for axis in [Vec2::X, Vec2::Y] {
+            let step = velocity.linvel * axis * delta;
+            if step == Vec2::ZERO { continue; }
+            let filter = QueryFilter::only_fixed().exclude_collider(player).exclude_sensors();
+            let shape = CharacterPhysicsBundle::player().collider;
+            let allowed = match rapier_context.cast_shape(translation, 0., step, &shape, 1., filter) {
+                Some((_, hit)) => (hit.toi - SKIN_WIDTH / step.length()).max(0.),
+                None => 1.,
+            };
+            translation += step * allowed;
+        }
+        transform.translation = translation.extend(transform.translation.z);
+    }
+}
+
+fn network_door_interaction(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    player_q: Query<(&NetworkPlayer, &PreviousInput, &Transform)>,
+    mut door_q: Query<(&mut Door, &GlobalTransform), Without<NetworkPlayer>>,
+) {
+    for (network_player, previous, transform) in player_q.iter() {
+        let (input, _) = inputs[network_player.handle];
+        let was_pressed = PlayerInput { buttons: previous.0, ..input }.interact();
+        if !input.interact() || was_pressed { continue; }
+        let position = transform.translation.truncate();
+        let nearest = door_q.iter_mut()
+            .map(|(door, door_transform)| (door, door_transform.translation().truncate().distance(position)))
+            .filter(|(_, distance)| *distance <= INTERACT_RANGE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((mut door, _)) = nearest {
+            door.interact();
+        }
+    }
+}
+
+fn store_previous_inputs(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    mut player_q: Query<(&NetworkPlayer, &mut PreviousInput)>,
+) {
+    for (network_player, mut previous) in player_q.iter_mut() {
+        let (input, _) = inputs[network_player.handle];
+        previous.0 = input.buttons;
+    }
+}
+
+#[cfg(test)]
+mod network_test {
+    use std::thread;
+    use std::time::Duration;
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy_rapier2d::prelude::{NoUserData, RapierPhysicsPlugin, RigidBody};
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::gamepad::GamepadPlugin;
+    use crate::test_utils::LoadTestPlugins;
+
+    #[test]
+    fn it_parses_peer_to_peer_arguments() {
+        let args = NetworkArgs::parse(
+            ["--local-port", "7000", "--players", "localhost", "127.0.0.1:7001"]
+                .map(String::from)
+        ).unwrap().unwrap();
+        assert_eq!(args, NetworkArgs {
+            local_port: 7000,
+            players: vec![None, Some("127.0.0.1:7001".parse().unwrap())],
+            sync_test: false,
+        });
+    }
+
+    #[test]
+    fn it_runs_offline_without_arguments() {
+        assert_eq!(NetworkArgs::parse(Vec::<String>::new()), Ok(None));
+    }
+
+    #[test]
+    fn it_reports_bad_arguments() {
+        let parse = |args: &[&str]| NetworkArgs::parse(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            parse(&["--local-port", "7000", "--players", "localhost", "nowhere"]),
+            Err(NetworkArgsError::InvalidAddress("nowhere".to_string())),
+        );
+        assert_eq!(parse(&["--local-port", "seven"]), Err(NetworkArgsError::InvalidPort("seven".to_string())));
+        assert_eq!(parse(&["--players", "localhost"]), Err(NetworkArgsError::MissingLocalPort));
+    }
+
+    #[test]
+    fn it_moves_players_deterministically_under_sync_test() {
+        let mut app = setup();
+        let players = spawn_players(&mut app);
+        press_key(&mut app, KeyCode::D);
+        run_frames(&mut app, 30);
+        let first = app.world.get::<Transform>(players[0]).unwrap().translation;
+        let second = app.world.get::<Transform>(players[1]).unwrap().translation;
+        assert!(first.x > 0.);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn it_leaves_moving_networked_players_to_the_rollback_schedule() {
+        let mut app = setup();
+        let local = app.world.spawn((
+            Player,
+            SpriteSheetBundle::default(),
+            CharacterPhysicsBundle::player(),
+        )).id();
+        app.update();
+        let mut bodies = app.world.query_filtered::<&RigidBody, With<NetworkPlayer>>();
+        assert_eq!(bodies.iter(&app.world).len(), NUM_PLAYERS);
+        assert!(bodies.iter(&app.world).all(|body| *body == RigidBody::KinematicPositionBased));
+        assert!(app.world.get::<NetworkPlayer>(local).is_some());
+    }
+
+    #[test]
+    fn it_keeps_peers_in_the_local_players_level_until_it_respawns() {
+        let mut app = setup();
+        let level = app.world.spawn(TransformBundle::from_transform(Transform::from_xyz(-432., 0., 0.))).id();
+        let local = app.world.spawn((Player, SpriteSheetBundle::default())).set_parent(level).id();
+        app.update();
+        let peers: Vec<_> = app.world.query_filtered::<&Parent, With<NetworkPeer>>().iter(&app.world)
+            .map(|parent| parent.get())
+            .collect();
+        assert_eq!(peers, vec![level; NUM_PLAYERS - 1]);
+        app.world.spawn((Player, SpriteSheetBundle::default())).set_parent(level);
+        app.update();
+        assert_eq!(app.world.query::<&NetworkPeer>().iter(&app.world).len(), NUM_PLAYERS - 1);
+        assert!(app.world.get::<NetworkPlayer>(local).is_some());
+        app.world.spawn((Handle::<LdtkAsset>::default(), Respawn));
+        app.update();
+        assert_eq!(app.world.query::<&NetworkPeer>().iter(&app.world).len(), 0);
+    }
+
+    #[test]
+    fn it_toggles_doors_once_per_press_under_sync_test() {
+        let mut app = setup();
+        let players = spawn_players(&mut app);
+        app.world.get_mut::<Transform>(players[1]).unwrap().translation.x = 10. * INTERACT_RANGE;
+        let door = app.world.spawn((
+            Door::default(),
+            TransformBundle::default(),
+            Rollback::new(app.world.resource_mut::<RollbackIdProvider>().next_id()),
+        )).id();
+        press_key(&mut app, KeyCode::E);
+        run_frames(&mut app, 30);
+        // Holding the button across frames and rollbacks only toggles the door once.
+        assert!(app.world.get::<Door>(door).unwrap().is_open());
+    }
+
+    fn spawn_players(app: &mut App) -> Vec<Entity> {
+        (0..NUM_PLAYERS).map(|handle| {
+            let rollback = Rollback::new(app.world.resource_mut::<RollbackIdProvider>().next_id());
+            app.world.spawn((
+                TransformBundle::default(),
+                CharacterPhysicsBundle::network_player(),
+                NetworkPlayer { handle },
+                PreviousInput::default(),
+                rollback,
+            )).id()
+        }).collect()
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(key),
+            state: ButtonState::Pressed,
+        });
+    }
+
+    fn run_frames(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            thread::sleep(Duration::from_secs_f32(1. / FPS as f32));
+            app.update();
+        }
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(InputPlugin)
+            .add_plugin(GamepadPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+            .add_plugin(NetworkPlugin);
+        let args = NetworkArgs::parse(["--synctest".to_string()]).unwrap().unwrap();
+        start_session(&mut app, &args).unwrap();
+        app.update();
+        app
+    }
+}
@@ -24,17 +24,38 @@ impl Default for CharacterPhysicsBundle {
     }
 }
 
+impl CharacterPhysicsBundle {
+    pub fn player() -> CharacterPhysicsBundle {
+        CharacterPhysicsBundle {
+            collider: Collider::capsule_y(4., 7.),
+            rigid_body: RigidBody::Dynamic,
+            rotation_constraints: LockedAxes::ROTATION_LOCKED,
+            gravity: GravityScale(0.),
+            ..Default::default()
+        }
+    }
+
+    /// A player moved by hand in the rollback schedule, which Rapier must not integrate again.
+    pub fn network_player() -> CharacterPhysicsBundle {
+        CharacterPhysicsBundle {
+            rigid_body: RigidBody::KinematicPositionBased,
+            ..CharacterPhysicsBundle::player()
+        }
+    }
+
+    pub fn zombie() -> CharacterPhysicsBundle {
+        CharacterPhysicsBundle {
+            collider: Collider::capsule_y(5., 7.),
+            ..CharacterPhysicsBundle::player()
+        }
+    }
+}
+
 impl From<&EntityInstance> for CharacterPhysicsBundle {
     fn from(entity_instance: &EntityInstance) -> CharacterPhysicsBundle {
-        let rotation_constraints = LockedAxes::ROTATION_LOCKED;
         match entity_instance.identifier.as_ref() {
-            "Player" => CharacterPhysicsBundle {
-                collider: Collider::capsule_y(4., 7.),
-                rigid_body: RigidBody::Dynamic,
-                rotation_constraints,
-                gravity: GravityScale(0.),
-                ..Default::default()
-            },
+            "Player" => CharacterPhysicsBundle::player(),
+            "Zombie" => CharacterPhysicsBundle::zombie(),
             _ => CharacterPhysicsBundle::default(),
         }
     }
@@ -57,6 +78,18 @@ impl From<&EntityInstance> for ObjectPhysicsBundle {
                 sensor: Sensor,
                 events: ActiveEvents::COLLISION_EVENTS,
             },
+            "Npc" => ObjectPhysicsBundle {
+                collider: Collider::cuboid(12., 20.),
+                rigid_body: RigidBody::Fixed,
+                sensor: Sensor,
+                events: ActiveEvents::COLLISION_EVENTS,
+            },
+            "Key" | "Checkpoint" | "Weapon" | "LevelExit" => ObjectPhysicsBundle {
+                collider: Collider::cuboid(8., 8.),
+                rigid_body: RigidBody::Fixed,
+                sensor: Sensor,
+                events: ActiveEvents::COLLISION_EVENTS,
+            },
             _ => ObjectPhysicsBundle::default(),
         }
     }
@@ -1,8 +1,13 @@
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use crate::animation::{CharacterAnimation};
+use crate::game_state::GameState;
+use crate::health::Health;
 use crate::interaction::{InteractiveText, Interactor};
+use crate::inventory::Inventory;
 use crate::physics_bundle::CharacterPhysicsBundle;
+use crate::spell::SpellCaster;
+use crate::weapon::WeaponSlot;
 
 pub struct PlayerPlugin;
 
@@ -10,8 +15,7 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
             .register_ldtk_entity::<PlayerBundle>("Player")
-            .add_system(add_interactive_text)
-            .add_system(camera_follow);
+            .add_system(add_interactive_text.in_set(OnUpdate(GameState::InGame)));
     }
 }
 
@@ -37,21 +41,13 @@ pub struct PlayerBundle {
     #[bundle]
     pub physics: CharacterPhysicsBundle,
     pub interactor: Interactor,
+    pub inventory: Inventory,
+    pub caster: SpellCaster,
+    pub weapon: WeaponSlot,
     #[from_entity_instance]
-    pub animation: CharacterAnimation
-}
-
-fn camera_follow(
-    mut commands: Commands,
-    mut camera_q: Query<(Entity, &mut Transform), With<Camera>>,
-    player_q: Query<(Entity, &Transform), (Added<Player>, Without<Camera>)>,
-) {
-    for (player, p_transform) in player_q.iter() {
-        for (camera, mut transform) in camera_q.iter_mut() {
-            commands.entity(player).add_child(camera);
-            transform.translation.z -= p_transform.translation.z;
-        }
-    }
+    pub animation: CharacterAnimation,
+    #[from_entity_instance]
+    pub health: Health,
 }
 
 fn add_interactive_text(
@@ -86,8 +82,10 @@ mod player_tests {
     use bevy_rapier2d::prelude::*;
     use crate::level::LevelPlugin;
     use crate::{test_utils};
-    use crate::camera::CameraPlugin;
-    use crate::test_utils::LoadTestPlugins;
+    use bevy::input::InputPlugin;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::camera::{CameraController, CameraPlugin};
+    use crate::test_utils::{enter_game, LoadTestPlugins};
 
     #[test]
     fn player_spawns() {
@@ -104,27 +102,33 @@ mod player_tests {
     }
 
     #[test]
-    fn player_has_camara_as_child() {
+    fn camera_targets_the_player() {
+        let mut app = setup();
+        test_utils::update(&mut app, 9);
+        let player = app.world.query_filtered::<Entity, With<Player>>().single(&app.world);
+        let controller = app.world.query::<&CameraController>().single(&app.world);
+        assert_eq!(controller.target, Some(player));
+    }
+
+    #[test]
+    fn camera_survives_leaving_the_game() {
         let mut app = setup();
         test_utils::update(&mut app, 9);
-        assert_eq!(app.world.query_filtered::<&Parent, With<Camera>>()
-                       .iter(&app.world).len(), 1);
+        app.world.insert_resource(NextState(Some(GameState::MainMenu)));
+        test_utils::update(&mut app, 2);
+        assert_eq!(app.world.query::<&Camera>().iter(&app.world).len(), 1);
+        assert_eq!(app.world.query::<&Player>().iter(&app.world).len(), 0);
     }
 
     #[test]
     fn camera_does_not_exceed_default_clipping() {
         let mut app = setup();
         test_utils::update(&mut app, 9);
-        let mut player_query = app.world.query::<(&Camera, &Transform)>();
-        let mut camera_query = app.world.query::<(&Player, &Transform)>();
-        for (_, p_transform) in player_query.iter(&app.world) {
-            for (_, c_transform) in camera_query.iter(&app.world) {
-                assert!(
-                    p_transform.translation.z + c_transform.translation.z < 1000.,
-                    "Camera global transform is not above 1000 (clipping issues)"
-                );
-            }
-        }
+        let transform = app.world.query_filtered::<&GlobalTransform, With<Camera>>().single(&app.world);
+        assert!(
+            transform.translation().z < 1000.,
+            "Camera global transform is not above 1000 (clipping issues)"
+        );
     }
 
     #[test]
@@ -164,10 +168,15 @@ mod player_tests {
         let mut app = App::new();
         app
             .add_plugins(LoadTestPlugins)
+            .add_state::<GameState>()
             .add_plugin(LevelPlugin)
             .add_plugin(PlayerPlugin)
             .insert_resource(LevelSelection::Index(0))
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
             .add_plugin(CameraPlugin);
+        enter_game(&mut app);
         app
     }
 }
@@ -0,0 +1,310 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+use crate::localization::Localization;
+use crate::save_game::{parse_save, RestoreSave, SaveFile};
+
+pub struct RemoteSavePlugin;
+
+impl Plugin for RemoteSavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<FetchRemoteSave>()
+            .insert_resource(RemoteSaveSettings::from_env())
+            .init_resource::<RemoteSaveStatus>()
+            .add_systems((start_remote_fetch, poll_remote_fetch).chain());
+    }
+}
+
+const ENDPOINT_VAR: &str = "ONYX_SAVE_SERVER";
+const TOKEN_VAR: &str = "ONYX_SAVE_TOKEN";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the authoritative save lives and the JSON web token that grants access to it.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct RemoteSaveSettings {
+    pub endpoint: Option<String>,
+    pub token: Option<String>,
+}
+
+impl RemoteSaveSettings {
+    pub fn from_env() -> Self {
+        RemoteSaveSettings {
+            endpoint: std::env::var(ENDPOINT_VAR).ok(),
+            token: std::env::var(TOKEN_VAR).ok(),
+        }
+    }
+}
+
+pub struct FetchRemoteSave;
+
+#[derive(Resource, Debug, Default)]
+pub enum RemoteSaveStatus {
+    #[default]
+    Idle,
+    Fetching,
+    Failed(RemoteSaveError),
+}
+
+#[derive(Debug)]
+pub enum RemoteSaveError {
+    NotConfigured,
+    Unauthorized,
+    TokenExpired,
+    Unreachable(String),
+    Server(u16),
+    InvalidResponse(String),
+}
+
+impl Display for RemoteSaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoteSaveError::NotConfigured => write!(f, "No save server configured"),
+            RemoteSaveError::Unauthorized => write!(f, "Not authorized to load this save"),
+            RemoteSaveError::TokenExpired => write!(f, "Login expired, please sign in again"),
+            RemoteSaveError::Unreachable(reason) => write!(f, "Save server unreachable: {reason}"),
+            RemoteSaveError::Server(status) => write!(f, "Save server error ({status})"),
+            RemoteSaveError::InvalidResponse(reason) => write!(f, "Invalid save from server: {reason}"),
+        }
+    }
+}
+
+impl RemoteSaveError {
+    /// The error as shown to the player, in the current language.
+    pub fn message(&self, text: &Localization) -> String {
+        match self {
+            RemoteSaveError::NotConfigured => text.get("error.remote.not_configured").to_string(),
+            RemoteSaveError::Unauthorized => text.get("error.remote.unauthorized").to_string(),
+            RemoteSaveError::TokenExpired => text.get("error.remote.token_expired").to_string(),
+            RemoteSaveError::Unreachable(reason) => text.format("error.remote.unreachable", &[("reason", reason)]),
+            RemoteSaveError::Server(status) => text.format("error.remote.server", &[("status", &status.to_string())]),
+            RemoteSaveError::InvalidResponse(reason) => text.format("error.remote.invalid_response", &[("reason", reason)]),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct RemoteSaveTask(Task<Result<SaveFile, RemoteSaveError>>);
+
+/// Blocking GET of the save document, passing the token as the `token` query parameter.
+pub fn fetch_save(settings: &RemoteSaveSettings) -> Result<SaveFile, RemoteSaveError> {
+    let endpoint = settings.endpoint.as_deref().ok_or(RemoteSaveError::NotConfigured)?;
+    let token = settings.token.as_deref().ok_or(RemoteSaveError::NotConfigured)?;
+    let response = ureq::get(endpoint)
+        .query("token", token)
+        .timeout(REQUEST_TIMEOUT)
+        .call();
+    match response {
+        Ok(response) => {
+            let body = response.into_string()
+                .map_err(|e| RemoteSaveError::InvalidResponse(e.to_string()))?;
+            parse_save(body.as_bytes())
+                .map_err(|error| RemoteSaveError::InvalidResponse(error.to_string()))
+        }
+        Err(ureq::Error::Status(401, response)) => {
+            let challenge = response.header("WWW-Authenticate").unwrap_or_default();
+            if challenge.contains("expired") {
+                Err(RemoteSaveError::TokenExpired)
+            } else {
+                Err(RemoteSaveError::Unauthorized)
+            }
+        }
+        Err(ureq::Error::Status(403, _)) => Err(RemoteSaveError::Unauthorized),
+        Err(ureq::Error::Status(status, _)) => Err(RemoteSaveError::Server(status)),
+        Err(ureq::Error::Transport(transport)) => {
+            Err(RemoteSaveError::Unreachable(transport.to_string()))
+        }
+    }
+}
+
+fn start_remote_fetch(
+    mut commands: Commands,
+    mut fetch_ev: EventReader<FetchRemoteSave>,
+    settings: Res<RemoteSaveSettings>,
+    mut status: ResMut<RemoteSaveStatus>,
+    task: Option<Res<RemoteSaveTask>>,
+) {
+    if fetch_ev.iter().count() == 0 || task.is_some() { return; }
+    let settings = settings.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { fetch_save(&settings) });
+    commands.insert_resource(RemoteSaveTask(task));
+    *status = RemoteSaveStatus::Fetching;
+}
+
+fn poll_remote_fetch(
+    mut commands: Commands,
+    task: Option<ResMut<RemoteSaveTask>>,
+    mut status: ResMut<RemoteSaveStatus>,
+    mut restore_ev: EventWriter<RestoreSave>,
+) {
+    let Some(mut task) = task else { return; };
+    let Some(result) = future::block_on(future::poll_once(&mut task.0)) else { return; };
+    commands.remove_resource::<RemoteSaveTask>();
+    match result {
+        Ok(save) => {
+            *status = RemoteSaveStatus::Idle;
+            restore_ev.send(RestoreSave(save));
+        }
+        Err(error) => {
+            warn!("Failed to fetch remote save: {error}");
+            *status = RemoteSaveStatus::Failed(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod remote_save_test {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+    use bevy::input::InputPlugin;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::game_state::GameState;
+    use crate::level::LevelPlugin;
+    use crate::save_game::{LevelSave, SAVE_VERSION, SaveGamePlugin};
+    use crate::test_utils::{LoadTestPlugins, update};
+
+    #[test]
+    fn it_sends_the_token_and_parses_the_save() {
+        let body = serde_json::to_string(&test_save()).unwrap();
+        let (endpoint, request) = serve_once(response(200, "", &body));
+        let result = fetch_save(&settings(endpoint));
+        assert_eq!(result.unwrap(), test_save());
+        let request_line = request.recv().unwrap();
+        assert!(request_line.starts_with("GET /save?token=test-jwt "), "{request_line}");
+    }
+
+    #[test]
+    fn it_reports_unauthorized_tokens() {
+        let (endpoint, _) = serve_once(response(401, "", ""));
+        let result = fetch_save(&settings(endpoint));
+        assert!(matches!(result, Err(RemoteSaveError::Unauthorized)));
+    }
+
+    #[test]
+    fn it_reports_a_missing_token_as_not_configured() {
+        let result = fetch_save(&RemoteSaveSettings { endpoint: Some("http://127.0.0.1:1/save".to_string()), token: None });
+        assert!(matches!(result, Err(RemoteSaveError::NotConfigured)));
+    }
+
+    #[test]
+    fn it_reports_expired_tokens() {
+        let header = "WWW-Authenticate: Bearer error=\"invalid_token\", error_description=\"The token expired\"\r\n";
+        let (endpoint, _) = serve_once(response(401, header, ""));
+        let result = fetch_save(&settings(endpoint));
+        assert!(matches!(result, Err(RemoteSaveError::TokenExpired)));
+    }
+
+    #[test]
+    fn it_reports_unreachable_servers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/save", listener.local_addr().unwrap());
+        drop(listener);
+        let result = fetch_save(&settings(endpoint));
+        assert!(matches!(result, Err(RemoteSaveError::Unreachable(_))));
+    }
+
+    #[test]
+    fn it_rejects_responses_that_are_not_saves() {
+        let (endpoint, _) = serve_once(response(200, "", "{\"hello\": \"world\"}"));
+        let result = fetch_save(&settings(endpoint));
+        assert!(matches!(result, Err(RemoteSaveError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn it_restores_the_fetched_save() {
+        let body = serde_json::to_string(&test_save()).unwrap();
+        let (endpoint, _) = serve_once(response(200, "", &body));
+        let mut app = setup(endpoint);
+        app.world.send_event(FetchRemoteSave);
+        update_until_fetched(&mut app);
+        assert!(matches!(app.world.resource::<RemoteSaveStatus>(), RemoteSaveStatus::Idle));
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::InGame);
+    }
+
+    #[test]
+    fn it_surfaces_fetch_errors() {
+        let (endpoint, _) = serve_once(response(401, "", ""));
+        let mut app = setup(endpoint);
+        app.world.send_event(FetchRemoteSave);
+        update_until_fetched(&mut app);
+        assert!(matches!(
+            app.world.resource::<RemoteSaveStatus>(),
+            RemoteSaveStatus::Failed(RemoteSaveError::Unauthorized)
+        ));
+    }
+
+    fn update_until_fetched(app: &mut App) {
+        app.update();
+        for _ in 0..100 {
+            if app.world.get_resource::<RemoteSaveTask>().is_none() { break; }
+            thread::sleep(Duration::from_millis(10));
+            app.update();
+        }
+    }
+
+    fn response(status: u16, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status} Status\r\n{headers}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len(),
+        )
+    }
+
+    /// Stand-in save server answering a single request, reporting the request line it received.
+    fn serve_once(response: String) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/save", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 2 {
+                header.clear();
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            let _ = sender.send(request_line);
+        });
+        (endpoint, receiver)
+    }
+
+    fn settings(endpoint: String) -> RemoteSaveSettings {
+        RemoteSaveSettings {
+            endpoint: Some(endpoint),
+            token: Some("test-jwt".to_string()),
+        }
+    }
+
+    fn test_save() -> SaveFile {
+        SaveFile {
+            version: SAVE_VERSION,
+            saved_at: 1_700_000_000,
+            play_time: 10.,
+            level_name: Some("Level_1".to_string()),
+            level: LevelSave::Index(1),
+            player: Some([1., 2., 3.]),
+            progress: Default::default(),
+        }
+    }
+
+    fn setup(endpoint: String) -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_state::<GameState>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(LevelPlugin)
+            .add_plugin(SaveGamePlugin)
+            .add_plugin(RemoteSavePlugin)
+            .insert_resource(settings(endpoint));
+        app
+    }
+}
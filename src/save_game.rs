@@ -0,0 +1,734 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::actions::{Action, ActionInput};
+use crate::door::Door;
+use crate::game_state::GameState;
+use crate::inventory::{Inventory, Item, ItemPickedUp};
+use crate::key::Key;
+use crate::level::START_LEVEL;
+use crate::localization::Localization;
+use crate::network::NetworkPeer;
+use crate::player::Player;
+use crate::weapon::Weapon;
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SaveGame>()
+            .add_event::<LoadGame>()
+            .add_event::<RestoreSave>()
+            .add_event::<ItemPickedUp>()
+            .init_resource::<SaveDirectory>()
+            .init_resource::<GameProgress>()
+            .init_resource::<PlayTime>()
+            .add_systems((store_last_session, reset_progress).chain().in_schedule(OnExit(GameState::InGame)))
+            .add_systems((load_game, restore_save).chain())
+            .add_systems((
+                tick_play_time,
+                quick_save_input,
+                apply_door_progress,
+                apply_item_progress,
+                record_door_progress,
+                record_item_progress,
+                restore_player_position,
+                save_game,
+            ).chain().in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+/// Current save schema. Bump it and append to [MIGRATIONS] whenever [SaveFile] changes shape.
+pub const SAVE_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` save document to version `n + 2`.
+const MIGRATIONS: [fn(&mut Value); 1] = [migrate_key_ring_to_inventory];
+
+const QUICK_SAVE_SLOT: &str = "quicksave";
+const SAVE_EXTENSION: &str = "json";
+
+pub struct SaveGame {
+    pub slot: String,
+}
+
+pub struct LoadGame {
+    pub slot: String,
+}
+
+/// Applies an already parsed save to the game, wherever it came from.
+pub struct RestoreSave(pub SaveFile);
+
+#[derive(Resource)]
+pub struct SaveDirectory(pub PathBuf);
+
+impl Default for SaveDirectory {
+    fn default() -> Self {
+        SaveDirectory(PathBuf::from("saves"))
+    }
+}
+
+impl SaveDirectory {
+    pub fn slot_path(&self, slot: &str) -> PathBuf {
+        self.0.join(slot).with_extension(SAVE_EXTENSION)
+    }
+}
+
+/// Gameplay state that outlives the entities it belongs to.
+///
+/// LDtk rebuilds every entity from the level file when a level spawns, so anything the player
+/// changed has to be kept here and reapplied.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GameProgress {
+    /// Door `is_open` keyed by LDtk entity iid.
+    #[serde(default)]
+    pub doors: HashMap<String, bool>,
+    /// Iids of locked doors that have been unlocked.
+    #[serde(default)]
+    pub unlocked_doors: HashSet<String>,
+    /// Iids of keys, weapons and other pickups taken from the level.
+    #[serde(default)]
+    pub collected_items: HashSet<String>,
+    /// The player's [Inventory], once it has been recorded.
+    #[serde(default)]
+    pub inventory: Option<Inventory>,
+    /// Story flags set by dialogue effects and read by dialogue conditions.
+    #[serde(default)]
+    pub dialogue_flags: HashSet<String>,
+}
+
+#[derive(Resource, Default)]
+pub struct PlayTime(pub Duration);
+
+#[derive(Resource)]
+struct PendingPlayerPosition(Vec3);
+
+/// Snapshot of the game the player last left, so it can still be saved from the menus.
+#[derive(Resource)]
+pub struct LastSession(pub SaveFile);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SaveFile {
+    pub version: u32,
+    /// Seconds since the unix epoch.
+    pub saved_at: u64,
+    pub play_time: f32,
+    pub level_name: Option<String>,
+    pub level: LevelSave,
+    pub player: Option<[f32; 3]>,
+    pub progress: GameProgress,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LevelSave {
+    Identifier(String),
+    Index(usize),
+    Iid(String),
+    Uid(i32),
+}
+
+impl From<&LevelSelection> for LevelSave {
+    fn from(selection: &LevelSelection) -> Self {
+        match selection {
+            LevelSelection::Identifier(identifier) => LevelSave::Identifier(identifier.clone()),
+            LevelSelection::Index(index) => LevelSave::Index(*index),
+            LevelSelection::Iid(iid) => LevelSave::Iid(iid.clone()),
+            LevelSelection::Uid(uid) => LevelSave::Uid(*uid),
+        }
+    }
+}
+
+impl From<&LevelSave> for LevelSelection {
+    fn from(save: &LevelSave) -> Self {
+        match save {
+            LevelSave::Identifier(identifier) => LevelSelection::Identifier(identifier.clone()),
+            LevelSave::Index(index) => LevelSelection::Index(*index),
+            LevelSave::Iid(iid) => LevelSelection::Iid(iid.clone()),
+            LevelSave::Uid(uid) => LevelSelection::Uid(*uid),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Corrupt(String),
+    NewerVersion(u32),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "could not access save: {error}"),
+            SaveError::Corrupt(reason) => write!(f, "save is corrupt: {reason}"),
+            SaveError::NewerVersion(version) => {
+                write!(f, "save version {version} is newer than supported version {SAVE_VERSION}")
+            }
+        }
+    }
+}
+
+impl SaveError {
+    /// The error as shown to the player, in the current language.
+    pub fn message(&self, text: &Localization) -> String {
+        match self {
+            SaveError::Io(error) => text.format("error.save.io", &[("error", &error.to_string())]),
+            SaveError::Corrupt(reason) => text.format("error.save.corrupt", &[("reason", reason)]),
+            SaveError::NewerVersion(version) => text.format("error.save.newer", &[
+                ("version", &version.to_string()),
+                ("supported", &SAVE_VERSION.to_string()),
+            ]),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+/// Writes next to the destination first so a crash mid-write never leaves a truncated save.
+pub fn write_save(path: &Path, save: &SaveFile) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_vec_pretty(save)
+        .map_err(|e| SaveError::Corrupt(e.to_string()))?;
+    let tmp_path = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(&contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub fn delete_save(path: &Path) -> Result<(), SaveError> {
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+pub struct SaveSlot {
+    pub name: String,
+    pub save: Result<SaveFile, SaveError>,
+}
+
+/// Every save in `directory`, including the ones that can no longer be loaded.
+pub fn list_slots(directory: &SaveDirectory) -> Vec<SaveSlot> {
+    let Ok(entries) = fs::read_dir(&directory.0) else { return Vec::new(); };
+    let mut slots: Vec<SaveSlot> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == SAVE_EXTENSION))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            Some(SaveSlot { name, save: read_save(&path) })
+        })
+        .collect();
+    slots.sort_by(|a, b| a.name.cmp(&b.name));
+    slots
+}
+
+pub fn read_save(path: &Path) -> Result<SaveFile, SaveError> {
+    let contents = fs::read(path)?;
+    parse_save(&contents)
+}
+
+pub fn parse_save(contents: &[u8]) -> Result<SaveFile, SaveError> {
+    let document: Value = serde_json::from_slice(contents)
+        .map_err(|e| SaveError::Corrupt(e.to_string()))?;
+    migrate(document)
+}
+
+fn migrate(mut document: Value) -> Result<SaveFile, SaveError> {
+    let version = document.get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| SaveError::Corrupt("missing version".to_string()))? as u32;
+    if version > SAVE_VERSION {
+        return Err(SaveError::NewerVersion(version));
+    }
+    if version == 0 {
+        return Err(SaveError::Corrupt("invalid version 0".to_string()));
+    }
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(&mut document);
+    }
+    document["version"] = Value::from(SAVE_VERSION);
+    let save: SaveFile = serde_json::from_value(document).map_err(|e| SaveError::Corrupt(e.to_string()))?;
+    // Restoring turns it into a Duration, which can't be negative or infinite.
+    if !save.play_time.is_finite() || save.play_time < 0. {
+        return Err(SaveError::Corrupt(format!("invalid play time {}", save.play_time)));
+    }
+    Ok(save)
+}
+
+/// Version 1 kept keys on a key ring of their own, before every item went through the [Inventory].
+fn migrate_key_ring_to_inventory(document: &mut Value) {
+    let Some(progress) = document.get_mut("progress").and_then(Value::as_object_mut) else { return; };
+    if let Some(collected_keys) = progress.remove("collected_keys") {
+        progress.insert("collected_items".to_string(), collected_keys);
+    }
+    let Some(Value::Array(keys)) = progress.remove("keys") else { return; };
+    let mut inventory = Inventory::default();
+    for key_id in keys.iter().filter_map(Value::as_str) {
+        if let Err(error) = inventory.add(Item::Key(key_id.to_string()), 1) {
+            warn!("Dropped key {key_id} while migrating the key ring to the inventory: {error:?}");
+        }
+    }
+    if let Ok(inventory) = serde_json::to_value(inventory) {
+        progress.insert("inventory".to_string(), inventory);
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Everything needed to capture the running game as a [SaveFile].
+#[derive(SystemParam)]
+pub struct SessionSnapshot<'w, 's> {
+    level_selection: Res<'w, LevelSelection>,
+    world_q: Query<'w, 's, &'static Handle<LdtkAsset>>,
+    ldtk_assets: Res<'w, Assets<LdtkAsset>>,
+    player_q: Query<'w, 's, &'static Transform, (With<Player>, Without<NetworkPeer>)>,
+    progress: Res<'w, GameProgress>,
+    play_time: Res<'w, PlayTime>,
+}
+
+impl SessionSnapshot<'_, '_> {
+    pub fn take(&self) -> SaveFile {
+        SaveFile {
+            version: SAVE_VERSION,
+            saved_at: unix_now(),
+            play_time: self.play_time.0.as_secs_f32(),
+            level_name: self.level_name(),
+            level: LevelSave::from(&*self.level_selection),
+            player: self.player_q.get_single().ok()
+                .map(|transform| transform.translation.to_array()),
+            progress: self.progress.clone(),
+        }
+    }
+
+    fn level_name(&self) -> Option<String> {
+        self.world_q.iter()
+            .filter_map(|handle| self.ldtk_assets.get(handle))
+            .find_map(|asset| asset.get_level(&self.level_selection))
+            .map(|level| level.identifier.clone())
+    }
+}
+
+fn tick_play_time(time: Res<Time>, mut play_time: ResMut<PlayTime>) {
+    play_time.0 += time.delta();
+}
+
+fn quick_save_input(
+    actions: ActionInput,
+    mut save_ev: EventWriter<SaveGame>,
+    mut load_ev: EventWriter<LoadGame>,
+) {
+    if actions.just_pressed(Action::QuickSave) {
+        save_ev.send(SaveGame { slot: QUICK_SAVE_SLOT.to_string() });
+    }
+    if actions.just_pressed(Action::QuickLoad) {
+        load_ev.send(LoadGame { slot: QUICK_SAVE_SLOT.to_string() });
+    }
+}
+
+fn apply_door_progress(
+    progress: Res<GameProgress>,
+    mut door_q: Query<&mut Door, Added<Door>>,
+) {
+    for mut door in door_q.iter_mut() {
+        if progress.unlocked_doors.contains(door.iid()) {
+            door.unlock();
+        }
+        if let Some(&is_open) = progress.doors.get(door.iid()) {
+            if door.is_open() != is_open {
+                door.set_open(is_open);
+            }
+        }
+    }
+}
+
+fn apply_item_progress(
+    mut commands: Commands,
+    progress: Res<GameProgress>,
+    key_q: Query<(Entity, &Key), Added<Key>>,
+    weapon_q: Query<(Entity, &Weapon), Added<Weapon>>,
+    mut inventory_q: Query<&mut Inventory, (Added<Inventory>, With<Player>, Without<NetworkPeer>)>,
+) {
+    let collected = key_q.iter().map(|(entity, key)| (entity, key.iid()))
+        .chain(weapon_q.iter().map(|(entity, weapon)| (entity, weapon.iid())));
+    for (entity, iid) in collected {
+        if progress.collected_items.contains(iid) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    let Some(saved) = &progress.inventory else { return; };
+    for mut inventory in inventory_q.iter_mut() {
+        *inventory = saved.clone();
+    }
+}
+
+fn record_door_progress(
+    mut progress: ResMut<GameProgress>,
+    door_q: Query<&Door, Changed<Door>>,
+) {
+    for door in door_q.iter() {
+        progress.doors.insert(door.iid().to_string(), door.is_open());
+        if door.key_id().is_some() && !door.is_locked() {
+            progress.unlocked_doors.insert(door.iid().to_string());
+        }
+    }
+}
+
+fn record_item_progress(
+    mut progress: ResMut<GameProgress>,
+    mut picked_up_ev: EventReader<ItemPickedUp>,
+    inventory_q: Query<&Inventory, (Changed<Inventory>, With<Player>, Without<NetworkPeer>)>,
+) {
+    for event in picked_up_ev.iter() {
+        progress.collected_items.insert(event.iid.clone());
+    }
+    for inventory in inventory_q.iter() {
+        progress.inventory = Some(inventory.clone());
+    }
+}
+
+fn restore_player_position(
+    mut commands: Commands,
+    pending: Option<Res<PendingPlayerPosition>>,
+    mut player_q: Query<&mut Transform, (Added<Player>, Without<NetworkPeer>)>,
+) {
+    let Some(pending) = pending else { return; };
+    for mut transform in player_q.iter_mut() {
+        transform.translation = pending.0;
+        commands.remove_resource::<PendingPlayerPosition>();
+    }
+}
+
+fn save_game(
+    mut save_ev: EventReader<SaveGame>,
+    save_directory: Res<SaveDirectory>,
+    session: SessionSnapshot,
+) {
+    for SaveGame { slot } in save_ev.iter() {
+        let save = session.take();
+        match write_save(&save_directory.slot_path(slot), &save) {
+            Ok(()) => info!("Saved game to slot {slot}"),
+            Err(error) => error!("Failed to save slot {slot}: {error}"),
+        }
+    }
+}
+
+fn load_game(
+    mut load_ev: EventReader<LoadGame>,
+    mut restore_ev: EventWriter<RestoreSave>,
+    save_directory: Res<SaveDirectory>,
+) {
+    for LoadGame { slot } in load_ev.iter() {
+        match read_save(&save_directory.slot_path(slot)) {
+            Ok(save) => restore_ev.send(RestoreSave(save)),
+            Err(error) => error!("Failed to load slot {slot}: {error}"),
+        }
+    }
+}
+
+fn restore_save(
+    mut commands: Commands,
+    mut restore_ev: EventReader<RestoreSave>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    world_q: Query<Entity, With<Handle<LdtkAsset>>>,
+) {
+    for RestoreSave(save) in restore_ev.iter() {
+        commands.insert_resource(LevelSelection::from(&save.level));
+        commands.insert_resource(save.progress.clone());
+        commands.insert_resource(PlayTime(Duration::from_secs_f32(save.play_time)));
+        if let Some(translation) = save.player {
+            commands.insert_resource(PendingPlayerPosition(Vec3::from_array(translation)));
+        }
+        if state.0 == GameState::InGame {
+            for world in world_q.iter() {
+                commands.entity(world).insert(Respawn);
+            }
+        } else {
+            next_state.set(GameState::InGame);
+        }
+    }
+}
+
+fn store_last_session(mut commands: Commands, session: SessionSnapshot) {
+    commands.insert_resource(LastSession(session.take()));
+}
+
+fn reset_progress(mut commands: Commands) {
+    commands.insert_resource(GameProgress::default());
+    commands.insert_resource(PlayTime::default());
+    commands.insert_resource(START_LEVEL);
+    commands.remove_resource::<PendingPlayerPosition>();
+}
+
+#[cfg(test)]
+mod save_game_test {
+    use bevy::input::InputPlugin;
+    use bevy_rapier2d::prelude::{NoUserData, RapierPhysicsPlugin};
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::door::DoorPlugin;
+    use crate::game_logs::GameLogEvent;
+    use crate::gamepad::GamepadPlugin;
+    use crate::interaction::{Interaction, InteractionPlugin};
+    use crate::level::LevelPlugin;
+    use crate::localization::LocalizationPlugin;
+    use crate::player::PlayerPlugin;
+    use crate::test_utils::{enter_game, LoadTestPlugins, update};
+
+    const LEVEL_0_DOOR: &str = "4c5c3b30-c640-11ed-922e-cdb2bb070654";
+
+    #[test]
+    fn it_round_trips_a_save_file() {
+        let dir = test_directory("round_trip");
+        let save = test_save();
+        let path = dir.slot_path("slot");
+        write_save(&path, &save).unwrap();
+        assert_eq!(read_save(&path).unwrap(), save);
+    }
+
+    #[test]
+    fn it_does_not_leave_temporary_files_behind() {
+        let dir = test_directory("atomic");
+        write_save(&dir.slot_path("slot"), &test_save()).unwrap();
+        let files = fs::read_dir(&dir.0).unwrap().count();
+        assert_eq!(files, 1);
+    }
+
+    #[test]
+    fn it_rejects_saves_from_newer_versions() {
+        let mut document = serde_json::to_value(test_save()).unwrap();
+        document["version"] = Value::from(SAVE_VERSION + 1);
+        let result = parse_save(document.to_string().as_bytes());
+        assert!(matches!(result, Err(SaveError::NewerVersion(v)) if v == SAVE_VERSION + 1));
+    }
+
+    #[test]
+    fn it_reports_corrupt_saves() {
+        let result = parse_save(b"{ not json");
+        assert!(matches!(result, Err(SaveError::Corrupt(_))));
+    }
+
+    #[test]
+    fn it_rejects_invalid_play_times() {
+        for play_time in [Value::from(-1.), Value::from(1e39)] {
+            let mut document = serde_json::to_value(test_save()).unwrap();
+            document["play_time"] = play_time;
+            let result = parse_save(document.to_string().as_bytes());
+            assert!(matches!(result, Err(SaveError::Corrupt(_))));
+        }
+    }
+
+    #[test]
+    fn it_lists_usable_and_unusable_slots() {
+        let dir = test_directory("list_slots");
+        write_save(&dir.slot_path("a"), &test_save()).unwrap();
+        fs::write(dir.slot_path("b"), "{ not json").unwrap();
+        let slots = list_slots(&dir);
+        assert_eq!(slots.len(), 2);
+        assert!(slots[0].save.is_ok());
+        assert!(slots[1].save.is_err());
+    }
+
+    #[test]
+    fn it_keeps_a_snapshot_of_the_last_session() {
+        let mut app = setup("last_session");
+        close_door(&mut app);
+        app.world.insert_resource(NextState(Some(GameState::MainMenu)));
+        update(&mut app, 2);
+        let last_session = app.world.resource::<LastSession>();
+        assert_eq!(last_session.0.progress.doors.get(LEVEL_0_DOOR), Some(&false));
+    }
+
+    #[test]
+    fn it_starts_new_games_without_dialogue_flags() {
+        let mut app = setup("dialogue_flags");
+        app.world.resource_mut::<GameProgress>().dialogue_flags.insert("met".to_string());
+        app.world.insert_resource(NextState(Some(GameState::MainMenu)));
+        update(&mut app, 2);
+        assert!(app.world.resource::<LastSession>().0.progress.dialogue_flags.contains("met"));
+        assert!(app.world.resource::<GameProgress>().dialogue_flags.is_empty());
+    }
+
+    #[test]
+    fn it_starts_new_games_in_the_first_level() {
+        let mut app = setup("start_level");
+        app.world.insert_resource(LevelSelection::Index(1));
+        app.world.insert_resource(NextState(Some(GameState::MainMenu)));
+        update(&mut app, 2);
+        assert_eq!(app.world.resource::<LastSession>().0.level, LevelSave::Index(1));
+        assert_eq!(*app.world.resource::<LevelSelection>(), START_LEVEL);
+    }
+
+    #[test]
+    fn it_saves_player_position_and_door_state() {
+        let mut app = setup("saves_state");
+        close_door(&mut app);
+        app.world.send_event(SaveGame { slot: "slot".to_string() });
+        app.update();
+        let save = read_save(&app.world.resource::<SaveDirectory>().slot_path("slot")).unwrap();
+        assert_eq!(save.level, LevelSave::Index(0));
+        assert!(save.player.is_some());
+        assert_eq!(save.progress.doors.get(LEVEL_0_DOOR), Some(&false));
+    }
+
+    #[test]
+    fn it_restores_door_state_after_level_respawns() {
+        let mut app = setup("restores_state");
+        close_door(&mut app);
+        app.world.send_event(SaveGame { slot: "slot".to_string() });
+        app.update();
+        app.world.insert_resource(NextState(Some(GameState::MainMenu)));
+        update(&mut app, 2);
+        app.world.send_event(LoadGame { slot: "slot".to_string() });
+        update(&mut app, 6);
+        let door = app.world.query::<&Door>().single(&app.world);
+        assert!(!door.is_open());
+    }
+
+    #[test]
+    fn it_restores_player_position() {
+        let mut app = setup("restores_player");
+        let mut save = test_save();
+        save.player = Some([12., 34., 5.]);
+        app.world.send_event(RestoreSave(save));
+        update(&mut app, 6);
+        let transform = app.world
+            .query_filtered::<&Transform, With<Player>>()
+            .single(&app.world);
+        assert_eq!(transform.translation.truncate(), Vec2::new(12., 34.));
+    }
+
+    #[test]
+    fn it_restores_the_inventory_and_unlocked_doors() {
+        let mut app = setup("restores_inventory");
+        let mut save = test_save();
+        let mut inventory = Inventory::default();
+        inventory.add(Item::Key("crypt".to_string()), 1).unwrap();
+        save.progress.unlocked_doors.insert(LEVEL_0_DOOR.to_string());
+        save.progress.inventory = Some(inventory.clone());
+        app.world.send_event(RestoreSave(save));
+        update(&mut app, 6);
+        let restored = app.world
+            .query_filtered::<&Inventory, With<Player>>()
+            .single(&app.world);
+        assert_eq!(*restored, inventory);
+        let progress = app.world.resource::<GameProgress>();
+        assert!(progress.unlocked_doors.contains(LEVEL_0_DOOR));
+        assert_eq!(progress.inventory, Some(inventory));
+    }
+
+    #[test]
+    fn it_records_collected_items() {
+        let mut app = setup("collected_items");
+        app.world.send_event(ItemPickedUp { iid: "key-iid".to_string() });
+        app.world.query_filtered::<&mut Inventory, With<Player>>()
+            .single_mut(&mut app.world)
+            .add(Item::Key("crypt".to_string()), 1)
+            .unwrap();
+        app.update();
+        let progress = app.world.resource::<GameProgress>();
+        assert!(progress.collected_items.contains("key-iid"));
+        let inventory = progress.inventory.as_ref().unwrap();
+        assert_eq!(inventory.count(&Item::Key("crypt".to_string())), 1);
+    }
+
+    #[test]
+    fn it_saves_the_local_player_rather_than_network_peers() {
+        let mut app = setup("network_peers");
+        let mut peer_inventory = Inventory::default();
+        peer_inventory.add(Item::Key("crypt".to_string()), 1).unwrap();
+        app.world.spawn((Player, NetworkPeer, TransformBundle::default(), peer_inventory));
+        app.world.send_event(SaveGame { slot: "slot".to_string() });
+        app.update();
+        let save = read_save(&app.world.resource::<SaveDirectory>().slot_path("slot")).unwrap();
+        assert!(save.player.is_some());
+        assert_eq!(save.progress.inventory.unwrap_or_default().count(&Item::Key("crypt".to_string())), 0);
+    }
+
+    #[test]
+    fn it_moves_version_1_keys_into_the_inventory() {
+        let mut document = serde_json::to_value(test_save()).unwrap();
+        document["version"] = Value::from(1);
+        let progress = document["progress"].as_object_mut().unwrap();
+        progress.remove("collected_items");
+        progress.remove("inventory");
+        progress.insert("collected_keys".to_string(), serde_json::json!(["key-iid"]));
+        progress.insert("keys".to_string(), serde_json::json!(["crypt", "crypt"]));
+        let save = parse_save(document.to_string().as_bytes()).unwrap();
+        assert_eq!(save.version, SAVE_VERSION);
+        assert!(save.progress.collected_items.contains("key-iid"));
+        let inventory = save.progress.inventory.unwrap();
+        assert_eq!(inventory.count(&Item::Key("crypt".to_string())), 2);
+    }
+
+    fn close_door(app: &mut App) {
+        let mut door = app.world.query::<&mut Door>().single_mut(&mut app.world);
+        door.interact();
+        app.update();
+    }
+
+    fn test_save() -> SaveFile {
+        let mut progress = GameProgress::default();
+        progress.doors.insert(LEVEL_0_DOOR.to_string(), false);
+        SaveFile {
+            version: SAVE_VERSION,
+            saved_at: 1_700_000_000,
+            play_time: 42.,
+            level_name: Some("Level_0".to_string()),
+            level: LevelSave::Index(0),
+            player: Some([1., 2., 3.]),
+            progress,
+        }
+    }
+
+    fn test_directory(name: &str) -> SaveDirectory {
+        let dir = std::env::temp_dir().join(format!("onyx_save_game_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        SaveDirectory(dir)
+    }
+
+    fn setup(name: &str) -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(GamepadPlugin)
+            .add_plugin(InteractionPlugin)
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+            .add_plugin(LevelPlugin)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(DoorPlugin)
+            .add_plugin(SaveGamePlugin)
+            .insert_resource(test_directory(name))
+            .insert_resource(LevelSelection::Index(0));
+        enter_game(&mut app);
+        update(&mut app, 3);
+        app
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use crate::actions::{Action, ActionInput};
+use crate::cursor_indicator::CursorIndicator;
+use crate::game_state::GameState;
+use crate::dialogue::{no_dialogue, ActiveDialogue};
+use crate::health::Damage;
+use crate::inventory::{inventory_closed, InventoryCursor};
+use crate::network::is_offline;
+use crate::player::Player;
+
+pub struct SpellPlugin;
+
+impl Plugin for SpellPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SpellHit>()
+            .add_system(cast_spells.run_if(is_offline).in_set(OnUpdate(GameState::InGame)))
+            .add_systems((expire_projectiles, handle_projectile_collisions).in_set(OnUpdate(GameState::InGame)))
+            .add_system(despawn_projectiles.in_schedule(OnExit(GameState::InGame)));
+    }
+}
+
+/// How far in front of the caster projectiles appear, so they don't start inside its collider.
+const SPAWN_OFFSET: f32 = 12.;
+const SPELL_COLOR: Color = Color::rgb(0.5, 0.8, 1.);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spell {
+    pub speed: f32,
+    /// Seconds before an unobstructed projectile fizzles out.
+    pub lifetime: f32,
+    pub damage: u32,
+    /// Seconds between casts.
+    pub cooldown: f32,
+    pub radius: f32,
+}
+
+impl Spell {
+    pub fn magic_missile() -> Spell {
+        Spell {
+            speed: 200.,
+            lifetime: 1.5,
+            damage: 1,
+            cooldown: 0.4,
+            radius: 3.,
+        }
+    }
+}
+
+impl Default for Spell {
+    fn default() -> Self {
+        Spell::magic_missile()
+    }
+}
+
+/// Lets an entity cast its spell along its [CursorIndicator].
+#[derive(Component, Default, Debug)]
+pub struct SpellCaster {
+    pub spell: Spell,
+    /// Seconds until the next cast.
+    cooldown: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct Projectile {
+    pub caster: Entity,
+    lifetime: Timer,
+}
+
+impl Projectile {
+    pub fn new(caster: Entity, spell: &Spell) -> Self {
+        Projectile {
+            caster,
+            lifetime: Timer::from_seconds(spell.lifetime, TimerMode::Once),
+        }
+    }
+}
+
+/// Sent when a projectile runs into something solid, be it a wall or an enemy.
+pub struct SpellHit {
+    pub caster: Entity,
+    pub target: Entity,
+    pub damage: u32,
+}
+
+fn cast_spells(
+    mut commands: Commands,
+    time: Res<Time>,
+    actions: ActionInput,
+    mut caster_q: Query<(Entity, &GlobalTransform, &Children, &mut SpellCaster), With<Player>>,
+    indicator_q: Query<&Transform, With<CursorIndicator>>,
+    cursor: Option<Res<InventoryCursor>>,
+    dialogue: Option<Res<ActiveDialogue>>,
+) {
+    let casting = actions.pressed(Action::Attack) && inventory_closed(cursor) && no_dialogue(dialogue);
+    for (caster, transform, children, mut spell_caster) in caster_q.iter_mut() {
+        spell_caster.cooldown = (spell_caster.cooldown - time.delta_seconds()).max(0.);
+        if !casting || spell_caster.cooldown > 0. { continue; }
+        let Some(aim) = children.iter().find_map(|child| indicator_q.get(*child).ok()) else { continue; };
+        let direction = (aim.rotation * Vec3::X).truncate().normalize_or_zero();
+        let spell = spell_caster.spell;
+        spell_caster.cooldown = spell.cooldown;
+        let position = transform.translation() + (direction * SPAWN_OFFSET).extend(0.);
+        commands.spawn((
+            Projectile::new(caster, &spell),
+            Damage(spell.damage),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: SPELL_COLOR,
+                    custom_size: Some(Vec2::splat(spell.radius * 2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            RigidBody::KinematicVelocityBased,
+            Velocity::linear(direction * spell.speed),
+            Collider::ball(spell.radius),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            // Walls are fixed bodies, which kinematic ones ignore by default.
+            ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+        ));
+    }
+}
+
+fn expire_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut projectile_q: Query<(Entity, &mut Projectile)>,
+) {
+    for (entity, mut projectile) in projectile_q.iter_mut() {
+        if projectile.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn handle_projectile_collisions(
+    mut commands: Commands,
+    mut collision_ev: EventReader<CollisionEvent>,
+    projectile_q: Query<(&Projectile, &Damage)>,
+    solid_q: Query<(), (With<Collider>, Without<Sensor>)>,
+    mut hit_ev: EventWriter<SpellHit>,
+) {
+    let mut spent = HashSet::new();
+    for event in collision_ev.iter() {
+        let CollisionEvent::Started(a, b, _) = *event else { continue; };
+        let (entity, (projectile, damage), target) = match (projectile_q.get(a), projectile_q.get(b)) {
+            (Ok(projectile), _) => (a, projectile, b),
+            (_, Ok(projectile)) => (b, projectile, a),
+            _ => continue,
+        };
+        if target == projectile.caster || !solid_q.contains(target) { continue; }
+        if !spent.insert(entity) { continue; }
+        commands.entity(entity).despawn_recursive();
+        hit_ev.send(SpellHit { caster: projectile.caster, target, damage: damage.0 });
+    }
+}
+
+fn despawn_projectiles(mut commands: Commands, projectile_q: Query<Entity, With<Projectile>>) {
+    for entity in projectile_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod spell_test {
+    use std::f32::consts::FRAC_PI_2;
+    use bevy::input::InputPlugin;
+    use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::test_utils::{enter_game, LoadTestPlugins, update};
+
+    #[test]
+    fn it_casts_along_the_cursor_indicator() {
+        let mut app = setup();
+        spawn_caster(&mut app);
+        press_attack(&mut app);
+        app.update();
+        let velocity = app.world
+            .query_filtered::<&Velocity, With<Projectile>>()
+            .single(&app.world);
+        assert!(velocity.linvel.x.abs() < 0.001);
+        assert!((velocity.linvel.y - Spell::magic_missile().speed).abs() < 0.001);
+    }
+
+    #[test]
+    fn it_waits_for_the_cooldown_between_casts() {
+        let mut app = setup();
+        spawn_caster(&mut app);
+        press_attack(&mut app);
+        update(&mut app, 3);
+        assert_eq!(app.world.query::<&Projectile>().iter(&app.world).len(), 1);
+    }
+
+    #[test]
+    fn it_does_not_cast_without_the_attack_action() {
+        let mut app = setup();
+        spawn_caster(&mut app);
+        update(&mut app, 2);
+        assert_eq!(app.world.query::<&Projectile>().iter(&app.world).len(), 0);
+    }
+
+    #[test]
+    fn it_does_not_cast_while_the_inventory_is_open() {
+        let mut app = setup();
+        spawn_caster(&mut app);
+        app.insert_resource(InventoryCursor { open: true, ..default() });
+        press_attack(&mut app);
+        update(&mut app, 2);
+        assert_eq!(app.world.query::<&Projectile>().iter(&app.world).len(), 0);
+    }
+
+    #[test]
+    fn it_despawns_projectiles_after_their_lifetime() {
+        let mut app = setup();
+        let caster = app.world.spawn_empty().id();
+        let spell = Spell { lifetime: 0., ..Spell::magic_missile() };
+        let projectile = app.world.spawn(Projectile::new(caster, &spell)).id();
+        update(&mut app, 2);
+        assert!(app.world.get_entity(projectile).is_none());
+    }
+
+    #[test]
+    fn it_despawns_on_contact_and_reports_the_hit() {
+        let mut app = setup();
+        let caster = app.world.spawn_empty().id();
+        let projectile = app.world.spawn((Projectile::new(caster, &Spell::magic_missile()), Damage(1))).id();
+        let wall = app.world.spawn((Collider::cuboid(8., 8.), RigidBody::Fixed)).id();
+        app.world.send_event(CollisionEvent::Started(wall, projectile, CollisionEventFlags::SENSOR));
+        app.update();
+        assert!(app.world.get_entity(projectile).is_none());
+        let hits = app.world.resource::<Events<SpellHit>>();
+        let hit = hits.iter_current_update_events().next().unwrap();
+        assert_eq!((hit.caster, hit.target, hit.damage), (caster, wall, 1));
+    }
+
+    #[test]
+    fn it_passes_through_its_caster_and_sensors() {
+        let mut app = setup();
+        let caster = app.world.spawn(Collider::ball(4.)).id();
+        let projectile = app.world.spawn((Projectile::new(caster, &Spell::magic_missile()), Damage(1))).id();
+        let door = app.world.spawn((Collider::cuboid(8., 8.), Sensor)).id();
+        app.world.send_event(CollisionEvent::Started(projectile, caster, CollisionEventFlags::SENSOR));
+        app.world.send_event(CollisionEvent::Started(projectile, door, CollisionEventFlags::SENSOR));
+        app.update();
+        assert!(app.world.get_entity(projectile).is_some());
+    }
+
+    fn spawn_caster(app: &mut App) -> Entity {
+        app.world.spawn((Player, SpellCaster::default(), TransformBundle::default()))
+            .with_children(|parent| {
+                parent.spawn((
+                    CursorIndicator,
+                    TransformBundle::from_transform(Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_2))),
+                ));
+            })
+            .id()
+    }
+
+    fn press_attack(app: &mut App) {
+        app.world.resource_mut::<Input<MouseButton>>().press(MouseButton::Left);
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_state::<GameState>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+            .add_plugin(SpellPlugin);
+        enter_game(&mut app);
+        app
+    }
+}
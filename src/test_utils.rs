@@ -1,12 +1,14 @@
 use bevy::app::{PluginGroup, PluginGroupBuilder, ScheduleRunnerPlugin};
 use bevy::time::TimePlugin;
-use bevy::prelude::{App, FrameCountPlugin, Gamepad, HierarchyPlugin, ImagePlugin, TaskPoolPlugin,
+use bevy::prelude::{App, Entity, FrameCountPlugin, Gamepad, HierarchyPlugin, ImagePlugin, NextState, TaskPoolPlugin,
                     TransformPlugin, TypeRegistrationPlugin, WindowPlugin};
 use bevy::asset::AssetPlugin;
 use bevy::render::RenderPlugin;
 use bevy::core_pipeline::CorePipelinePlugin;
 use bevy::sprite::SpritePlugin;
 use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadInfo};
+use crate::game_state::GameState;
+use crate::health::DamageEvent;
 
 pub struct LoadTestPlugins;
 
@@ -35,7 +37,6 @@ pub fn update(app: &mut App, cycles: usize) {
     }
 }
 
-#[allow(dead_code)]
 pub fn connect_test_gamepad(app: &mut App) {
     app.world.send_event(
         GamepadConnectionEvent::new(
@@ -46,5 +47,13 @@ pub fn connect_test_gamepad(app: &mut App) {
     app.update();
 }
 
+pub fn enter_game(app: &mut App) {
+    app.world.insert_resource(NextState(Some(GameState::InGame)));
+    app.update();
+}
 
-
+/// Hurts `target` the way any in-game hit would, with no knockback.
+pub fn inflict_damage(app: &mut App, target: Entity, amount: u32) {
+    app.world.send_event(DamageEvent { target, amount, source: None });
+    app.update();
+}
@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::cursor_indicator::CursorIndicator;
+use crate::game_logs::{GameLogEvent, GameLogWriter, LogCategory, LogSeverity};
+use crate::game_state::GameState;
+use crate::interaction::{Interaction, InteractionAppExt, InteractionEvent, InteractionSet};
+use crate::inventory::{Inventory, Item, ItemPickedUp, UseItem};
+use crate::localization::Localization;
+use crate::physics_bundle::ObjectPhysicsBundle;
+use crate::spell::SpellCaster;
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ItemPickedUp>()
+            .add_event::<UseItem>()
+            .init_resource::<WeaponSheet>()
+            .register_ldtk_entity::<WeaponBundle>("Weapon")
+            .register_interaction::<Weapon>()
+            .add_system(set_weapon_sprites.in_set(OnUpdate(GameState::InGame)))
+            .add_system(equip_weapons.after(InteractionSet::React).in_set(OnUpdate(GameState::InGame)))
+            .add_system(orient_equipped_weapons.after(equip_weapons).in_set(OnUpdate(GameState::InGame)));
+    }
+}
+
+/// How far from the wielder's centre the equipped weapon is held.
+const HOLD_DISTANCE: f32 = 10.;
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct WeaponBundle {
+    #[from_entity_instance]
+    pub weapon: Weapon,
+    /// Filled in by `set_weapon_sprites` from the [WeaponSheet].
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    #[bundle]
+    pub physics: ObjectPhysicsBundle,
+}
+
+/// Per-weapon stats, set on the LDtk `Weapon` entity.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeaponStats {
+    pub name: String,
+    /// Damage of the spells cast while it is equipped.
+    pub damage: u32,
+    /// Seconds between casts while it is equipped.
+    pub cooldown: f32,
+    /// Slice of the weapon sheet to show.
+    pub sprite: usize,
+}
+
+impl Default for WeaponStats {
+    fn default() -> Self {
+        WeaponStats { name: "Weapon".to_string(), damage: 1, cooldown: 0.4, sprite: 0 }
+    }
+}
+
+#[derive(Component, Default, Debug)]
+pub struct Weapon {
+    stats: WeaponStats,
+    iid: String,
+    taken: bool,
+}
+
+impl Weapon {
+    pub fn new(stats: WeaponStats, iid: &str) -> Self {
+        Weapon { stats, iid: iid.to_string(), taken: false }
+    }
+
+    pub fn stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    /// LDtk entity iid, stable across respawns of the level.
+    pub fn iid(&self) -> &str {
+        &self.iid
+    }
+}
+
+impl Interaction for Weapon {
+    fn interact(&mut self) {
+        self.taken = true;
+    }
+
+    fn prompt(&self, text: &Localization) -> String {
+        text.format("prompt.take_weapon", &[("weapon", &self.stats.name)])
+    }
+}
+
+impl From<&EntityInstance> for Weapon {
+    fn from(value: &EntityInstance) -> Self {
+        let field = |name: &str| value.field_instances.iter()
+            .find(|f| f.identifier == name)
+            .map(|f| &f.value);
+        let mut stats = WeaponStats::default();
+        if let Some(FieldValue::String(Some(name))) = field("name") {
+            stats.name = name.clone();
+        }
+        if let Some(&FieldValue::Int(Some(damage))) = field("damage") {
+            stats.damage = damage.max(0) as u32;
+        }
+        if let Some(&FieldValue::Float(Some(cooldown))) = field("cooldown") {
+            stats.cooldown = cooldown.max(0.);
+        }
+        if let Some(&FieldValue::Int(Some(sprite))) = field("sprite") {
+            stats.sprite = sprite.max(0) as usize;
+        }
+        Weapon::new(stats, &value.iid)
+    }
+}
+
+/// The weapon a player is holding, if any.
+#[derive(Component, Default, Debug)]
+pub struct WeaponSlot(pub Option<WeaponStats>);
+
+/// The weapon sheet with one atlas entry per slice of its Aseprite file.
+#[derive(Resource)]
+pub struct WeaponSheet(Handle<TextureAtlas>);
+
+impl FromWorld for WeaponSheet {
+    fn from_world(world: &mut World) -> Self {
+        WeaponSheet(world.resource::<AssetServer>().load("cementary/Weapons/Cursed Weapons/Cursed Weapons.aseprite#atlas"))
+    }
+}
+
+/// The sprite of the equipped weapon, a child of its wielder.
+#[derive(Component)]
+pub struct EquippedWeapon;
+
+fn set_weapon_sprites(
+    sheet: Res<WeaponSheet>,
+    mut weapon_q: Query<(&Weapon, &mut TextureAtlasSprite, &mut Handle<TextureAtlas>), Added<Weapon>>,
+) {
+    for (weapon, mut sprite, mut atlas) in weapon_q.iter_mut() {
+        *atlas = sheet.0.clone();
+        sprite.index = weapon.stats().sprite;
+    }
+}
+
+/// Equips weapons picked up from the level, storing them in the wielder's [Inventory] if it has one,
+/// and weapons used from an inventory.
+fn equip_weapons(
+    mut commands: Commands,
+    mut interaction_ev: EventReader<InteractionEvent>,
+    mut use_ev: EventReader<UseItem>,
+    mut weapon_q: Query<&mut Weapon>,
+    mut wielder_q: Query<(&mut WeaponSlot, Option<&mut SpellCaster>, Option<&mut Inventory>, Option<&Children>)>,
+    mut equipped_q: Query<&mut TextureAtlasSprite, (With<EquippedWeapon>, Without<Weapon>)>,
+    sheet: Res<WeaponSheet>,
+    mut picked_up_ev: EventWriter<ItemPickedUp>,
+    mut log: GameLogWriter,
+    localization: Res<Localization>,
+) {
+    let mut equips = Vec::new();
+    for event in interaction_ev.iter() {
+        let Ok(mut weapon) = weapon_q.get_mut(event.target) else { continue; };
+        let Ok((_, _, inventory, _)) = wielder_q.get_mut(event.actor) else { continue; };
+        if !weapon.taken { continue; }
+        let stats = weapon.stats().clone();
+        if let Some(mut inventory) = inventory {
+            if inventory.add(Item::Weapon(stats.clone()), 1).is_err() {
+                weapon.taken = false;
+                log.send(GameLogEvent::new(LogCategory::Loot, localization.get("log.inventory_full")).with_severity(LogSeverity::Warning));
+                continue;
+            }
+        }
+        picked_up_ev.send(ItemPickedUp { iid: weapon.iid().to_string() });
+        commands.entity(event.target).despawn_recursive();
+        equips.push((event.actor, stats));
+    }
+    for event in use_ev.iter() {
+        if let Item::Weapon(stats) = &event.item {
+            equips.push((event.owner, stats.clone()));
+        }
+    }
+    for (wielder, stats) in equips {
+        let Ok((mut slot, caster, _, children)) = wielder_q.get_mut(wielder) else { continue; };
+        if let Some(mut caster) = caster {
+            caster.spell.damage = stats.damage;
+            caster.spell.cooldown = stats.cooldown;
+        }
+        let equipped = children.and_then(|children| children.iter().find(|c| equipped_q.contains(**c)));
+        match equipped.and_then(|e| equipped_q.get_mut(*e).ok()) {
+            Some(mut sprite) => sprite.index = stats.sprite,
+            None => {
+                commands.entity(wielder).with_children(|parent| {
+                    parent.spawn((
+                        EquippedWeapon,
+                        SpriteSheetBundle {
+                            texture_atlas: sheet.0.clone(),
+                            sprite: TextureAtlasSprite::new(stats.sprite),
+                            transform: Transform::from_xyz(HOLD_DISTANCE, 0., 0.1),
+                            ..default()
+                        },
+                    ));
+                });
+            }
+        }
+        log.send(GameLogEvent::from_template(LogCategory::Loot, localization.get("log.weapon_equipped"), &[("weapon", &stats.name)]));
+        slot.0 = Some(stats);
+    }
+}
+
+/// Points equipped weapons the same way as their wielder's [CursorIndicator].
+fn orient_equipped_weapons(
+    indicator_q: Query<(&Parent, &Transform), (With<CursorIndicator>, Without<EquippedWeapon>)>,
+    mut weapon_q: Query<(&Parent, &mut Transform), With<EquippedWeapon>>,
+) {
+    for (wielder, mut transform) in weapon_q.iter_mut() {
+        let Some((_, aim)) = indicator_q.iter().find(|(parent, _)| parent.get() == wielder.get()) else { continue; };
+        let direction = (aim.rotation * Vec3::X).truncate();
+        transform.translation = (direction * HOLD_DISTANCE).extend(transform.translation.z);
+        // The sheet draws weapons pointing up.
+        transform.rotation = aim.rotation * Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2);
+    }
+}
+
+#[cfg(test)]
+mod weapon_test {
+    use std::f32::consts::FRAC_PI_2;
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy_rapier2d::prelude::{CollisionEvent, NoUserData, RapierPhysicsPlugin};
+    use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
+    use super::*;
+    use crate::actions::{ActionPlugin, SettingsPath};
+    use crate::aseprite::AsepritePlugin;
+    use crate::interaction::{InteractionPlugin, Interactor};
+    use crate::level::LevelPlugin;
+    use crate::localization::LocalizationPlugin;
+    use crate::test_utils::{enter_game, LoadTestPlugins, update};
+
+    #[test]
+    fn it_reads_weapon_stats_from_ldtk() {
+        let mut app = setup();
+        app.insert_resource(LevelSelection::Index(1));
+        update(&mut app, 3);
+        let weapon = app.world.query::<&Weapon>().single(&app.world);
+        assert_eq!(weapon.stats(), &WeaponStats {
+            name: "Cursed Sword".to_string(),
+            damage: 2,
+            cooldown: 0.3,
+            sprite: 0,
+        });
+        let atlas = app.world.query_filtered::<&Handle<TextureAtlas>, With<Weapon>>().single(&app.world);
+        assert_eq!(atlas, &app.world.resource::<WeaponSheet>().0);
+    }
+
+    #[test]
+    fn it_equips_picked_up_weapons() {
+        let mut app = setup();
+        let player = spawn_wielder(&mut app);
+        let weapon = app.world.spawn((Weapon::new(axe(), "weapon-iid"), SpriteSheetBundle::default())).id();
+        pick_up(&mut app, player, weapon);
+        assert_eq!(app.world.get::<WeaponSlot>(player).unwrap().0, Some(axe()));
+        assert!(app.world.get_entity(weapon).is_none());
+        let spell = app.world.get::<SpellCaster>(player).unwrap().spell;
+        assert_eq!((spell.damage, spell.cooldown), (3, 0.8));
+        let sprite = app.world
+            .query_filtered::<&TextureAtlasSprite, With<EquippedWeapon>>()
+            .single(&app.world);
+        assert_eq!(sprite.index, 2);
+        assert_eq!(app.world.get::<Inventory>(player).unwrap().count(&Item::Weapon(axe())), 1);
+    }
+
+    #[test]
+    fn it_equips_weapons_used_from_the_inventory() {
+        let mut app = setup();
+        let player = spawn_wielder(&mut app);
+        app.world.send_event(UseItem { owner: player, item: Item::Weapon(axe()) });
+        app.update();
+        assert_eq!(app.world.get::<WeaponSlot>(player).unwrap().0, Some(axe()));
+        let spell = app.world.get::<SpellCaster>(player).unwrap().spell;
+        assert_eq!((spell.damage, spell.cooldown), (3, 0.8));
+    }
+
+    #[test]
+    fn it_points_the_equipped_weapon_along_the_cursor_indicator() {
+        let mut app = setup();
+        let player = spawn_wielder(&mut app);
+        let weapon = app.world.spawn((Weapon::new(axe(), "weapon-iid"), SpriteSheetBundle::default())).id();
+        pick_up(&mut app, player, weapon);
+        app.update();
+        let transform = app.world
+            .query_filtered::<&Transform, With<EquippedWeapon>>()
+            .single(&app.world);
+        assert!(transform.translation.x.abs() < 0.001);
+        assert!((transform.translation.y - HOLD_DISTANCE).abs() < 0.001);
+    }
+
+    fn axe() -> WeaponStats {
+        WeaponStats { name: "Axe".to_string(), damage: 3, cooldown: 0.8, sprite: 2 }
+    }
+
+    fn spawn_wielder(app: &mut App) -> Entity {
+        app.world
+            .spawn((Interactor, WeaponSlot::default(), SpellCaster::default(), Inventory::default(), TransformBundle::default()))
+            .with_children(|parent| {
+                parent.spawn((
+                    CursorIndicator,
+                    TransformBundle::from_transform(Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_2))),
+                ));
+            })
+            .id()
+    }
+
+    fn pick_up(app: &mut App, player: Entity, weapon: Entity) {
+        app.world.send_event(CollisionEvent::Started(player, weapon, CollisionEventFlags::SENSOR));
+        app.update();
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Option::from(KeyCode::E),
+            state: ButtonState::Pressed,
+        });
+        app.update();
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(LoadTestPlugins)
+            .add_plugin(LocalizationPlugin)
+            .insert_resource(Localization::shipped())
+            .add_state::<GameState>()
+            .add_event::<GameLogEvent>()
+            .add_plugin(InputPlugin)
+            .add_plugin(ActionPlugin)
+            .insert_resource(SettingsPath::temporary())
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+            .add_plugin(InteractionPlugin)
+            .add_plugin(LevelPlugin)
+            .add_plugin(AsepritePlugin)
+            .add_plugin(WeaponPlugin);
+        enter_game(&mut app);
+        app
+    }
+}